@@ -145,6 +145,15 @@ impl Widget for BatteryWidget {
         18
     }
 
+    fn next_update(&self) -> Option<Duration> {
+        // Keep the frame loop running while the charging/low effect plays
+        if self.effect.lock().unwrap().is_some() {
+            Some(Duration::ZERO)
+        } else {
+            Some(Duration::from_secs(5).saturating_sub(self.last_update))
+        }
+    }
+
     fn update(&mut self, dt: Duration, _state: &BarState) {
         self.last_update += dt;
         if self.last_update > Duration::from_secs(5) {
@@ -173,23 +182,10 @@ impl Widget for BatteryWidget {
         let bg_color = Some(state.config_ink.resolve_bg("bg"));
         let accent_color = Some(state.config_ink.resolve_color("accent"));
 
-        let base_config = state
-            .config
-            .widget
-            .get("battery")
-            .and_then(|v| v.as_table());
-        let instance_config = if let Some(alias) = &self.instance_name {
-            base_config
-                .and_then(|t| t.get(alias))
-                .and_then(|v| v.as_table())
-        } else {
-            None
-        };
-
         let resolve_override = |key: &str, fallback: Option<Color>| -> Option<Color> {
-            instance_config
-                .and_then(|t| t.get(key))
-                .or_else(|| base_config.and_then(|t| t.get(key)))
+            state
+                .config
+                .widget_option("battery", self.instance_name.as_deref(), key)
                 .and_then(|v| v.as_str())
                 .map(|s| {
                     let c = ColorResolver::hex_to_color(s);
@@ -349,7 +345,4 @@ impl Widget for BatteryWidget {
     }
 }
 
-#[unsafe(no_mangle)]
-pub extern "Rust" fn _create_widget() -> Box<dyn Widget> {
-    Box::new(BatteryWidget::new())
-}
+export_widget!(BatteryWidget::new());
@@ -41,6 +41,11 @@ impl Widget for InteractionDemoWidget {
         25
     }
 
+    fn next_update(&self) -> Option<Duration> {
+        // Only changes in response to pointer events
+        None
+    }
+
     fn handle_event(&mut self, event: WidgetEvent) {
         let mut state = self.state.lock().unwrap();
 
@@ -67,6 +72,10 @@ impl Widget for InteractionDemoWidget {
             WidgetEvent::Motion { .. } => {
                 // Could track position if needed
             }
+            WidgetEvent::PopupDismissed => {
+                // Closed by a click outside; the next click opens it again
+                state.show_popup = false;
+            }
         }
     }
 
@@ -150,12 +159,4 @@ impl Widget for InteractionDemoWidget {
     }
 }
 
-#[unsafe(no_mangle)]
-pub extern "Rust" fn _create_widget() -> Box<dyn Widget> {
-    Box::new(InteractionDemoWidget::new())
-}
-
-#[unsafe(no_mangle)]
-pub extern "C" fn _has_popup() -> bool {
-    true
-}
+export_widget!(InteractionDemoWidget::new());
@@ -20,94 +20,34 @@ impl TraySpaceWidget {
     }
 
     fn icon_size(&self, state: &BarState) -> u16 {
-        let base_config = state
+        state
             .config
-            .widget
-            .get("tray_space")
-            .and_then(|v| v.as_table());
-
-        if let Some(alias) = &self.instance_name {
-            if let Some(size) = base_config
-                .and_then(|t| t.get(alias))
-                .and_then(|v| v.get("icon_size"))
-                .and_then(|v| v.as_integer())
-            {
-                return size as u16;
-            }
-        }
-
-        base_config
-            .and_then(|t| t.get("icon_size"))
+            .widget_option("tray_space", self.instance_name.as_deref(), "icon_size")
             .and_then(|v| v.as_integer())
             .unwrap_or(2) as u16
     }
 
     fn max_icons(&self, state: &BarState) -> usize {
-        let base_config = state
+        state
             .config
-            .widget
-            .get("tray_space")
-            .and_then(|v| v.as_table());
-
-        if let Some(alias) = &self.instance_name {
-            if let Some(count) = base_config
-                .and_then(|t| t.get(alias))
-                .and_then(|v| v.get("max_icons"))
-                .and_then(|v| v.as_integer())
-            {
-                return count as usize;
-            }
-        }
-
-        base_config
-            .and_then(|t| t.get("max_icons"))
+            .widget_option("tray_space", self.instance_name.as_deref(), "max_icons")
             .and_then(|v| v.as_integer())
             .unwrap_or(5) as usize
     }
 
     fn separator(&self, state: &BarState) -> String {
-        let base_config = state
+        state
             .config
-            .widget
-            .get("tray_space")
-            .and_then(|v| v.as_table());
-
-        if let Some(alias) = &self.instance_name {
-            if let Some(sep) = base_config
-                .and_then(|t| t.get(alias))
-                .and_then(|v| v.get("separator"))
-                .and_then(|v| v.as_str())
-            {
-                return sep.to_string();
-            }
-        }
-
-        base_config
-            .and_then(|t| t.get("separator"))
+            .widget_option("tray_space", self.instance_name.as_deref(), "separator")
             .and_then(|v| v.as_str())
             .unwrap_or(" ")
             .to_string()
     }
 
     fn show_placeholder(&self, state: &BarState) -> bool {
-        let base_config = state
+        state
             .config
-            .widget
-            .get("tray_space")
-            .and_then(|v| v.as_table());
-
-        if let Some(alias) = &self.instance_name {
-            if let Some(show) = base_config
-                .and_then(|t| t.get(alias))
-                .and_then(|v| v.get("show_placeholder"))
-                .and_then(|v| v.as_bool())
-            {
-                return show;
-            }
-        }
-
-        base_config
-            .and_then(|t| t.get("show_placeholder"))
+            .widget_option("tray_space", self.instance_name.as_deref(), "show_placeholder")
             .and_then(|v| v.as_bool())
             .unwrap_or(true)
     }
@@ -137,6 +77,10 @@ impl Widget for TraySpaceWidget {
         base_width + separator_width
     }
 
+    fn next_update(&self) -> Option<Duration> {
+        None
+    }
+
     fn update(&mut self, _dt: Duration, _state: &BarState) {}
 
     fn render(&mut self, area: Rect, buf: &mut Buffer, state: &BarState, _dt: Duration) {
@@ -178,7 +122,4 @@ impl Widget for TraySpaceWidget {
     }
 }
 
-#[unsafe(no_mangle)]
-pub extern "Rust" fn _create_widget() -> Box<dyn Widget> {
-    Box::new(TraySpaceWidget::new())
-}
+export_widget!(TraySpaceWidget::new());
@@ -28,6 +28,11 @@ impl Widget for UiKitDemoWidget {
         20
     }
 
+    fn next_update(&self) -> Option<Duration> {
+        // Breathing effect loops forever
+        Some(Duration::ZERO)
+    }
+
     fn update(&mut self, _dt: Duration, state: &BarState) {
         let mut effect_lock = self.effect.lock().unwrap();
         if effect_lock.is_none() {
@@ -60,7 +65,4 @@ impl Widget for UiKitDemoWidget {
     }
 }
 
-#[unsafe(no_mangle)]
-pub extern "Rust" fn _create_widget() -> Box<dyn Widget> {
-    Box::new(UiKitDemoWidget::new())
-}
+export_widget!(UiKitDemoWidget::new());
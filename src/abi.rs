@@ -0,0 +1,801 @@
+//! Stable C ABI between the bar and widget plugins.
+//!
+//! Plugins link their own copy of `hyprbar`, possibly built with a different compiler,
+//! so nothing Rust-specific crosses the `.so` boundary. The host only calls the
+//! `extern "C"` functions of a `#[repr(C)]` [`WidgetVTable`], exchanges buffer contents
+//! as [`AbiCell`] arrays and passes the bar config as JSON.
+//!
+//! Plugins export the table with [`export_widget!`](crate::export_widget), which also
+//! exports `_hyprbar_abi_version`. The loader refuses plugins whose version differs
+//! from [`ABI_VERSION`].
+//!
+//! A plugin builds its own [`BarState`](crate::state::BarState) from the config, the
+//! host's theme colors and the messages on the host's event bus, which are replayed on
+//! the plugin's bus before each call. Messages a plugin sends stay on its own bus.
+
+use crate::bus::BusMessage;
+use crate::event::WidgetEvent;
+use crate::widget::PopupRequest;
+use ratatui::buffer::{Buffer, Cell};
+use ratatui::layout::Rect;
+use ratatui::style::{Color, Modifier};
+use std::ffi::c_void;
+use std::time::Duration;
+
+/// Bump on every incompatible change to the types in this module.
+pub const ABI_VERSION: u32 = 4;
+
+pub const ABI_VERSION_SYMBOL: &[u8] = b"_hyprbar_abi_version";
+pub const VTABLE_SYMBOL: &[u8] = b"_hyprbar_widget_vtable";
+
+/// Longest grapheme (in UTF-8 bytes) a cell can carry across the boundary.
+pub const ABI_SYMBOL_LEN: usize = 32;
+
+/// Sentinel for [`WidgetVTable::next_update`] meaning "no scheduled update".
+pub const NO_UPDATE: u64 = u64::MAX;
+
+/// Opaque pointer to a plugin-side widget instance.
+pub type WidgetHandle = *mut c_void;
+
+/// Borrowed UTF-8 string.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct AbiStr {
+    pub ptr: *const u8,
+    pub len: usize,
+}
+
+impl Default for AbiStr {
+    fn default() -> Self {
+        Self {
+            ptr: std::ptr::null(),
+            len: 0,
+        }
+    }
+}
+
+impl AbiStr {
+    pub fn new(s: &str) -> Self {
+        Self {
+            ptr: s.as_ptr(),
+            len: s.len(),
+        }
+    }
+
+    /// # Safety
+    /// `ptr` must point to `len` readable bytes that outlive `'a`.
+    pub unsafe fn as_str<'a>(&self) -> &'a str {
+        if self.ptr.is_null() {
+            return "";
+        }
+        let bytes = unsafe { std::slice::from_raw_parts(self.ptr, self.len) };
+        std::str::from_utf8(bytes).unwrap_or_default()
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct AbiRect {
+    pub x: u16,
+    pub y: u16,
+    pub width: u16,
+    pub height: u16,
+}
+
+impl From<Rect> for AbiRect {
+    fn from(r: Rect) -> Self {
+        Self {
+            x: r.x,
+            y: r.y,
+            width: r.width,
+            height: r.height,
+        }
+    }
+}
+
+impl From<AbiRect> for Rect {
+    fn from(r: AbiRect) -> Self {
+        Rect::new(r.x, r.y, r.width, r.height)
+    }
+}
+
+const COLOR_RESET: u8 = 0;
+const COLOR_NAMED: u8 = 1;
+const COLOR_INDEXED: u8 = 2;
+const COLOR_RGB: u8 = 3;
+
+const NAMED_COLORS: [Color; 16] = [
+    Color::Black,
+    Color::Red,
+    Color::Green,
+    Color::Yellow,
+    Color::Blue,
+    Color::Magenta,
+    Color::Cyan,
+    Color::Gray,
+    Color::DarkGray,
+    Color::LightRed,
+    Color::LightGreen,
+    Color::LightYellow,
+    Color::LightBlue,
+    Color::LightMagenta,
+    Color::LightCyan,
+    Color::White,
+];
+
+/// `kind` selects the interpretation: reset, named (`r` = ANSI index),
+/// indexed (`r` = palette index) or RGB.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AbiColor {
+    pub kind: u8,
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl From<Color> for AbiColor {
+    fn from(c: Color) -> Self {
+        let (kind, r, g, b) = match c {
+            Color::Reset => (COLOR_RESET, 0, 0, 0),
+            Color::Rgb(r, g, b) => (COLOR_RGB, r, g, b),
+            Color::Indexed(i) => (COLOR_INDEXED, i, 0, 0),
+            named => {
+                let idx = NAMED_COLORS.iter().position(|n| *n == named).unwrap_or(0);
+                (COLOR_NAMED, idx as u8, 0, 0)
+            }
+        };
+        Self { kind, r, g, b }
+    }
+}
+
+impl From<AbiColor> for Color {
+    fn from(c: AbiColor) -> Self {
+        match c.kind {
+            COLOR_RGB => Color::Rgb(c.r, c.g, c.b),
+            COLOR_INDEXED => Color::Indexed(c.r),
+            COLOR_NAMED => NAMED_COLORS
+                .get(c.r as usize)
+                .copied()
+                .unwrap_or(Color::Reset),
+            _ => Color::Reset,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct AbiCell {
+    pub symbol: [u8; ABI_SYMBOL_LEN],
+    pub symbol_len: u8,
+    pub fg: AbiColor,
+    pub bg: AbiColor,
+    pub modifier: u16,
+}
+
+impl From<&Cell> for AbiCell {
+    fn from(cell: &Cell) -> Self {
+        let mut symbol = [0u8; ABI_SYMBOL_LEN];
+        let mut s = cell.symbol();
+        if s.len() > ABI_SYMBOL_LEN {
+            s = " ";
+        }
+        symbol[..s.len()].copy_from_slice(s.as_bytes());
+        Self {
+            symbol,
+            symbol_len: s.len() as u8,
+            fg: cell.fg.into(),
+            bg: cell.bg.into(),
+            modifier: cell.modifier.bits(),
+        }
+    }
+}
+
+impl AbiCell {
+    pub fn symbol(&self) -> &str {
+        let len = (self.symbol_len as usize).min(ABI_SYMBOL_LEN);
+        std::str::from_utf8(&self.symbol[..len]).unwrap_or(" ")
+    }
+
+    pub fn apply_to(&self, cell: &mut Cell) {
+        cell.set_symbol(self.symbol());
+        cell.fg = self.fg.into();
+        cell.bg = self.bg.into();
+        cell.modifier = Modifier::from_bits_truncate(self.modifier);
+    }
+}
+
+/// Copies the cells of `area` out of `buf`, row by row.
+pub fn read_cells(buf: &Buffer, area: Rect) -> Vec<AbiCell> {
+    let mut cells = Vec::with_capacity(area.width as usize * area.height as usize);
+    for y in area.top()..area.bottom() {
+        for x in area.left()..area.right() {
+            let cell = buf.cell((x, y)).cloned().unwrap_or_default();
+            cells.push(AbiCell::from(&cell));
+        }
+    }
+    cells
+}
+
+/// Writes cells produced by [`read_cells`] back into `area` of `buf`.
+pub fn write_cells(buf: &mut Buffer, area: Rect, cells: &[AbiCell]) {
+    let mut iter = cells.iter();
+    for y in area.top()..area.bottom() {
+        for x in area.left()..area.right() {
+            let Some(abi) = iter.next() else {
+                return;
+            };
+            if let Some(cell) = buf.cell_mut((x, y)) {
+                abi.apply_to(cell);
+            }
+        }
+    }
+}
+
+const EVENT_ENTER: u32 = 0;
+const EVENT_LEAVE: u32 = 1;
+const EVENT_MOTION: u32 = 2;
+const EVENT_CLICK: u32 = 3;
+const EVENT_SCROLL: u32 = 4;
+const EVENT_POPUP_DISMISSED: u32 = 5;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AbiEvent {
+    pub kind: u32,
+    pub button: u32,
+    pub x: u16,
+    pub y: u16,
+    pub dx: f64,
+    pub dy: f64,
+}
+
+impl From<WidgetEvent> for AbiEvent {
+    fn from(event: WidgetEvent) -> Self {
+        let mut abi = Self {
+            kind: EVENT_ENTER,
+            button: 0,
+            x: 0,
+            y: 0,
+            dx: 0.0,
+            dy: 0.0,
+        };
+        match event {
+            WidgetEvent::Enter => abi.kind = EVENT_ENTER,
+            WidgetEvent::Leave => abi.kind = EVENT_LEAVE,
+            WidgetEvent::Motion { x, y } => {
+                abi.kind = EVENT_MOTION;
+                abi.x = x;
+                abi.y = y;
+            }
+            WidgetEvent::Click { button, x, y } => {
+                abi.kind = EVENT_CLICK;
+                abi.button = button;
+                abi.x = x;
+                abi.y = y;
+            }
+            WidgetEvent::Scroll { dx, dy } => {
+                abi.kind = EVENT_SCROLL;
+                abi.dx = dx;
+                abi.dy = dy;
+            }
+            WidgetEvent::PopupDismissed => abi.kind = EVENT_POPUP_DISMISSED,
+        }
+        abi
+    }
+}
+
+impl AbiEvent {
+    pub fn to_event(self) -> Option<WidgetEvent> {
+        Some(match self.kind {
+            EVENT_ENTER => WidgetEvent::Enter,
+            EVENT_LEAVE => WidgetEvent::Leave,
+            EVENT_MOTION => WidgetEvent::Motion {
+                x: self.x,
+                y: self.y,
+            },
+            EVENT_CLICK => WidgetEvent::Click {
+                button: self.button,
+                x: self.x,
+                y: self.y,
+            },
+            EVENT_SCROLL => WidgetEvent::Scroll {
+                dx: self.dx,
+                dy: self.dy,
+            },
+            EVENT_POPUP_DISMISSED => WidgetEvent::PopupDismissed,
+            _ => return None,
+        })
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AbiPopupRequest {
+    pub width: u16,
+    pub height: u16,
+    pub offset_x: i16,
+    pub offset_y: i16,
+    pub persist: bool,
+}
+
+impl From<PopupRequest> for AbiPopupRequest {
+    fn from(r: PopupRequest) -> Self {
+        Self {
+            width: r.width,
+            height: r.height,
+            offset_x: r.offset_x,
+            offset_y: r.offset_y,
+            persist: r.persist,
+        }
+    }
+}
+
+impl From<AbiPopupRequest> for PopupRequest {
+    fn from(r: AbiPopupRequest) -> Self {
+        Self {
+            width: r.width,
+            height: r.height,
+            offset_x: r.offset_x,
+            offset_y: r.offset_y,
+            persist: r.persist,
+        }
+    }
+}
+
+const MESSAGE_SIGNAL: u32 = 0;
+const MESSAGE_DATA: u32 = 1;
+const MESSAGE_BYTES: u32 = 2;
+
+/// A borrowed [`BusMessage`]. `data` is empty for signals and UTF-8 for data payloads.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct AbiBusMessage {
+    pub kind: u32,
+    pub topic: AbiStr,
+    pub data: *const u8,
+    pub data_len: usize,
+}
+
+impl From<&BusMessage> for AbiBusMessage {
+    fn from(msg: &BusMessage) -> Self {
+        let (kind, topic, data): (_, _, &[u8]) = match msg {
+            BusMessage::Signal(s) => (MESSAGE_SIGNAL, s, &[]),
+            BusMessage::Data(k, v) => (MESSAGE_DATA, k, v.as_bytes()),
+            BusMessage::Bytes(k, v) => (MESSAGE_BYTES, k, v),
+        };
+        Self {
+            kind,
+            topic: AbiStr::new(topic),
+            data: data.as_ptr(),
+            data_len: data.len(),
+        }
+    }
+}
+
+impl AbiBusMessage {
+    /// # Safety
+    /// `topic` and `data` must point to live memory of their stated lengths.
+    pub unsafe fn to_message(&self) -> Option<BusMessage> {
+        let topic = unsafe { self.topic.as_str() }.to_string();
+        let data = if self.data.is_null() {
+            &[][..]
+        } else {
+            unsafe { std::slice::from_raw_parts(self.data, self.data_len) }
+        };
+        Some(match self.kind {
+            MESSAGE_SIGNAL => BusMessage::Signal(topic),
+            MESSAGE_DATA => BusMessage::Data(topic, String::from_utf8_lossy(data).into_owned()),
+            MESSAGE_BYTES => BusMessage::Bytes(topic, data.to_vec()),
+            _ => return None,
+        })
+    }
+}
+
+/// Bar state as seen by a plugin. `config_json` is the serialized `BarConfig`;
+/// plugins only re-parse it when `revision` changes. `theme_json` holds the host's
+/// theme colors and `messages` the host bus messages since the previous call.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct AbiState {
+    pub revision: u64,
+    pub config_json: AbiStr,
+    pub theme_json: AbiStr,
+    pub messages: *const AbiBusMessage,
+    pub message_count: usize,
+}
+
+impl AbiState {
+    /// # Safety
+    /// `messages` must point to `message_count` valid messages.
+    pub unsafe fn messages(&self) -> &[AbiBusMessage] {
+        if self.messages.is_null() {
+            return &[];
+        }
+        unsafe { std::slice::from_raw_parts(self.messages, self.message_count) }
+    }
+}
+
+/// Function table exported by every plugin. Durations are in microseconds.
+///
+/// Panics never unwind across the boundary: the plugin catches them, returns a default
+/// value and reports them through `last_panic` until the next call on that instance.
+#[repr(C)]
+pub struct WidgetVTable {
+    pub abi_version: u32,
+    pub create: unsafe extern "C" fn() -> WidgetHandle,
+    pub destroy: unsafe extern "C" fn(WidgetHandle),
+    pub last_panic: unsafe extern "C" fn(WidgetHandle) -> AbiStr,
+    pub name: unsafe extern "C" fn(WidgetHandle) -> AbiStr,
+    pub width: unsafe extern "C" fn(WidgetHandle, *const AbiState) -> u16,
+    pub update: unsafe extern "C" fn(WidgetHandle, u64, *const AbiState),
+    pub render:
+        unsafe extern "C" fn(WidgetHandle, AbiRect, *mut AbiCell, usize, *const AbiState, u64),
+    pub set_instance_config: unsafe extern "C" fn(WidgetHandle, AbiStr),
+    pub handle_event: unsafe extern "C" fn(WidgetHandle, AbiEvent),
+    pub next_update: unsafe extern "C" fn(WidgetHandle) -> u64,
+    pub needs_redraw: unsafe extern "C" fn(WidgetHandle) -> bool,
+    pub popup_request: unsafe extern "C" fn(WidgetHandle, *mut AbiPopupRequest) -> bool,
+    pub render_popup:
+        unsafe extern "C" fn(WidgetHandle, AbiRect, *mut AbiCell, usize, *const AbiState),
+    pub handle_popup_event: unsafe extern "C" fn(WidgetHandle, AbiEvent),
+}
+
+pub fn duration_to_micros(d: Duration) -> u64 {
+    u64::try_from(d.as_micros()).unwrap_or(NO_UPDATE - 1)
+}
+
+/// Plugin-side implementation of the vtable, used by [`export_widget!`](crate::export_widget).
+#[doc(hidden)]
+pub mod glue {
+    use super::*;
+    use crate::config::BarConfig;
+    use crate::isolation::catch_panic;
+    use crate::state::BarState;
+    use crate::widget::Widget;
+    use hyprink::config::Config;
+    use std::sync::{Arc, Mutex};
+
+    struct PluginInstance {
+        widget: Box<dyn Widget>,
+        /// State built from the host's, keyed by config revision.
+        state: Option<(u64, BarState)>,
+        /// Report of a panic during the most recent call, handed to the host via `last_panic`.
+        panic: Option<String>,
+    }
+
+    impl PluginInstance {
+        /// Runs a widget call, turning a panic into `default` plus a report for the host.
+        /// Unwinding must not cross the `extern "C"` boundary.
+        fn guard<R>(&mut self, default: R, f: impl FnOnce(&mut Self) -> R) -> R {
+            self.panic = None;
+            match catch_panic(|| f(self)) {
+                Ok(r) => r,
+                Err(report) => {
+                    self.panic = Some(report);
+                    default
+                }
+            }
+        }
+    }
+
+    /// The hyprink config with the host's theme colors, shared by the plugin's instances.
+    /// It is only loaded again when the host's theme changes; without it the plugin's
+    /// widgets render nothing.
+    fn config_ink(theme_json: &str) -> Option<Arc<Config>> {
+        static CONFIG_INK: Mutex<Option<(String, Option<Arc<Config>>)>> = Mutex::new(None);
+        let mut cache = CONFIG_INK.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((theme, config)) = cache.as_ref()
+            && theme == theme_json
+        {
+            return config.clone();
+        }
+        let config = match Config::load() {
+            Ok(mut config) => {
+                if let Ok(colors) = serde_json::from_str(theme_json) {
+                    config.theme.colors = colors;
+                }
+                Some(Arc::new(config))
+            }
+            Err(e) => {
+                hyprlog::internal::warn(
+                    "PLUGIN",
+                    &format!("Cannot load the theme, plugin widgets stay empty: {}", e),
+                );
+                None
+            }
+        };
+        *cache = Some((theme_json.to_string(), config.clone()));
+        config
+    }
+
+    fn sync_state(cache: &mut Option<(u64, BarState)>, abi: *const AbiState) -> Option<&BarState> {
+        let abi = unsafe { abi.as_ref()? };
+        let config_ink = config_ink(unsafe { abi.theme_json.as_str() })?;
+        let stale = cache.as_ref().is_none_or(|(rev, _)| *rev != abi.revision);
+        if stale {
+            let json = unsafe { abi.config_json.as_str() };
+            if let Ok(config) = serde_json::from_str::<BarConfig>(json) {
+                let state = match cache.take() {
+                    Some((_, mut state)) => {
+                        state.config = config;
+                        state
+                    }
+                    None => BarState::new(config_ink.clone(), config),
+                };
+                *cache = Some((abi.revision, state));
+            }
+        }
+        let (_, state) = cache.as_mut()?;
+        state.config_ink = config_ink;
+        for msg in unsafe { abi.messages() } {
+            if let Some(msg) = unsafe { msg.to_message() } {
+                state.bus.send(msg);
+            }
+        }
+        Some(state)
+    }
+
+    unsafe fn instance<'a>(handle: WidgetHandle) -> &'a mut PluginInstance {
+        unsafe { &mut *(handle as *mut PluginInstance) }
+    }
+
+    /// Constructs the widget; a panicking constructor yields a null handle.
+    pub fn create(ctor: impl FnOnce() -> Box<dyn Widget>) -> WidgetHandle {
+        match catch_panic(ctor) {
+            Ok(widget) => Box::into_raw(Box::new(PluginInstance {
+                widget,
+                state: None,
+                panic: None,
+            })) as WidgetHandle,
+            Err(_) => std::ptr::null_mut(),
+        }
+    }
+
+    unsafe extern "C" fn destroy(handle: WidgetHandle) {
+        if !handle.is_null() {
+            let inst = unsafe { Box::from_raw(handle as *mut PluginInstance) };
+            let _ = catch_panic(move || drop(inst));
+        }
+    }
+
+    unsafe extern "C" fn last_panic(handle: WidgetHandle) -> AbiStr {
+        match &unsafe { instance(handle) }.panic {
+            Some(report) => AbiStr::new(report),
+            None => AbiStr::default(),
+        }
+    }
+
+    unsafe extern "C" fn name(handle: WidgetHandle) -> AbiStr {
+        let inst = unsafe { instance(handle) };
+        inst.guard(AbiStr::default(), |inst| AbiStr::new(inst.widget.name()))
+    }
+
+    unsafe extern "C" fn width(handle: WidgetHandle, state: *const AbiState) -> u16 {
+        unsafe { instance(handle) }.guard(0, |inst| match sync_state(&mut inst.state, state) {
+            Some(state) => inst.widget.width(state),
+            None => 0,
+        })
+    }
+
+    unsafe extern "C" fn update(handle: WidgetHandle, dt_us: u64, state: *const AbiState) {
+        unsafe { instance(handle) }.guard((), |inst| {
+            if let Some(state) = sync_state(&mut inst.state, state) {
+                inst.widget.update(Duration::from_micros(dt_us), state);
+            }
+        })
+    }
+
+    unsafe fn with_cells(
+        area: AbiRect,
+        cells: *mut AbiCell,
+        len: usize,
+        draw: impl FnOnce(Rect, &mut Buffer),
+    ) {
+        let area = Rect::from(area);
+        if cells.is_null() || len != area.width as usize * area.height as usize {
+            return;
+        }
+        let cells = unsafe { std::slice::from_raw_parts_mut(cells, len) };
+        let mut buf = Buffer::empty(area);
+        write_cells(&mut buf, area, cells);
+        draw(area, &mut buf);
+        cells.copy_from_slice(&read_cells(&buf, area));
+    }
+
+    unsafe extern "C" fn render(
+        handle: WidgetHandle,
+        area: AbiRect,
+        cells: *mut AbiCell,
+        len: usize,
+        state: *const AbiState,
+        dt_us: u64,
+    ) {
+        unsafe { instance(handle) }.guard((), |inst| {
+            let Some(state) = sync_state(&mut inst.state, state) else {
+                return;
+            };
+            let widget = &mut inst.widget;
+            unsafe {
+                with_cells(area, cells, len, |area, buf| {
+                    widget.render(area, buf, state, Duration::from_micros(dt_us))
+                })
+            };
+        })
+    }
+
+    unsafe extern "C" fn set_instance_config(handle: WidgetHandle, name: AbiStr) {
+        let name = unsafe { name.as_str() }.to_string();
+        unsafe { instance(handle) }.guard((), |inst| inst.widget.set_instance_config(name))
+    }
+
+    unsafe extern "C" fn handle_event(handle: WidgetHandle, event: AbiEvent) {
+        unsafe { instance(handle) }.guard((), |inst| {
+            if let Some(event) = event.to_event() {
+                inst.widget.handle_event(event);
+            }
+        })
+    }
+
+    unsafe extern "C" fn next_update(handle: WidgetHandle) -> u64 {
+        unsafe { instance(handle) }.guard(NO_UPDATE, |inst| {
+            inst.widget
+                .next_update()
+                .map_or(NO_UPDATE, duration_to_micros)
+        })
+    }
+
+    unsafe extern "C" fn needs_redraw(handle: WidgetHandle) -> bool {
+        unsafe { instance(handle) }.guard(false, |inst| inst.widget.needs_redraw())
+    }
+
+    unsafe extern "C" fn popup_request(handle: WidgetHandle, out: *mut AbiPopupRequest) -> bool {
+        unsafe { instance(handle) }.guard(false, |inst| match inst.widget.popup_request() {
+            Some(request) if !out.is_null() => {
+                unsafe { *out = request.into() };
+                true
+            }
+            _ => false,
+        })
+    }
+
+    unsafe extern "C" fn render_popup(
+        handle: WidgetHandle,
+        area: AbiRect,
+        cells: *mut AbiCell,
+        len: usize,
+        state: *const AbiState,
+    ) {
+        unsafe { instance(handle) }.guard((), |inst| {
+            let Some(state) = sync_state(&mut inst.state, state) else {
+                return;
+            };
+            let widget = &mut inst.widget;
+            unsafe {
+                with_cells(area, cells, len, |area, buf| {
+                    widget.render_popup(area, buf, state)
+                })
+            };
+        })
+    }
+
+    unsafe extern "C" fn handle_popup_event(handle: WidgetHandle, event: AbiEvent) {
+        unsafe { instance(handle) }.guard((), |inst| {
+            if let Some(event) = event.to_event() {
+                inst.widget.handle_popup_event(event);
+            }
+        })
+    }
+
+    pub const fn vtable(create: unsafe extern "C" fn() -> WidgetHandle) -> WidgetVTable {
+        WidgetVTable {
+            abi_version: ABI_VERSION,
+            create,
+            destroy,
+            last_panic,
+            name,
+            width,
+            update,
+            render,
+            set_instance_config,
+            handle_event,
+            next_update,
+            needs_redraw,
+            popup_request,
+            render_popup,
+            handle_popup_event,
+        }
+    }
+}
+
+/// Exports a widget from a plugin crate through the stable C ABI.
+///
+/// ```ignore
+/// hyprbar::export_widget!(MyWidget::new());
+/// ```
+#[macro_export]
+macro_rules! export_widget {
+    ($ctor:expr) => {
+        #[allow(non_upper_case_globals)]
+        #[unsafe(no_mangle)]
+        pub static _hyprbar_abi_version: u32 = $crate::abi::ABI_VERSION;
+
+        #[unsafe(no_mangle)]
+        pub extern "C" fn _hyprbar_widget_vtable() -> *const $crate::abi::WidgetVTable {
+            unsafe extern "C" fn create() -> $crate::abi::WidgetHandle {
+                $crate::abi::glue::create(|| ::std::boxed::Box::new($ctor))
+            }
+            static VTABLE: $crate::abi::WidgetVTable = $crate::abi::glue::vtable(create);
+            &VTABLE
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_color_roundtrip() {
+        let colors = [
+            Color::Reset,
+            Color::Black,
+            Color::LightCyan,
+            Color::White,
+            Color::Indexed(208),
+            Color::Rgb(12, 34, 56),
+        ];
+        for c in colors {
+            assert_eq!(Color::from(AbiColor::from(c)), c);
+        }
+    }
+
+    #[test]
+    fn test_cells_roundtrip() {
+        let area = Rect::new(2, 0, 3, 1);
+        let mut src = Buffer::empty(Rect::new(0, 0, 6, 1));
+        src[(2, 0)].set_symbol("é").set_fg(Color::Red);
+        src[(3, 0)].set_symbol("✓").modifier = Modifier::BOLD | Modifier::ITALIC;
+
+        let cells = read_cells(&src, area);
+        assert_eq!(cells.len(), 3);
+
+        let mut dst = Buffer::empty(Rect::new(0, 0, 6, 1));
+        write_cells(&mut dst, area, &cells);
+        assert_eq!(dst[(2, 0)], src[(2, 0)]);
+        assert_eq!(dst[(3, 0)], src[(3, 0)]);
+        assert_eq!(dst[(0, 0)].symbol(), " ");
+    }
+
+    #[test]
+    fn test_event_roundtrip() {
+        let events = [
+            WidgetEvent::Enter,
+            WidgetEvent::Leave,
+            WidgetEvent::Motion { x: 3, y: 1 },
+            WidgetEvent::Click {
+                button: 272,
+                x: 4,
+                y: 0,
+            },
+            WidgetEvent::Scroll { dx: 0.0, dy: -1.5 },
+        ];
+        for e in events {
+            assert_eq!(AbiEvent::from(e).to_event(), Some(e));
+        }
+    }
+
+    #[test]
+    fn test_bus_message_roundtrip() {
+        let messages = [
+            BusMessage::Signal(crate::bus::SIGNAL_REVEAL.to_string()),
+            BusMessage::Data("track".to_string(), "{\"title\":\"é\"}".to_string()),
+            BusMessage::Bytes("raw".to_string(), vec![0, 255, 7]),
+        ];
+        for msg in &messages {
+            let abi = AbiBusMessage::from(msg);
+            let back = unsafe { abi.to_message() }.unwrap();
+            assert_eq!(format!("{:?}", back), format!("{:?}", msg));
+        }
+    }
+}
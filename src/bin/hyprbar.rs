@@ -4,6 +4,7 @@ use hyprink::config::Config;
 
 use hyprbar::modules::autostart;
 use hyprbar::modules::build;
+use hyprbar::modules::check;
 use hyprbar::modules::cli::{Cli, Commands};
 use hyprbar::modules::config;
 use hyprbar::modules::daemon;
@@ -16,13 +17,17 @@ use hyprbar::tui;
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
+    if let Some(path) = &cli.config {
+        config::set_config_path(path);
+    }
 
     // 1. Load Config (Global Styles) - Essential for logging everywhere
     let config_ink = std::sync::Arc::new(Config::load().context("Failed to load config")?);
 
     // 0. Handle Detached Debug Mode
-    // Only if debug is on, no subcommand, AND no action flags (start/stop/restart/autostart)
-    let has_action_flag = cli.start || cli.stop || cli.restart || cli.autostart;
+    // Only if debug is on, no subcommand, AND no action flags (start/stop/restart/autostart/toggle-reveal)
+    let has_action_flag =
+        cli.start || cli.stop || cli.restart || cli.autostart || cli.toggle_reveal;
     if cli.debug && cli.command.is_none() && !has_action_flag {
         // If debug is on and no subcommand, we spawn the daemon and the viewer, then exit
         daemon::spawn_bar_daemon(&config_ink, true)
@@ -83,6 +88,10 @@ async fn main() -> Result<()> {
         }
         return Ok(());
     }
+    if cli.toggle_reveal {
+        daemon::toggle_reveal(&config_ink).context("Failed to signal bar daemon")?;
+        return Ok(());
+    }
     if cli.autostart {
         hyprlog::internal::debug("CLI", "Handling --autostart flag");
         autostart::handle_autostart(&config_ink)?;
@@ -108,6 +117,10 @@ async fn main() -> Result<()> {
             }
             Commands::Compile { path } => return build::compile_widget(path, &config_ink).await,
             Commands::Install { path } => return install::install_widget(path, &config_ink).await,
+            Commands::Check { path } => {
+                let path = path.clone().unwrap_or_else(config::bar_config_path);
+                return check::run_check(&path);
+            }
             Commands::List => {
                 use hyprbar::modules::registry::Registry;
                 let registry = Registry::load()?;
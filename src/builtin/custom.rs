@@ -0,0 +1,327 @@
+//! `custom`: shows the output of a shell command, refreshed periodically.
+//!
+//! Configured in `[widget.custom.<alias>]` (falling back to `[widget.custom]`):
+//!
+//! - `command`: run with `sh -c`; plain text output shows its first line, a JSON
+//!   object may set `text`, `class` (a theme color key) and `percentage`
+//! - `interval`: seconds between runs; without it the command runs once
+//! - `format`: e.g. `"  {text} ({percentage}%)"`; `{}` is short for `{text}`
+//! - `on_click_left`, `on_click_middle`, `on_click_right`, `on_scroll_up`,
+//!   `on_scroll_down`: commands run on pointer input, after which `command`
+//!   is re-run
+
+use crate::config::BarConfig;
+use crate::event::WidgetEvent;
+use crate::modules::logging::*;
+use crate::state::BarState;
+use crate::ui::style::ThemeExt;
+use crate::widget::Widget;
+use ratatui::prelude::*;
+use serde::Deserialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::process::Command;
+use tokio::runtime::Handle;
+
+const NAME: &str = "custom";
+
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+struct Output {
+    #[serde(default)]
+    text: String,
+    #[serde(default)]
+    class: Option<String>,
+    #[serde(default)]
+    percentage: Option<f64>,
+}
+
+impl Output {
+    /// A JSON object is taken field by field, anything else is plain text.
+    fn parse(stdout: &str) -> Self {
+        let trimmed = stdout.trim();
+        if trimmed.starts_with('{')
+            && let Ok(output) = serde_json::from_str::<Output>(trimmed)
+        {
+            return output;
+        }
+        Self {
+            text: trimmed.lines().next().unwrap_or_default().to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn format(&self, format: Option<&str>) -> String {
+        let Some(format) = format else {
+            return self.text.clone();
+        };
+        let percentage = self
+            .percentage
+            .map(|p| format!("{:.0}", p))
+            .unwrap_or_default();
+        format
+            .replace("{}", &self.text)
+            .replace("{text}", &self.text)
+            .replace("{class}", self.class.as_deref().unwrap_or_default())
+            .replace("{percentage}", &percentage)
+    }
+}
+
+/// Options re-read after every config change so edits apply in place.
+#[derive(Debug, Default, Clone)]
+struct Settings {
+    command: Option<String>,
+    interval: Option<Duration>,
+    format: Option<String>,
+    on_click_left: Option<String>,
+    on_click_middle: Option<String>,
+    on_click_right: Option<String>,
+    on_scroll_up: Option<String>,
+    on_scroll_down: Option<String>,
+}
+
+impl Settings {
+    fn load(config: &BarConfig, alias: Option<&str>) -> Self {
+        let string = |key: &str| {
+            config
+                .widget_option(NAME, alias, key)
+                .and_then(|v| v.as_str())
+                .map(str::to_string)
+        };
+        let interval = config
+            .widget_option(NAME, alias, "interval")
+            .and_then(|v| v.as_float().or_else(|| v.as_integer().map(|i| i as f64)))
+            .filter(|secs| *secs > 0.0)
+            .map(Duration::from_secs_f64);
+
+        Self {
+            command: string("command"),
+            interval,
+            format: string("format"),
+            on_click_left: string("on_click_left"),
+            on_click_middle: string("on_click_middle"),
+            on_click_right: string("on_click_right"),
+            on_scroll_up: string("on_scroll_up"),
+            on_scroll_down: string("on_scroll_down"),
+        }
+    }
+
+    fn action(&self, event: WidgetEvent) -> Option<&str> {
+        let action = match event {
+            // Raw Wayland button codes: BTN_LEFT, BTN_RIGHT, BTN_MIDDLE
+            WidgetEvent::Click { button: 272, .. } => &self.on_click_left,
+            WidgetEvent::Click { button: 273, .. } => &self.on_click_right,
+            WidgetEvent::Click { button: 274, .. } => &self.on_click_middle,
+            WidgetEvent::Scroll { dy, .. } if dy < 0.0 => &self.on_scroll_up,
+            WidgetEvent::Scroll { dy, .. } if dy > 0.0 => &self.on_scroll_down,
+            _ => return None,
+        };
+        action.as_deref()
+    }
+}
+
+pub(super) struct CustomWidget {
+    runtime: Handle,
+    instance: Option<String>,
+    settings: Settings,
+    /// `BarState::config_revision` the settings were loaded from.
+    settings_revision: Option<u64>,
+    output: Arc<Mutex<Output>>,
+    /// New output arrived since the last render.
+    dirty: Arc<AtomicBool>,
+    /// An action finished and the command should run again right away.
+    refresh: Arc<AtomicBool>,
+    /// A run is in flight; runs never overlap.
+    running: Arc<AtomicBool>,
+    /// `None` once a command without `interval` has run.
+    next_run: Option<Instant>,
+}
+
+impl CustomWidget {
+    pub(super) fn new(runtime: Handle) -> Self {
+        Self {
+            runtime,
+            instance: None,
+            settings: Settings::default(),
+            settings_revision: None,
+            output: Arc::new(Mutex::new(Output::default())),
+            dirty: Arc::new(AtomicBool::new(false)),
+            refresh: Arc::new(AtomicBool::new(false)),
+            running: Arc::new(AtomicBool::new(false)),
+            next_run: Some(Instant::now()),
+        }
+    }
+
+    fn label(&self) -> &str {
+        self.instance.as_deref().unwrap_or(NAME)
+    }
+
+    fn output(&self) -> std::sync::MutexGuard<'_, Output> {
+        self.output.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn text(&self) -> String {
+        self.output().format(self.settings.format.as_deref())
+    }
+
+    fn shell(&self, command: &str) -> Command {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c")
+            .arg(command)
+            .env("HYPRBAR_INSTANCE", self.label())
+            .stdin(std::process::Stdio::null())
+            .kill_on_drop(true);
+        cmd
+    }
+
+    /// Starts a run unless one is still in flight; returns whether it started.
+    fn run_command(&self, command: &str) -> bool {
+        if self.running.swap(true, Ordering::AcqRel) {
+            return false;
+        }
+        let mut cmd = self.shell(command);
+        let label = self.label().to_string();
+        let output = self.output.clone();
+        let dirty = self.dirty.clone();
+        let refresh = self.refresh.clone();
+        let running = self.running.clone();
+        self.runtime.spawn(async move {
+            match cmd.output().await {
+                Ok(out) => {
+                    if !out.status.success() {
+                        log_debug(
+                            "CUSTOM",
+                            &format!("{}: command exited with {}", label, out.status),
+                        );
+                    }
+                    let new = Output::parse(&String::from_utf8_lossy(&out.stdout));
+                    let mut current = output.lock().unwrap_or_else(|e| e.into_inner());
+                    if *current != new {
+                        *current = new;
+                        dirty.store(true, Ordering::Release);
+                        crate::wake::wake();
+                    }
+                }
+                Err(e) => log_warn(
+                    "CUSTOM",
+                    &format!("{}: failed to run command: {}", label, e),
+                ),
+            }
+            running.store(false, Ordering::Release);
+            // A refresh requested meanwhile waits for this run to end
+            if refresh.load(Ordering::Acquire) {
+                crate::wake::wake();
+            }
+        });
+        true
+    }
+
+    fn run_action(&self, action: &str) {
+        let mut cmd = self.shell(action);
+        let label = self.label().to_string();
+        let refresh = self.refresh.clone();
+        self.runtime.spawn(async move {
+            match cmd.status().await {
+                Ok(status) if !status.success() => log_debug(
+                    "CUSTOM",
+                    &format!("{}: action exited with {}", label, status),
+                ),
+                Ok(_) => {}
+                Err(e) => log_warn("CUSTOM", &format!("{}: failed to run action: {}", label, e)),
+            }
+            // Actions usually change what the command reports
+            refresh.store(true, Ordering::Release);
+            crate::wake::wake();
+        });
+    }
+}
+
+impl Widget for CustomWidget {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn set_instance_config(&mut self, name: String) {
+        self.instance = Some(name);
+    }
+
+    fn update(&mut self, _dt: Duration, state: &BarState) {
+        if self.settings_revision != Some(state.config_revision) {
+            self.settings = Settings::load(&state.config, self.instance.as_deref());
+            self.settings_revision = Some(state.config_revision);
+        }
+        let Some(command) = self.settings.command.clone() else {
+            if self.next_run.take().is_some() {
+                log_warn(
+                    "CUSTOM",
+                    &format!("{}: no command configured", self.label()),
+                );
+            }
+            return;
+        };
+
+        let now = Instant::now();
+        let due = self.next_run.is_some_and(|t| now >= t);
+        let refresh = self.refresh.load(Ordering::Acquire);
+        if !due && !refresh {
+            return;
+        }
+        // A refresh stays pending until a run actually starts
+        if self.run_command(&command) {
+            self.refresh.store(false, Ordering::Release);
+        }
+        if due {
+            self.next_run = self.settings.interval.map(|interval| now + interval);
+        }
+    }
+
+    fn width(&self, _state: &BarState) -> u16 {
+        Line::from(self.text()).width() as u16
+    }
+
+    fn render(&mut self, area: Rect, buf: &mut Buffer, state: &BarState, _dt: Duration) {
+        self.dirty.store(false, Ordering::Release);
+        let class = self.output().class.clone();
+        let style = match class.map(|c| state.config_ink.resolve_color(&c)) {
+            Some(Color::Reset) | None => Style::default(),
+            Some(color) => Style::default().fg(color),
+        };
+        buf.set_stringn(area.x, area.y, self.text(), area.width as usize, style);
+    }
+
+    fn handle_event(&mut self, event: WidgetEvent) {
+        if let Some(action) = self.settings.action(event) {
+            self.run_action(action);
+        }
+    }
+
+    fn next_update(&self) -> Option<Duration> {
+        self.next_run
+            .map(|t| t.saturating_duration_since(Instant::now()))
+    }
+
+    fn needs_redraw(&self) -> bool {
+        self.dirty.load(Ordering::Acquire)
+            || (self.refresh.load(Ordering::Acquire) && !self.running.load(Ordering::Acquire))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_output() {
+        let plain = Output::parse("42°C\nsecond line\n");
+        assert_eq!(plain.text, "42°C");
+        assert_eq!(plain.class, None);
+
+        let json = Output::parse(r#"{"text": "vol", "class": "warning", "percentage": 66.6}"#);
+        assert_eq!(json.text, "vol");
+        assert_eq!(json.class.as_deref(), Some("warning"));
+        assert_eq!(
+            json.format(Some("{} {percentage}% [{class}]")),
+            "vol 67% [warning]"
+        );
+    }
+}
@@ -0,0 +1,50 @@
+//! Widgets compiled into the bar, so a fresh install works without building
+//! any plugin.
+
+mod custom;
+pub mod datetime;
+pub mod separator;
+pub mod text_area;
+
+use crate::config::ProviderKind;
+use crate::widget::{Widget, WidgetProvider};
+use custom::CustomWidget;
+use datetime::DateTimeWidget;
+use separator::Separator;
+use text_area::TextArea;
+use tokio::runtime::Handle;
+
+/// Names of the widgets compiled into the bar.
+pub const WIDGETS: &[&str] = &["custom", "datetime", "separator", "text_area"];
+
+pub struct BuiltinProvider {
+    // Commands of `custom` widgets run on the bar's tokio runtime
+    runtime: Option<Handle>,
+}
+
+impl BuiltinProvider {
+    pub fn new() -> Self {
+        Self {
+            runtime: Handle::try_current().ok(),
+        }
+    }
+}
+
+impl Default for BuiltinProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WidgetProvider for BuiltinProvider {
+    fn create_widget(&self, name: &str) -> Option<(Box<dyn Widget>, ProviderKind)> {
+        let widget: Box<dyn Widget> = match name {
+            "custom" => Box::new(CustomWidget::new(self.runtime.clone()?)),
+            "datetime" => Box::new(DateTimeWidget::new()),
+            "separator" => Box::new(Separator::new()),
+            "text_area" => Box::new(TextArea::new()),
+            _ => return None,
+        };
+        Some((widget, ProviderKind::Builtin))
+    }
+}
@@ -0,0 +1,57 @@
+//! `separator`: a fixed divider between modules.
+
+use crate::prelude::*;
+use std::borrow::Cow;
+
+pub struct Separator {
+    _symbol: String,
+}
+
+impl Separator {
+    pub fn new() -> Self {
+        Self {
+            _symbol: "|".to_string(),
+        }
+    }
+}
+
+impl Default for Separator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Widget for Separator {
+    fn name(&self) -> &str {
+        "separator"
+    }
+
+    fn width(&self, state: &BarState) -> u16 {
+        let symbol = state
+            .config
+            .widget
+            .get("separator")
+            .and_then(|v| v.get("symbol"))
+            .and_then(|v| v.as_str())
+            .unwrap_or("|");
+
+        symbol.chars().count() as u16
+    }
+
+    fn next_update(&self) -> Option<Duration> {
+        None
+    }
+
+    fn update(&mut self, _dt: std::time::Duration, _state: &BarState) {}
+
+    fn render(&mut self, area: Rect, buf: &mut Buffer, state: &BarState, _dt: Duration) {
+        use ratatui::widgets::Widget as RatatuiWidget;
+        // Sections stack vertically on a left/right bar
+        let symbol = if state.config.window.edge().is_vertical() {
+            "─"
+        } else {
+            "|"
+        };
+        ratatui::widgets::Paragraph::new(Cow::from(symbol)).render(area, buf);
+    }
+}
@@ -0,0 +1,61 @@
+//! `text_area`: static text from `content` (per instance alias).
+
+use crate::prelude::*;
+
+pub struct TextArea {
+    instance_name: Option<String>,
+}
+
+impl TextArea {
+    pub fn new() -> Self {
+        Self {
+            instance_name: None,
+        }
+    }
+}
+
+impl Default for TextArea {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Widget for TextArea {
+    fn name(&self) -> &str {
+        "text_area"
+    }
+
+    fn set_instance_config(&mut self, name: String) {
+        self.instance_name = Some(name);
+    }
+
+    fn width(&self, state: &BarState) -> u16 {
+        let content = self.get_content(state);
+        content.chars().count() as u16
+    }
+
+    fn next_update(&self) -> Option<Duration> {
+        None
+    }
+
+    fn update(&mut self, _dt: std::time::Duration, _state: &BarState) {}
+
+    fn render(&mut self, area: Rect, buf: &mut Buffer, state: &BarState, _dt: Duration) {
+        let content = self.get_content(state);
+
+        Label::new(&content)
+            .variant(TypographyVariant::Body)
+            .render(area, buf, state.config_ink.as_ref());
+    }
+}
+
+impl TextArea {
+    fn get_content(&self, state: &BarState) -> String {
+        state
+            .config
+            .widget_option("text_area", self.instance_name.as_deref(), "content")
+            .and_then(|v| v.as_str())
+            .unwrap_or("hyprbar")
+            .to_string()
+    }
+}
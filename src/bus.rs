@@ -1,6 +1,13 @@
 use std::fmt;
 use tokio::sync::broadcast;
 
+/// Keeps auto-hidden bars shown until [`SIGNAL_CONCEAL`].
+pub const SIGNAL_REVEAL: &str = "bar_reveal";
+/// Lets auto-hidden bars hide again.
+pub const SIGNAL_CONCEAL: &str = "bar_conceal";
+/// Switches between the two; sent when the bar receives SIGUSR1.
+pub const SIGNAL_TOGGLE_REVEAL: &str = "bar_toggle_reveal";
+
 /// Message types that can be broadcasted on the event bus.
 #[derive(Debug, Clone)]
 pub enum BusMessage {
@@ -1,6 +1,7 @@
-use serde::{Deserialize, Serialize};
+use serde::de::{DeserializeOwned, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
 
-#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Default)]
 pub struct BarConfig {
     #[serde(default)]
     pub window: WindowConfig,
@@ -13,10 +14,96 @@ pub struct BarConfig {
     #[serde(default)]
     pub popup: PopupConfig,
     #[serde(default)]
+    pub fault: FaultConfig,
+    /// Out-of-process widgets, keyed by the name used in `modules_*`.
+    #[serde(default)]
+    pub external: std::collections::HashMap<String, ExternalWidgetConfig>,
+    #[serde(default)]
     pub widget: std::collections::HashMap<String, toml::Value>,
+    /// Bars to open. Empty means one bar described by the sections above.
+    #[serde(default)]
+    pub bar: Vec<BarEntry>,
+}
+
+impl BarConfig {
+    /// Option `key` of a widget instance: `[widget.<name>.<alias>]` first,
+    /// then the shared `[widget.<name>]` table.
+    pub fn widget_option(
+        &self,
+        name: &str,
+        alias: Option<&str>,
+        key: &str,
+    ) -> Option<&toml::Value> {
+        let base = self.widget.get(name)?.as_table()?;
+        alias
+            .and_then(|alias| base.get(alias))
+            .and_then(|v| v.as_table())
+            .and_then(|t| t.get(key))
+            .or_else(|| base.get(key))
+    }
+
+    /// The config of every bar to open: each `[[bar]]` entry laid over this
+    /// config, or this config alone if there are none.
+    pub fn bars(&self) -> Result<Vec<BarConfig>, toml::de::Error> {
+        if self.bar.is_empty() {
+            return Ok(vec![self.clone()]);
+        }
+        self.bar
+            .iter()
+            .map(|entry| {
+                Ok(BarConfig {
+                    window: overlay(&self.window, &entry.window)?,
+                    style: overlay(&self.style, &entry.style)?,
+                    layout: overlay(&self.layout, &entry.layout)?,
+                    popup: overlay(&self.popup, &entry.popup)?,
+                    bar: Vec::new(),
+                    ..self.clone()
+                })
+            })
+            .collect()
+    }
+}
+
+/// One `[[bar]]`. Its tables override the top-level sections key by key, so
+/// shared settings only have to be written once.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Default)]
+pub struct BarEntry {
+    #[serde(default)]
+    pub window: toml::Table,
+    #[serde(default)]
+    pub style: toml::Table,
+    #[serde(default)]
+    pub layout: toml::Table,
+    #[serde(default)]
+    pub popup: toml::Table,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+/// `base` with the keys of `overrides` replaced; nested tables are merged.
+pub fn overlay<T>(base: &T, overrides: &toml::Table) -> Result<T, toml::de::Error>
+where
+    T: Clone + Serialize + DeserializeOwned,
+{
+    if overrides.is_empty() {
+        return Ok(base.clone());
+    }
+    let mut table =
+        toml::Table::try_from(base).map_err(<toml::de::Error as serde::de::Error>::custom)?;
+    merge_table(&mut table, overrides);
+    table.try_into()
+}
+
+fn merge_table(base: &mut toml::Table, overrides: &toml::Table) {
+    for (key, value) in overrides {
+        match (base.get_mut(key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(value)) => merge_table(base, value),
+            _ => {
+                base.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Default)]
 pub struct PopupConfig {
     #[serde(default)]
     pub offset_x: i16,
@@ -24,7 +111,70 @@ pub struct PopupConfig {
     pub offset_y: i16,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+/// A widget backed by a child process speaking the JSON line protocol
+/// (see [`crate::external`]).
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ExternalWidgetConfig {
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+/// What happens to a widget after it panics.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct FaultConfig {
+    /// Shown in place of a faulted widget; `{name}` expands to the widget name.
+    #[serde(default = "default_fault_placeholder")]
+    pub placeholder: String,
+    /// Retry with backoff instead of leaving the widget disabled.
+    #[serde(default = "default_fault_retry")]
+    pub retry: bool,
+    #[serde(default = "default_fault_retry_delay")]
+    pub retry_delay: u64, // ms, doubled after every failure
+    #[serde(default = "default_fault_max_retry_delay")]
+    pub max_retry_delay: u64, // ms
+}
+
+impl Default for FaultConfig {
+    fn default() -> Self {
+        Self {
+            placeholder: default_fault_placeholder(),
+            retry: default_fault_retry(),
+            retry_delay: default_fault_retry_delay(),
+            max_retry_delay: default_fault_max_retry_delay(),
+        }
+    }
+}
+
+impl FaultConfig {
+    /// Delay before the next retry after `failures` consecutive failures.
+    pub fn backoff(&self, failures: u32) -> std::time::Duration {
+        let factor = 1u64 << failures.saturating_sub(1).min(16);
+        let ms = self
+            .retry_delay
+            .saturating_mul(factor)
+            .min(self.max_retry_delay);
+        std::time::Duration::from_millis(ms)
+    }
+}
+
+fn default_fault_placeholder() -> String {
+    "⚠ {name}".to_string()
+}
+
+fn default_fault_retry() -> bool {
+    true
+}
+
+fn default_fault_retry_delay() -> u64 {
+    1000
+}
+
+fn default_fault_max_retry_delay() -> u64 {
+    60_000
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct LoggingConfig {
     #[serde(default = "default_log_level")]
     pub level: String,
@@ -49,8 +199,9 @@ fn default_debug_filter() -> String {
     "info,hyprbar=debug".to_string()
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct LayoutConfig {
+    /// Left, center and right sections; top, middle and bottom on a vertical bar.
     #[serde(default)]
     pub modules_left: Vec<String>,
     #[serde(default)]
@@ -69,6 +220,30 @@ pub struct LayoutConfig {
     pub strategy: String,
     #[serde(default = "default_layout_padding")]
     pub padding: u16,
+
+    /// Where module names are looked up, first match wins.
+    #[serde(default = "default_layout_providers")]
+    pub providers: Vec<ProviderKind>,
+}
+
+/// Splits a `modules_*` entry into widget name and instance alias:
+/// `name.alias`, `name#alias` or just `name` (its own alias).
+pub fn parse_module_spec(spec: &str) -> (&str, &str) {
+    spec.split_once('.')
+        .or_else(|| spec.split_once('#'))
+        .unwrap_or((spec, spec))
+}
+
+/// A source of widgets for `modules_*` names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProviderKind {
+    /// Widgets compiled into hyprbar.
+    Builtin,
+    /// `.so` plugins from the widgets directory.
+    Plugin,
+    /// Child processes from `[external.*]`.
+    External,
 }
 
 impl Default for LayoutConfig {
@@ -79,6 +254,7 @@ impl Default for LayoutConfig {
             right: 33,
             strategy: default_layout_strategy(),
             padding: default_layout_padding(),
+            providers: default_layout_providers(),
             modules_left: vec![],
             modules_center: vec![],
             modules_right: vec![],
@@ -98,10 +274,19 @@ fn default_layout_padding() -> u16 {
     1
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+fn default_layout_providers() -> Vec<ProviderKind> {
+    vec![
+        ProviderKind::Builtin,
+        ProviderKind::Plugin,
+        ProviderKind::External,
+    ]
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct WindowConfig {
+    /// Thickness of the bar: its height, or its width when anchored left/right.
     pub height: u32,
-    pub anchor: String, // "top", "bottom"
+    pub anchor: String, // "top", "bottom", "left", "right"
     #[serde(default = "default_monitor")]
     pub monitor: String,
 
@@ -116,9 +301,104 @@ pub struct WindowConfig {
     pub min_padding: u32,
     #[serde(default)]
     pub height_rows: Option<u32>,
+
+    // Layer-shell placement
+    /// "background", "bottom", "top" or "overlay".
+    #[serde(default = "default_layer")]
+    pub layer: String,
+    /// Gaps to the screen edges, in pixels.
+    #[serde(default)]
+    pub margin_top: i32,
+    #[serde(default)]
+    pub margin_right: i32,
+    #[serde(default)]
+    pub margin_bottom: i32,
+    #[serde(default)]
+    pub margin_left: i32,
+    #[serde(default)]
+    pub exclusive: Exclusive,
+    /// Length along the edge; 0 spans the whole edge, anything else makes a
+    /// floating bar centered on it.
+    #[serde(default)]
+    pub width: u32,
+
+    /// Collapse the bar to a thin strip at its edge while the pointer is away.
+    #[serde(default)]
+    pub autohide: bool,
+    /// Milliseconds the bar stays shown after the pointer left it.
+    #[serde(default = "default_autohide_delay")]
+    pub autohide_delay: u64,
+    /// Thickness of the strip that reveals a hidden bar, in pixels.
+    #[serde(default = "default_autohide_trigger")]
+    pub autohide_trigger: u32,
+}
+
+/// Space the compositor reserves for the bar, keeping windows off it:
+/// `true` its thickness, `false` none, or a size in pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum Exclusive {
+    Enabled(bool),
+    Size(i32),
+}
+
+impl Default for Exclusive {
+    fn default() -> Self {
+        Exclusive::Enabled(true)
+    }
+}
+
+/// Screen edge a bar is anchored to, from `window.anchor`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edge {
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+impl Edge {
+    /// Left/right bars stack their sections top to bottom.
+    pub fn is_vertical(self) -> bool {
+        matches!(self, Edge::Left | Edge::Right)
+    }
 }
 
 impl WindowConfig {
+    /// Unknown anchors fall back to the top edge.
+    pub fn edge(&self) -> Edge {
+        match self.anchor.as_str() {
+            "bottom" => Edge::Bottom,
+            "left" => Edge::Left,
+            "right" => Edge::Right,
+            _ => Edge::Top,
+        }
+    }
+
+    /// Margins as (top, right, bottom, left).
+    pub fn margins(&self) -> (i32, i32, i32, i32) {
+        (
+            self.margin_top,
+            self.margin_right,
+            self.margin_bottom,
+            self.margin_left,
+        )
+    }
+
+    /// The layer-shell exclusive zone for a bar `thickness` pixels thick.
+    /// An auto-hidden bar never reserves space, it shows above windows, and
+    /// neither does a `hidden` one collapsed to its trigger strip.
+    pub fn exclusive_zone(&self, thickness: u32, hidden: bool) -> i32 {
+        if self.autohide || hidden {
+            return 0;
+        }
+        match self.exclusive {
+            Exclusive::Enabled(true) => thickness as i32,
+            Exclusive::Enabled(false) => 0,
+            Exclusive::Size(size) => size,
+        }
+    }
+
     /// Calculates (font_size, window_height) based on configuration.
     /// Returns:
     /// - font_size: The calculated font size in pixels
@@ -130,6 +410,9 @@ impl WindowConfig {
             let lh = fs * 1.2;
             let h = (lh * rows as f32).ceil() as u32 + self.min_padding;
             (fs, h)
+        } else if self.edge().is_vertical() {
+            // The thickness of a vertical bar is its width, which says nothing about the font
+            (16.0, self.height)
         } else {
             // Pixel-based sizing: Font follows Height (Smart Scaling)
             let target_h = self.height;
@@ -162,6 +445,16 @@ impl Default for WindowConfig {
             font_base_size: 10,
             min_padding: 2,
             height_rows: None,
+            layer: default_layer(),
+            margin_top: 0,
+            margin_right: 0,
+            margin_bottom: 0,
+            margin_left: 0,
+            exclusive: Exclusive::default(),
+            width: 0,
+            autohide: false,
+            autohide_delay: default_autohide_delay(),
+            autohide_trigger: default_autohide_trigger(),
         }
     }
 }
@@ -216,6 +509,93 @@ mod tests {
         assert_eq!(fs, 20.0);
     }
 
+    #[test]
+    fn test_fault_backoff() {
+        let config = FaultConfig {
+            retry_delay: 500,
+            max_retry_delay: 3000,
+            ..FaultConfig::default()
+        };
+        assert_eq!(config.backoff(1).as_millis(), 500);
+        assert_eq!(config.backoff(2).as_millis(), 1000);
+        assert_eq!(config.backoff(3).as_millis(), 2000);
+        assert_eq!(config.backoff(4).as_millis(), 3000);
+        assert_eq!(config.backoff(40).as_millis(), 3000);
+    }
+
+    #[test]
+    fn test_bars() {
+        let config: BarConfig = toml::from_str(
+            "[window]\nheight = 30\nanchor = \"top\"\n\
+             [layout]\nmodules_left = [\"a\"]\nmodules_right = [\"b\"]\n\
+             [[bar]]\nwindow = { monitor = \"DP-1\" }\n\
+             [[bar]]\n[bar.window]\nmonitor = \"HDMI-A-1\"\nheight = 20\n\
+             [bar.layout]\nmodules_left = []\n",
+        )
+        .unwrap();
+
+        let bars = config.bars().unwrap();
+        assert_eq!(bars.len(), 2);
+        assert_eq!(bars[0].window.monitor, "DP-1");
+        assert_eq!(bars[0].window.height, 30);
+        assert_eq!(bars[0].layout, config.layout);
+        assert_eq!(bars[1].window.height, 20);
+        assert_eq!(bars[1].window.anchor, "top");
+        assert!(bars[1].layout.modules_left.is_empty());
+        assert_eq!(bars[1].layout.modules_right, vec!["b".to_string()]);
+        assert!(bars.iter().all(|b| b.bar.is_empty()));
+
+        let single = BarConfig::default();
+        assert_eq!(single.bars().unwrap(), vec![single.clone()]);
+    }
+
+    #[test]
+    fn test_vertical_sizing() {
+        let config = WindowConfig {
+            height: 200,
+            anchor: "left".to_string(),
+            ..WindowConfig::default()
+        };
+
+        assert_eq!(config.edge(), Edge::Left);
+        assert_eq!(config.calculate_dimensions(), (16.0, 200));
+    }
+
+    #[test]
+    fn test_font_list() {
+        let style: StyleConfig = toml::from_str("font = \"JetBrains Mono\"").unwrap();
+        assert_eq!(style.font, vec!["JetBrains Mono".to_string()]);
+
+        let style: StyleConfig =
+            toml::from_str("font = [\"JetBrains Mono\", \"Noto Color Emoji\"]").unwrap();
+        assert_eq!(style.font.len(), 2);
+        assert!(toml::from_str::<StyleConfig>("font = 12").is_err());
+    }
+
+    #[test]
+    fn test_exclusive_zone() {
+        let parse = |toml: &str| -> WindowConfig {
+            toml::from_str(&format!("height = 30\nanchor = \"top\"\n{}", toml)).unwrap()
+        };
+
+        let config = parse("margin_top = 8");
+        assert_eq!(config.exclusive, Exclusive::Enabled(true));
+        assert_eq!(config.exclusive_zone(30, false), 30);
+
+        let config = parse("exclusive = false\nmargin_top = 8");
+        assert_eq!(config.exclusive_zone(30, false), 0);
+
+        let config = parse("exclusive = 20");
+        assert_eq!(config.exclusive_zone(30, false), 20);
+        // A bar collapsed to its trigger strip reserves nothing
+        assert_eq!(config.exclusive_zone(2, true), 0);
+
+        // Auto-hidden bars overlap windows whatever `exclusive` says
+        let config = parse("autohide = true\nexclusive = 20");
+        assert_eq!(config.exclusive_zone(30, false), 0);
+        assert_eq!(config.exclusive_zone(2, true), 0);
+    }
+
     #[test]
     fn test_no_scaling() {
         let mut config = WindowConfig::default();
@@ -232,6 +612,18 @@ fn default_monitor() -> String {
     String::new() // Empty = compositor decides
 }
 
+fn default_layer() -> String {
+    "top".to_string()
+}
+
+fn default_autohide_delay() -> u64 {
+    500
+}
+
+fn default_autohide_trigger() -> u32 {
+    2
+}
+
 fn default_scale_font() -> bool {
     true
 }
@@ -244,7 +636,7 @@ fn default_min_padding() -> u32 {
     2
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct StyleConfig {
     #[serde(default)]
     pub window_bg: Option<String>,
@@ -264,12 +656,19 @@ pub struct StyleConfig {
     pub success: Option<String>,
     #[serde(default)]
     pub error: Option<String>,
-    pub font: Option<String>,
+    /// Font families or font files in fallback order. A single name works too:
+    /// `font = "JetBrains Mono"`.
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub font: Vec<String>,
     #[serde(default)]
     pub animation: Option<AnimationConfig>,
+    /// Declare the fully opaque parts of the bar to the compositor. Translucent
+    /// (`#RRGGBBAA`) and transparent parts are left out, so blur shows through.
+    #[serde(default)]
+    pub opaque_region: bool,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Default)]
 pub struct AnimationConfig {
     pub entry: Option<String>, // e.g. "slide_up", "fade"
     pub exit: Option<String>,
@@ -288,10 +687,38 @@ impl Default for StyleConfig {
             secondary: None,
             success: None,
             error: None,
-            font: None,
+            font: Vec::new(),
             animation: None,
+            opaque_region: false,
+        }
+    }
+}
+
+/// Accepts a string as well as a list of strings.
+fn one_or_many<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    struct OneOrMany;
+
+    impl<'de> Visitor<'de> for OneOrMany {
+        type Value = Vec<String>;
+
+        fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            f.write_str("a string or a list of strings")
+        }
+
+        fn visit_str<E: serde::de::Error>(self, value: &str) -> Result<Self::Value, E> {
+            Ok(vec![value.to_string()])
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let mut values = Vec::new();
+            while let Some(value) = seq.next_element()? {
+                values.push(value);
+            }
+            Ok(values)
         }
     }
+
+    deserializer.deserialize_any(OneOrMany)
 }
 
 fn default_bg() -> String {
@@ -14,6 +14,8 @@ pub enum WidgetEvent {
     Click { button: u32, x: u16, y: u16 },
     /// Scroll event (dx, dy)
     Scroll { dx: f64, dy: f64 },
+    /// The compositor closed the widget's popup, e.g. for a click outside it
+    PopupDismissed,
 }
 
 impl InteractionExt for WidgetEvent {
@@ -0,0 +1,41 @@
+//! Widgets implemented as external programs.
+//!
+//! Any executable configured under `[external.<name>]` can be placed in `modules_*`
+//! like a plugin. It runs as a child process and talks to the bar over stdio using
+//! the JSON line protocol in [`protocol`]. Crashed children are restarted with the
+//! `[fault]` backoff.
+
+pub mod protocol;
+mod widget;
+
+use crate::config::{BarConfig, ExternalWidgetConfig, FaultConfig, ProviderKind};
+use crate::widget::{Widget, WidgetProvider};
+use std::collections::HashMap;
+use tokio::runtime::Handle;
+use widget::ExternalWidget;
+
+pub struct ExternalProvider {
+    widgets: HashMap<String, ExternalWidgetConfig>,
+    fault: FaultConfig,
+    // Children and their pipes are driven by the bar's tokio runtime
+    runtime: Option<Handle>,
+}
+
+impl ExternalProvider {
+    pub fn new(config: &BarConfig) -> Self {
+        Self {
+            widgets: config.external.clone(),
+            fault: config.fault.clone(),
+            runtime: Handle::try_current().ok(),
+        }
+    }
+}
+
+impl WidgetProvider for ExternalProvider {
+    fn create_widget(&self, name: &str) -> Option<(Box<dyn Widget>, ProviderKind)> {
+        let config = self.widgets.get(name)?;
+        let runtime = self.runtime.clone()?;
+        let widget = ExternalWidget::new(name, config.clone(), self.fault.clone(), runtime);
+        Some((Box::new(widget), ProviderKind::External))
+    }
+}
@@ -0,0 +1,237 @@
+//! JSON line protocol between the bar and external widget processes.
+//!
+//! The child writes one [`Frame`] per line to stdout, e.g.
+//! `{"spans":[{"text":"CPU ","fg":"accent","bold":true},{"text":"12%"}]}`.
+//! The bar writes one [`EventMessage`] per line to the child's stdin, e.g.
+//! `{"event":"click","button":272,"x":3,"y":0}`. Clicks on the widget's popup
+//! arrive as `popup_click`, in cells from the popup's corner, and
+//! `popup_dismissed` tells the child the compositor closed its popup.
+//! Colors are `#rrggbb` hex or hyprink theme keys; `#rrggbbaa` is blended over
+//! the theme's `bg`.
+
+use crate::event::WidgetEvent;
+use crate::ui::style::{Rgba, ThemeExt};
+use crate::widget::PopupRequest;
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use serde::{Deserialize, Serialize};
+
+/// Widget content sent by the child. Each frame replaces the previous one.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Frame {
+    /// Shorthand for a single unstyled span.
+    #[serde(default)]
+    pub text: Option<String>,
+    #[serde(default)]
+    pub spans: Vec<SpanSpec>,
+    /// Width in cells; defaults to the width of the text.
+    #[serde(default)]
+    pub width: Option<u16>,
+    /// Popup shown for this widget while present.
+    #[serde(default)]
+    pub popup: Option<PopupFrame>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct SpanSpec {
+    pub text: String,
+    #[serde(default)]
+    pub fg: Option<String>,
+    #[serde(default)]
+    pub bg: Option<String>,
+    #[serde(default)]
+    pub bold: bool,
+    #[serde(default)]
+    pub italic: bool,
+    #[serde(default)]
+    pub underline: bool,
+    #[serde(default)]
+    pub dim: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PopupFrame {
+    pub width: u16,
+    pub height: u16,
+    #[serde(default)]
+    pub lines: Vec<Vec<SpanSpec>>,
+    /// Stay open until the child drops the popup (default), or close on leave.
+    #[serde(default = "default_persist")]
+    pub persist: bool,
+    #[serde(default)]
+    pub offset_x: i16,
+    #[serde(default)]
+    pub offset_y: i16,
+}
+
+fn default_persist() -> bool {
+    true
+}
+
+/// Cells have no alpha, so a `#rrggbbaa` color is blended over the theme's
+/// `bg`. Translucent theme keys stay translucent.
+fn resolve_color(value: &str, theme: &impl ThemeExt) -> Color {
+    if !value.starts_with('#') {
+        return theme.resolve_color(value);
+    }
+    let color = Rgba::from_hex(value);
+    match theme.resolve_bg("bg") {
+        Color::Rgb(r, g, b) if color.a < 255 => color.over(Rgba { r, g, b, a: 255 }).color(),
+        _ => color.color(),
+    }
+}
+
+impl SpanSpec {
+    pub fn to_span(&self, theme: &impl ThemeExt) -> Span<'static> {
+        let mut style = Style::default();
+        if let Some(fg) = &self.fg {
+            style = style.fg(resolve_color(fg, theme));
+        }
+        if let Some(bg) = &self.bg {
+            style = style.bg(resolve_color(bg, theme));
+        }
+        for (enabled, modifier) in [
+            (self.bold, Modifier::BOLD),
+            (self.italic, Modifier::ITALIC),
+            (self.underline, Modifier::UNDERLINED),
+            (self.dim, Modifier::DIM),
+        ] {
+            if enabled {
+                style = style.add_modifier(modifier);
+            }
+        }
+        Span::styled(self.text.clone(), style)
+    }
+}
+
+fn to_line(spans: &[SpanSpec], theme: &impl ThemeExt) -> Line<'static> {
+    Line::from(spans.iter().map(|s| s.to_span(theme)).collect::<Vec<_>>())
+}
+
+impl Frame {
+    pub fn line(&self, theme: &impl ThemeExt) -> Line<'static> {
+        let mut line = to_line(&self.spans, theme);
+        if let Some(text) = &self.text {
+            line.spans.insert(0, Span::raw(text.clone()));
+        }
+        line
+    }
+
+    pub fn width(&self, theme: &impl ThemeExt) -> u16 {
+        self.width
+            .unwrap_or_else(|| self.line(theme).width() as u16)
+    }
+}
+
+impl PopupFrame {
+    pub fn request(&self) -> PopupRequest {
+        PopupRequest {
+            width: self.width,
+            height: self.height,
+            offset_x: self.offset_x,
+            offset_y: self.offset_y,
+            persist: self.persist,
+        }
+    }
+
+    pub fn lines(&self, theme: &impl ThemeExt) -> Vec<Line<'static>> {
+        self.lines.iter().map(|l| to_line(l, theme)).collect()
+    }
+}
+
+/// Input forwarded to the child. Pointer motion is not forwarded. Popup events
+/// are in cells from the popup's corner.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum EventMessage {
+    Enter,
+    Leave,
+    Click { button: u32, x: u16, y: u16 },
+    Scroll { dx: f64, dy: f64 },
+    PopupClick { button: u32, x: u16, y: u16 },
+    PopupScroll { dx: f64, dy: f64 },
+    PopupDismissed,
+}
+
+impl EventMessage {
+    pub fn from_event(event: WidgetEvent) -> Option<Self> {
+        match event {
+            WidgetEvent::Enter => Some(Self::Enter),
+            WidgetEvent::Leave => Some(Self::Leave),
+            WidgetEvent::Click { button, x, y } => Some(Self::Click { button, x, y }),
+            WidgetEvent::Scroll { dx, dy } => Some(Self::Scroll { dx, dy }),
+            WidgetEvent::PopupDismissed => Some(Self::PopupDismissed),
+            WidgetEvent::Motion { .. } => None,
+        }
+    }
+
+    /// Popups only forward clicks and scrolling.
+    pub fn from_popup_event(event: WidgetEvent) -> Option<Self> {
+        match event {
+            WidgetEvent::Click { button, x, y } => Some(Self::PopupClick { button, x, y }),
+            WidgetEvent::Scroll { dx, dy } => Some(Self::PopupScroll { dx, dy }),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct NoTheme;
+
+    impl ThemeExt for NoTheme {
+        fn resolve_color(&self, _key: &str) -> Color {
+            Color::Reset
+        }
+
+        fn resolve_bg(&self, _key: &str) -> Color {
+            Color::Reset
+        }
+    }
+
+    #[test]
+    fn test_parse_frame() {
+        let frame: Frame = serde_json::from_str(
+            r#"{"text":"A","spans":[{"text":"bc","bold":true}],"popup":{"width":10,"height":2}}"#,
+        )
+        .unwrap();
+        let line = frame.line(&NoTheme);
+        assert_eq!(line.width(), 3);
+        assert!(line.spans[1].style.add_modifier.contains(Modifier::BOLD));
+        let popup = frame.popup.unwrap();
+        assert!(popup.persist);
+        assert_eq!(popup.request().width, 10);
+    }
+
+    #[test]
+    fn test_serialize_event() {
+        let msg = EventMessage::from_event(WidgetEvent::Click {
+            button: 272,
+            x: 3,
+            y: 0,
+        })
+        .unwrap();
+        assert_eq!(
+            serde_json::to_string(&msg).unwrap(),
+            r#"{"event":"click","button":272,"x":3,"y":0}"#
+        );
+        assert_eq!(
+            EventMessage::from_event(WidgetEvent::Motion { x: 0, y: 0 }),
+            None
+        );
+
+        let msg = EventMessage::from_popup_event(WidgetEvent::Click {
+            button: 272,
+            x: 1,
+            y: 2,
+        })
+        .unwrap();
+        assert_eq!(
+            serde_json::to_string(&msg).unwrap(),
+            r#"{"event":"popup_click","button":272,"x":1,"y":2}"#
+        );
+        assert_eq!(EventMessage::from_popup_event(WidgetEvent::Enter), None);
+    }
+}
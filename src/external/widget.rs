@@ -0,0 +1,289 @@
+use super::protocol::{EventMessage, Frame};
+use crate::config::{ExternalWidgetConfig, FaultConfig};
+use crate::event::WidgetEvent;
+use crate::modules::logging::*;
+use crate::state::BarState;
+use crate::widget::{PopupRequest, Widget};
+use ratatui::prelude::*;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, Command};
+use tokio::runtime::Handle;
+use tokio::sync::mpsc;
+
+/// A child that stays up this long is considered healthy again and restarts
+/// with the initial backoff.
+const STABLE_RUNTIME: Duration = Duration::from_secs(30);
+
+struct Running {
+    child: Child,
+    events: mpsc::UnboundedSender<String>,
+    started: Instant,
+    output_closed: Arc<AtomicBool>,
+}
+
+pub(super) struct ExternalWidget {
+    name: String,
+    config: ExternalWidgetConfig,
+    fault: FaultConfig,
+    runtime: Handle,
+    instance: Option<String>,
+    frame: Arc<Mutex<Option<Frame>>>,
+    dirty: Arc<AtomicBool>,
+    running: Option<Running>,
+    failures: u32,
+    /// When to (re)start the child; the first start happens on the first update.
+    restart_at: Option<Instant>,
+}
+
+impl ExternalWidget {
+    pub(super) fn new(
+        name: &str,
+        config: ExternalWidgetConfig,
+        fault: FaultConfig,
+        runtime: Handle,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            config,
+            fault,
+            runtime,
+            instance: None,
+            frame: Arc::new(Mutex::new(None)),
+            dirty: Arc::new(AtomicBool::new(false)),
+            running: None,
+            failures: 0,
+            restart_at: Some(Instant::now()),
+        }
+    }
+
+    fn frame(&self) -> std::sync::MutexGuard<'_, Option<Frame>> {
+        self.frame.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn command_path(&self) -> String {
+        match self.config.command.strip_prefix("~/") {
+            Some(rest) => dirs::home_dir()
+                .map(|home| home.join(rest).to_string_lossy().into_owned())
+                .unwrap_or_else(|| self.config.command.clone()),
+            None => self.config.command.clone(),
+        }
+    }
+
+    fn spawn(&mut self) -> std::io::Result<Running> {
+        // tokio needs its runtime context to register the child's pipes and SIGCHLD
+        let _guard = self.runtime.enter();
+        let mut child = Command::new(self.command_path())
+            .args(&self.config.args)
+            .env("HYPRBAR_WIDGET", &self.name)
+            .env(
+                "HYPRBAR_INSTANCE",
+                self.instance.as_deref().unwrap_or(&self.name),
+            )
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()?;
+
+        let output_closed = Arc::new(AtomicBool::new(false));
+
+        if let Some(stdout) = child.stdout.take() {
+            let name = self.name.clone();
+            let frame = self.frame.clone();
+            let dirty = self.dirty.clone();
+            let closed = output_closed.clone();
+            self.runtime.spawn(async move {
+                let mut lines = BufReader::new(stdout).lines();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    match serde_json::from_str::<Frame>(&line) {
+                        Ok(new_frame) => {
+                            *frame.lock().unwrap_or_else(|e| e.into_inner()) = Some(new_frame);
+                            dirty.store(true, Ordering::Release);
+                            crate::wake::wake();
+                        }
+                        Err(e) => log_warn("EXTERNAL", &format!("{}: invalid frame: {}", name, e)),
+                    }
+                }
+                closed.store(true, Ordering::Release);
+                crate::wake::wake();
+            });
+        }
+
+        if let Some(stderr) = child.stderr.take() {
+            let name = self.name.clone();
+            self.runtime.spawn(async move {
+                let mut lines = BufReader::new(stderr).lines();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    log_warn("EXTERNAL", &format!("{}: {}", name, line));
+                }
+            });
+        }
+
+        let (events, mut rx) = mpsc::unbounded_channel::<String>();
+        if let Some(mut stdin) = child.stdin.take() {
+            self.runtime.spawn(async move {
+                while let Some(msg) = rx.recv().await {
+                    if stdin.write_all(msg.as_bytes()).await.is_err()
+                        || stdin.flush().await.is_err()
+                    {
+                        break;
+                    }
+                }
+            });
+        }
+
+        Ok(Running {
+            child,
+            events,
+            started: Instant::now(),
+            output_closed,
+        })
+    }
+
+    fn schedule_restart(&mut self, stable: bool) {
+        self.failures = if stable { 1 } else { self.failures + 1 };
+        let delay = self.fault.backoff(self.failures);
+        self.restart_at = Some(Instant::now() + delay);
+        log_warn(
+            "EXTERNAL",
+            &format!("{}: restarting in {:?}", self.name, delay),
+        );
+    }
+
+    /// Reaps a child whose stdout closed and schedules its restart.
+    fn check_exited(&mut self) {
+        let Some(running) = self.running.as_mut() else {
+            return;
+        };
+        if !running.output_closed.load(Ordering::Acquire) {
+            return;
+        }
+
+        let status = match running.child.try_wait() {
+            Ok(Some(status)) => status.to_string(),
+            // Closed stdout but still alive: it can't send frames anymore
+            Ok(None) => {
+                let _ = running.child.start_kill();
+                "closed stdout".to_string()
+            }
+            Err(e) => e.to_string(),
+        };
+        let stable = running.started.elapsed() >= STABLE_RUNTIME;
+        log_warn("EXTERNAL", &format!("{} exited: {}", self.name, status));
+        self.running = None;
+        self.schedule_restart(stable);
+    }
+
+    /// Writes `msg` to the child's stdin, if it runs.
+    fn send_event(&self, msg: Option<EventMessage>) {
+        let (Some(running), Some(msg)) = (&self.running, msg) else {
+            return;
+        };
+        if let Ok(mut line) = serde_json::to_string(&msg) {
+            line.push('\n');
+            let _ = running.events.send(line);
+        }
+    }
+}
+
+impl Widget for ExternalWidget {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn set_instance_config(&mut self, name: String) {
+        self.instance = Some(name);
+    }
+
+    fn update(&mut self, _dt: Duration, _state: &BarState) {
+        self.check_exited();
+
+        if self.running.is_some() || self.restart_at.is_none_or(|t| Instant::now() < t) {
+            return;
+        }
+        self.restart_at = None;
+        match self.spawn() {
+            Ok(running) => {
+                log_info(
+                    "EXTERNAL",
+                    &format!("Started {} ({})", self.name, self.config.command),
+                );
+                self.running = Some(running);
+            }
+            Err(e) => {
+                log_error(
+                    "EXTERNAL",
+                    &format!(
+                        "Failed to start {} ({}): {}",
+                        self.name, self.config.command, e
+                    ),
+                );
+                self.schedule_restart(false);
+            }
+        }
+    }
+
+    fn width(&self, state: &BarState) -> u16 {
+        self.frame()
+            .as_ref()
+            .map_or(0, |f| f.width(state.config_ink.as_ref()))
+    }
+
+    fn render(&mut self, area: Rect, buf: &mut Buffer, state: &BarState, _dt: Duration) {
+        self.dirty.store(false, Ordering::Release);
+        if let Some(frame) = self.frame().as_ref() {
+            let line = frame.line(state.config_ink.as_ref());
+            buf.set_line(area.x, area.y, &line, area.width);
+        }
+    }
+
+    fn handle_event(&mut self, event: WidgetEvent) {
+        self.send_event(EventMessage::from_event(event));
+    }
+
+    fn handle_popup_event(&mut self, event: WidgetEvent) {
+        self.send_event(EventMessage::from_popup_event(event));
+    }
+
+    fn next_update(&self) -> Option<Duration> {
+        // Output arrives through `needs_redraw`; only (re)starts are scheduled
+        self.restart_at
+            .map(|t| t.saturating_duration_since(Instant::now()))
+    }
+
+    fn needs_redraw(&self) -> bool {
+        self.dirty.load(Ordering::Acquire)
+            || self
+                .running
+                .as_ref()
+                .is_some_and(|r| r.output_closed.load(Ordering::Acquire))
+    }
+
+    fn popup_request(&self) -> Option<PopupRequest> {
+        self.frame()
+            .as_ref()
+            .and_then(|f| f.popup.as_ref())
+            .map(|p| p.request())
+    }
+
+    fn render_popup(&mut self, area: Rect, buf: &mut Buffer, state: &BarState) {
+        let Some(popup) = self.frame().as_ref().and_then(|f| f.popup.clone()) else {
+            return;
+        };
+        for (row, line) in popup
+            .lines(state.config_ink.as_ref())
+            .iter()
+            .enumerate()
+            .take(area.height as usize)
+        {
+            buf.set_line(area.x, area.y + row as u16, line, area.width);
+        }
+    }
+}
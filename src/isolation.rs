@@ -0,0 +1,72 @@
+//! Panic capture for widget code.
+//!
+//! Widgets run inside the bar process (and plugins inside their own copy of `std`), so a
+//! panic would otherwise unwind through the renderer or abort at an `extern "C"` boundary.
+//! [`catch_panic`] turns it into an error report with the panic message and backtrace.
+
+use std::backtrace::Backtrace;
+use std::cell::{Cell, RefCell};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Once;
+
+thread_local! {
+    static GUARD_DEPTH: Cell<u32> = const { Cell::new(0) };
+    static LAST_REPORT: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Records panics raised inside [`catch_panic`] instead of printing them.
+/// Panics elsewhere still go to the previously installed hook.
+fn install_hook() {
+    static HOOK: Once = Once::new();
+    HOOK.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if GUARD_DEPTH.get() == 0 {
+                previous(info);
+                return;
+            }
+            let report = format!("{}\n{}", info, Backtrace::force_capture());
+            LAST_REPORT.set(Some(report));
+        }));
+    });
+}
+
+/// Runs `f`, returning the panic report (message and backtrace) if it panics.
+pub fn catch_panic<R>(f: impl FnOnce() -> R) -> Result<R, String> {
+    install_hook();
+    GUARD_DEPTH.set(GUARD_DEPTH.get() + 1);
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    GUARD_DEPTH.set(GUARD_DEPTH.get() - 1);
+
+    result.map_err(|payload| {
+        // Payloads re-raised with `resume_unwind` (plugin panics) skip the hook
+        LAST_REPORT.take().unwrap_or_else(|| {
+            payload
+                .downcast_ref::<String>()
+                .cloned()
+                .or_else(|| payload.downcast_ref::<&str>().map(|s| s.to_string()))
+                .unwrap_or_else(|| "panic with non-string payload".to_string())
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_catch_panic() {
+        assert_eq!(catch_panic(|| 1), Ok(1));
+        let report = catch_panic(|| panic!("boom")).unwrap_err();
+        assert!(report.contains("boom"));
+
+        // Re-raised payloads (plugin panics) report their message
+        let report = catch_panic(|| panic::resume_unwind(Box::new("plugin boom"))).unwrap_err();
+        assert_eq!(report, "plugin boom");
+
+        // Outside a guard the panic goes to the previous hook and isn't recorded
+        assert!(panic::catch_unwind(|| panic!("outside")).is_err());
+        assert_eq!(GUARD_DEPTH.get(), 0);
+        assert!(LAST_REPORT.take().is_none());
+    }
+}
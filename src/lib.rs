@@ -1,9 +1,14 @@
+pub mod abi;
+pub mod builtin;
 pub mod bus;
 pub mod config;
 pub mod event;
+pub mod external;
+pub mod isolation;
 pub mod plugin_loader;
 pub mod renderer;
 pub mod state;
+pub mod wake;
 pub mod widget;
 
 pub mod cli;
@@ -15,6 +20,7 @@ pub mod wayland;
 pub mod prelude {
     pub use crate::config::BarConfig;
     pub use crate::event::WidgetEvent;
+    pub use crate::export_widget;
     pub use crate::state::BarState;
     pub use crate::ui::container::{Container, ContainerVariant};
     pub use crate::ui::interaction::InteractionExt;
@@ -1,16 +1,14 @@
+use crate::modules::config::{bar_config_path, default_bar_config_path, hyprbar_config_dir};
 use anyhow::{Context, Result};
 use hyprink::config::Config;
 use hyprlog;
-use std::env;
 use std::fs;
 use std::os::unix::fs::PermissionsExt;
-use std::path::PathBuf;
 use std::sync::Arc;
 use tracing::debug;
 
 pub fn handle_autostart(config_ink: &Arc<Config>) -> Result<()> {
-    let home = env::var("HOME").unwrap_or_else(|_| ".".to_string());
-    let config_dir = PathBuf::from(home).join(".config").join("hyprbar");
+    let config_dir = hyprbar_config_dir();
 
     // Ensure config dir exists
     if !config_dir.exists() {
@@ -34,7 +32,19 @@ pub fn handle_autostart(config_ink: &Arc<Config>) -> Result<()> {
     } else {
         debug!("Autostart script does not exist. Creating...");
         // Simple shell script to start the daemon
-        let content = "#!/bin/sh\n# Hyprbar Autostart\n# Add this script to your window manager's startup\n\nhyprbar --start\n";
+        let mut command = "hyprbar --start".to_string();
+        let bar_config = bar_config_path();
+        // Pin a non-default config so the session start doesn't depend on the shell env
+        if bar_config != default_bar_config_path() {
+            command.push_str(&format!(
+                " --config {}",
+                shell_quote(&bar_config.to_string_lossy())
+            ));
+        }
+        let content = format!(
+            "#!/bin/sh\n# Hyprbar Autostart\n# Add this script to your window manager's startup\n\n{}\n",
+            command
+        );
         fs::write(&script_path, content).context("Failed to write autostart script")?;
 
         let mut perms = fs::metadata(&script_path)?.permissions();
@@ -53,9 +63,28 @@ pub fn handle_autostart(config_ink: &Arc<Config>) -> Result<()> {
         hyprlog::internal::info("AUTOSTART", &format!("Script location: {:?}", script_path));
         hyprlog::internal::info(
             "AUTOSTART",
-            "Example for Hyprland: exec-once = ~/.config/hyprbar/autostart.sh",
+            &format!(
+                "Example for Hyprland: exec-once = {}",
+                script_path.display()
+            ),
         );
     }
 
     Ok(())
 }
+
+/// Quotes `value` as a single shell word.
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("/home/me/bar.conf"), "'/home/me/bar.conf'");
+        assert_eq!(shell_quote("/tmp/it's here"), r"'/tmp/it'\''s here'");
+    }
+}
@@ -0,0 +1,116 @@
+use crate::config::BarConfig;
+use crate::modules::logging::*;
+use crate::modules::providers::Providers;
+use crate::renderer::BarRenderer;
+use crate::state::BarState;
+
+/// The widgets of one bar and the config they were built from. Its surface is
+/// the `WaylandState::bars` entry at the same index.
+pub struct Bar {
+    /// Index of the `[[bar]]` entry (0 without any).
+    pub entry: usize,
+    /// Output the bar was opened for; `None` if the compositor picks.
+    pub output: Option<String>,
+    pub state: BarState,
+    pub renderer: BarRenderer,
+}
+
+impl Bar {
+    /// Builds the widgets of one resolved bar config. `root` holds the top-level
+    /// config; its theme and event bus are shared by all bars.
+    pub fn new(
+        entry: usize,
+        output: Option<String>,
+        config: BarConfig,
+        root: &BarState,
+        providers: &Providers,
+    ) -> Self {
+        let state = BarState {
+            config_ink: root.config_ink.clone(),
+            config,
+            bus: root.bus.clone(),
+            config_revision: 0,
+        };
+        let renderer = BarRenderer::new(
+            100, // Resized once the surface is configured
+            state.config.window.height as u16,
+            &state.config,
+            &state.config_ink,
+            &providers.chain(&state.config),
+        );
+        let bar = Self {
+            entry,
+            output,
+            state,
+            renderer,
+        };
+        log_debug("BAR", &format!("Built bar {}", bar.label()));
+        bar
+    }
+
+    /// `#<entry>` plus the output, for logs.
+    pub fn label(&self) -> String {
+        match &self.output {
+            Some(output) => format!("#{} on {}", self.entry, output),
+            None => format!("#{}", self.entry),
+        }
+    }
+}
+
+/// The bars that should be open, in order: every entry gets one bar per
+/// connected output its `window.monitor` pattern matches, or a single bar
+/// placed by the compositor if the pattern is empty.
+pub fn wanted_bars(configs: &[BarConfig], outputs: &[String]) -> Vec<(usize, Option<String>)> {
+    configs
+        .iter()
+        .enumerate()
+        .flat_map(|(entry, config)| {
+            let monitor = config.window.monitor.as_str();
+            let outputs: Vec<Option<String>> = if monitor.is_empty() {
+                vec![None]
+            } else {
+                outputs
+                    .iter()
+                    .filter(|name| monitor_matches(monitor, name))
+                    .map(|name| Some(name.clone()))
+                    .collect()
+            };
+            outputs.into_iter().map(move |output| (entry, output))
+        })
+        .collect()
+}
+
+/// `window.monitor` is a glob over output names: "DP-1", "DP-*" or "*".
+fn monitor_matches(pattern: &str, name: &str) -> bool {
+    match glob::Pattern::new(pattern) {
+        Ok(pattern) => pattern.matches(name),
+        Err(_) => pattern == name,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wanted_bars() {
+        let bar = |monitor: &str| {
+            let mut config = BarConfig::default();
+            config.window.monitor = monitor.to_string();
+            config
+        };
+        let configs = [bar("*"), bar("HDMI-A-1"), bar(""), bar("DP-*")];
+        let outputs = ["eDP-1".to_string(), "DP-2".to_string()];
+
+        assert_eq!(
+            wanted_bars(&configs, &outputs),
+            vec![
+                (0, Some("eDP-1".to_string())),
+                (0, Some("DP-2".to_string())),
+                (2, None),
+                (3, Some("DP-2".to_string())),
+            ]
+        );
+        assert_eq!(wanted_bars(&configs, &[]), vec![(2, None)]);
+    }
+}
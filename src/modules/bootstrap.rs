@@ -1,18 +1,23 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use hyprink::config::Config;
 use std::sync::Arc;
 use tokio::signal::unix::{SignalKind, signal};
+use tokio::sync::broadcast::error::RecvError;
 use tokio::time::Duration;
 
+use crate::bus::{BusMessage, SIGNAL_CONCEAL, SIGNAL_REVEAL, SIGNAL_TOGGLE_REVEAL};
 use crate::modules::logging::*;
-use crate::{config::BarConfig, renderer::BarRenderer, state::BarState};
+use crate::{config::BarConfig, state::BarState};
 
+use crate::builtin::BuiltinProvider;
+use crate::external::ExternalProvider;
+use crate::modules::providers::Providers;
 use crate::plugin_loader::PluginManager;
 
 pub async fn init_application(
     config_ink: Arc<Config>,
     config: BarConfig,
-) -> Result<(Arc<Config>, BarConfig, BarState, PluginManager, BarRenderer)> {
+) -> Result<(Arc<Config>, BarState, Providers)> {
     log_debug("BOOTSTRAP", "Starting application initialization");
 
     // Pre-fetch log strings (Config consumed later)
@@ -45,9 +50,8 @@ pub async fn init_application(
         std::process::exit(0);
     });
 
-    // Initialize Bar State (now simpler)
-    log_debug("BOOTSTRAP", "Initializing bar state");
-    let bar_state = BarState::new(config_ink.clone(), config.clone());
+    // Bars are opened per output once Wayland is up; fail early on broken entries
+    config.bars().context("Invalid [[bar]] entry")?;
 
     // Initialize Plugin Manager
     log_debug("PLUGINS", "Initializing plugin manager");
@@ -55,49 +59,59 @@ pub async fn init_application(
 
     // Load plugins from ~/.local/share/hyprbar/widgets
     if let Some(data_dir) = dirs::data_local_dir() {
-        let widgets_dir = data_dir.join("hyprbar/widgets");
-        log_debug(
-            "PLUGINS",
-            &format!("Scanning for plugins in {:?}", widgets_dir),
-        );
-
-        if widgets_dir.exists() {
-            match std::fs::read_dir(&widgets_dir) {
-                Ok(entries) => {
-                    for entry in entries.flatten() {
-                        let path = entry.path();
-                        if path.extension().is_some_and(|ext| ext == "so") {
-                            log_debug("PLUGINS", &format!("Loading plugin: {:?}", path));
-                            if let Err(e) = plugin_manager.load_plugin(&path, true, true) {
-                                log_error("PLUGINS", &format!("Failed to load {:?}: {}", path, e));
-                            }
-                        }
-                    }
-                }
-                Err(e) => {
-                    log_warn("PLUGINS", &format!("Cannot read widgets dir: {}", e));
-                }
-            }
-        } else {
-            log_warn(
-                "PLUGINS",
-                &format!("Widgets directory does not exist: {:?}", widgets_dir),
-            );
-        }
+        plugin_manager.load_dir(&data_dir.join("hyprbar/widgets"), true);
     } else {
         log_error("PLUGINS", "Cannot determine local data directory");
     }
 
+    // External process widgets from [external.*]
+    let external_provider = ExternalProvider::new(&config);
+    if !config.external.is_empty() {
+        log_debug(
+            "EXTERNAL",
+            &format!("{} external widget(s) configured", config.external.len()),
+        );
+    }
+
     // Initialize Renderer
     log_debug("RENDER", "Initializing renderer");
-    let renderer = BarRenderer::new(
-        100, // TODO: This needs to be dynamic based on screen width
-        config.window.height as u16,
-        &config,
-        &bar_state.config_ink,
-        &plugin_manager,
+    let providers = Providers {
+        builtin: BuiltinProvider::new(),
+        plugins: plugin_manager,
+        external: external_provider,
+    };
+    log_debug(
+        "RENDER",
+        &format!("Widget providers: {:?}", config.layout.providers),
     );
+    // Bars share the root state's theme and event bus, so widgets can talk across them
+    let root = BarState::new(config_ink.clone(), config);
+
+    // SIGUSR1 (`hyprbar --toggle-reveal`) becomes a bus signal; reveal signals
+    // wake the bar loop, which may be sleeping while a bar is hidden
+    let bus = root.bus.clone();
+    tokio::spawn(async move {
+        let mut usr1 = signal(SignalKind::user_defined1()).unwrap();
+        let mut rx = bus.subscribe();
+        loop {
+            tokio::select! {
+                _ = usr1.recv() => {
+                    bus.send(BusMessage::Signal(SIGNAL_TOGGLE_REVEAL.to_string()));
+                }
+                msg = rx.recv() => match msg {
+                    Ok(BusMessage::Signal(signal))
+                        if [SIGNAL_REVEAL, SIGNAL_CONCEAL, SIGNAL_TOGGLE_REVEAL]
+                            .contains(&signal.as_str()) =>
+                    {
+                        crate::wake::wake()
+                    }
+                    Err(RecvError::Closed) => break,
+                    _ => {}
+                },
+            }
+        }
+    });
 
     log_info("BOOTSTRAP", "Application initialization complete");
-    Ok((config_ink, config, bar_state, plugin_manager, renderer))
+    Ok((config_ink, root, providers))
 }
@@ -98,6 +98,13 @@ pub async fn compile_widget(path: &Path, _config_ink: &Config) -> Result<()> {
 
     // 4. Parse Metadata & Append Code
     let source_content = fs::read_to_string(path).await?;
+    if !source_content.contains("export_widget!") {
+        return Err(anyhow::anyhow!(
+            "{:?} does not export a widget; add `export_widget!(MyWidget::new());` \
+             (the old `_create_widget` entry point is no longer loaded)",
+            path
+        ));
+    }
     let mut metadata_json = serde_json::json!({
         "name": "Unknown",
         "description": "",
@@ -202,7 +209,10 @@ pub extern "C" fn _plugin_metadata() -> *const std::ffi::c_char {{
 
     let output_name = format!("{}.so", widget_name);
     let target_path = widgets_dir.join(&output_name);
-    fs::copy(&artifact_path, &target_path).await?;
+    // Stage and rename like `install`: the running bar may have the old file mapped
+    let staging = widgets_dir.join(format!(".{}.tmp", output_name));
+    fs::copy(&artifact_path, &staging).await?;
+    fs::rename(&staging, &target_path).await?;
 
     hyprlog::internal::info(
         "BUILD",
@@ -0,0 +1,564 @@
+//! `hyprbar check`: validates the bar config without starting the bar.
+
+use crate::builtin;
+use crate::config::{
+    BarConfig, BarEntry, ExternalWidgetConfig, FaultConfig, LayoutConfig, LoggingConfig,
+    PopupConfig, ProviderKind, StyleConfig, WindowConfig, overlay, parse_module_spec,
+};
+use crate::modules::config::{ConfigSources, load_recursive_config};
+use crate::plugin_loader::PluginManager;
+use anyhow::Result;
+use serde::Deserialize;
+use serde::de::{self, Deserializer, Visitor};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use toml::Value;
+use toml::de::{DeTable, DeValue};
+
+/// Deserializes one top-level section on its own.
+type SectionCheck = fn(Value) -> Result<(), toml::de::Error>;
+
+/// Allowed values of string options, by key path.
+const ENUMS: &[(&str, &[&str])] = &[
+    ("window.anchor", &["top", "bottom", "left", "right"]),
+    ("window.layer", &["background", "bottom", "top", "overlay"]),
+    ("layout.strategy", &["grid", "flex"]),
+    (
+        "logging.level",
+        &["trace", "debug", "info", "warn", "error"],
+    ),
+    (
+        "style.animation.entry",
+        &[
+            "fade",
+            "slide_up",
+            "slide_down",
+            "slide_left",
+            "slide_right",
+        ],
+    ),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    pub file: PathBuf,
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Debug)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub location: Option<Location>,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(loc) = &self.location {
+            write!(f, "{}:{}:{}: ", loc.file.display(), loc.line, loc.column)?;
+        }
+        f.write_str(&self.message)
+    }
+}
+
+/// Runs [`check_config`] against the installed plugins and logs the result.
+/// Fails if the config has errors. The plugin registry is left as it is.
+pub fn run_check(path: &Path) -> Result<()> {
+    let mut plugins = PluginManager::new();
+    if let Some(data_dir) = dirs::data_local_dir() {
+        plugins.load_dir(&data_dir.join("hyprbar/widgets"), false);
+    }
+    let plugin_names: Vec<String> = plugins.widget_names().map(str::to_string).collect();
+
+    let diagnostics = check_config(path, &plugin_names);
+    let errors = diagnostics
+        .iter()
+        .filter(|d| d.severity == Severity::Error)
+        .count();
+    let warnings = diagnostics.len() - errors;
+
+    for diagnostic in &diagnostics {
+        match diagnostic.severity {
+            Severity::Error => hyprlog::internal::error("CHECK", &diagnostic.to_string()),
+            Severity::Warning => hyprlog::internal::warn("CHECK", &diagnostic.to_string()),
+        }
+    }
+
+    if errors > 0 {
+        anyhow::bail!(
+            "{} has {} error(s), {} warning(s)",
+            path.display(),
+            errors,
+            warnings
+        );
+    }
+    hyprlog::internal::info(
+        "CHECK",
+        &format!("{} is valid, {} warning(s)", path.display(), warnings),
+    );
+    Ok(())
+}
+
+/// Validates the config at `path` and everything it includes. `plugins` are the
+/// widget names installed plugins provide.
+pub fn check_config(path: &Path, plugins: &[String]) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let base_dir = path.parent().unwrap_or(Path::new("."));
+    let mut sources = ConfigSources::default();
+
+    let value = match load_recursive_config(path, base_dir, &mut sources) {
+        Ok(value) => value,
+        Err(e) => {
+            diagnostics.push(load_error(&e, sources.files.last().map(PathBuf::as_path)));
+            return diagnostics;
+        }
+    };
+    let locator = Locator::new(&sources.files);
+
+    // Sections are checked one by one so each reports its first error, not just
+    // the first section that fails
+    let sections: [(&str, SectionCheck); 8] = [
+        ("window", |v| v.try_into::<WindowConfig>().map(drop)),
+        ("style", |v| v.try_into::<StyleConfig>().map(drop)),
+        ("layout", |v| v.try_into::<LayoutConfig>().map(drop)),
+        ("logging", |v| v.try_into::<LoggingConfig>().map(drop)),
+        ("popup", |v| v.try_into::<PopupConfig>().map(drop)),
+        ("fault", |v| v.try_into::<FaultConfig>().map(drop)),
+        ("external", |v| {
+            v.try_into::<HashMap<String, ExternalWidgetConfig>>()
+                .map(drop)
+        }),
+        ("bar", |v| v.try_into::<Vec<BarEntry>>().map(drop)),
+    ];
+    for (section, deserialize) in sections {
+        if let Some(Err(e)) = value.get(section).cloned().map(deserialize) {
+            diagnostics.push(deserialize_error(&e, Some(section), &locator));
+        }
+    }
+    let config = match value.clone().try_into::<BarConfig>() {
+        Ok(config) => Some(config),
+        Err(e) => {
+            if diagnostics.is_empty() {
+                diagnostics.push(deserialize_error(&e, None, &locator));
+            }
+            None
+        }
+    };
+
+    check_unknown_keys(&value, "", &locator, &mut diagnostics);
+    check_enums(&value, "", &locator, &mut diagnostics);
+    if let Some(config) = &config {
+        check_modules(config, plugins, "", None, &locator, &mut diagnostics);
+        check_bars(config, &value, plugins, &locator, &mut diagnostics);
+    }
+    diagnostics
+}
+
+/// Checks every `[[bar]]` entry as it applies on top of the top-level sections.
+fn check_bars(
+    config: &BarConfig,
+    value: &Value,
+    plugins: &[String],
+    locator: &Locator,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let entries = value.get("bar").and_then(Value::as_array);
+    for (idx, entry) in config.bar.iter().enumerate() {
+        let prefix = format!("bar.{}.", idx);
+        let errors = [
+            ("window", overlay(&config.window, &entry.window).err()),
+            ("style", overlay(&config.style, &entry.style).err()),
+            ("layout", overlay(&config.layout, &entry.layout).err()),
+            ("popup", overlay(&config.popup, &entry.popup).err()),
+        ];
+        for (section, error) in errors {
+            if let Some(e) = error {
+                let section = format!("{}{}", prefix, section);
+                diagnostics.push(deserialize_error(&e, Some(&section), locator));
+            }
+        }
+
+        if let Some(entry_value) = entries.and_then(|e| e.get(idx)) {
+            let fields = struct_fields::<BarEntry>();
+            let unknown = entry_value
+                .as_table()
+                .into_iter()
+                .flat_map(|t| t.keys())
+                .filter(|k| !fields.contains(&k.as_str()));
+            for key in unknown {
+                let path = format!("{}{}", prefix, key);
+                diagnostics.push(Diagnostic {
+                    severity: Severity::Warning,
+                    message: format!("unknown key `{}`", path),
+                    location: locator.find(&path),
+                });
+            }
+            check_unknown_keys(entry_value, &prefix, locator, diagnostics);
+            check_enums(entry_value, &prefix, locator, diagnostics);
+        }
+
+        if let Ok(layout) = overlay(&config.layout, &entry.layout) {
+            let bar = BarConfig {
+                layout,
+                ..config.clone()
+            };
+            check_modules(
+                &bar,
+                plugins,
+                &prefix,
+                Some(&entry.layout),
+                locator,
+                diagnostics,
+            );
+        }
+    }
+}
+
+fn deserialize_error(
+    error: &toml::de::Error,
+    section: Option<&str>,
+    locator: &Locator,
+) -> Diagnostic {
+    // Value errors carry no span, only the key path on their last line
+    let text = error.to_string();
+    let message = text.lines().next().unwrap_or_default();
+    let key = text
+        .lines()
+        .find_map(|l| l.strip_prefix("in `")?.strip_suffix('`'));
+    let path = match (section, key) {
+        (Some(section), Some(key)) => format!("{}.{}", section, key),
+        (Some(section), None) => section.to_string(),
+        (None, key) => key.unwrap_or_default().to_string(),
+    };
+    Diagnostic {
+        severity: Severity::Error,
+        message: if path.is_empty() {
+            message.to_string()
+        } else {
+            format!("`{}`: {}", path, message)
+        },
+        location: locator.find_value(&path),
+    }
+}
+
+fn load_error(error: &anyhow::Error, file: Option<&Path>) -> Diagnostic {
+    let toml_error = error
+        .chain()
+        .find_map(|e| e.downcast_ref::<toml::de::Error>());
+
+    let location = toml_error.and_then(|e| {
+        let file = file?;
+        let content = std::fs::read_to_string(file).ok()?;
+        let (line, column) = line_column(&content, e.span()?.start);
+        Some(Location {
+            file: file.to_path_buf(),
+            line,
+            column,
+        })
+    });
+    let message = match toml_error {
+        Some(e) if location.is_some() => e.message().trim_end().to_string(),
+        _ => format!("{:#}", error),
+    };
+    Diagnostic {
+        severity: Severity::Error,
+        message,
+        location,
+    }
+}
+
+/// Unknown keys in the sections of `value`; `prefix` is its own key path.
+fn check_unknown_keys(
+    value: &Value,
+    prefix: &str,
+    locator: &Locator,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let sections = [
+        ("window", struct_fields::<WindowConfig>()),
+        ("style", struct_fields::<StyleConfig>()),
+        ("layout", struct_fields::<LayoutConfig>()),
+        ("logging", struct_fields::<LoggingConfig>()),
+        ("popup", struct_fields::<PopupConfig>()),
+    ];
+    for (section, fields) in sections {
+        let Some(table) = value.get(section).and_then(Value::as_table) else {
+            continue;
+        };
+        for key in table.keys().filter(|k| !fields.contains(&k.as_str())) {
+            let path = format!("{}{}.{}", prefix, section, key);
+            diagnostics.push(Diagnostic {
+                severity: Severity::Warning,
+                message: format!("unknown key `{}`", path),
+                location: locator.find(&path),
+            });
+        }
+    }
+}
+
+fn check_enums(value: &Value, prefix: &str, locator: &Locator, diagnostics: &mut Vec<Diagnostic>) {
+    for (key, allowed) in ENUMS {
+        let path = format!("{}{}", prefix, key);
+        let found = key
+            .split('.')
+            .try_fold(value, |v, key| v.get(key))
+            .and_then(Value::as_str);
+        if let Some(found) = found
+            && !allowed.contains(&found)
+        {
+            diagnostics.push(Diagnostic {
+                severity: Severity::Error,
+                message: format!(
+                    "`{}` is \"{}\", expected one of: {}",
+                    path,
+                    found,
+                    allowed.join(", ")
+                ),
+                location: locator.find_value(&path),
+            });
+        }
+    }
+}
+
+/// Module names that no provider of the bar can create. For a `[[bar]]`,
+/// `overrides` is its layout table; lists it inherits are checked at the top level.
+fn check_modules(
+    config: &BarConfig,
+    plugins: &[String],
+    prefix: &str,
+    overrides: Option<&toml::Table>,
+    locator: &Locator,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let providers = &config.layout.providers;
+    let resolves = |name: &str| {
+        providers.iter().any(|kind| match kind {
+            ProviderKind::Builtin => builtin::WIDGETS.contains(&name),
+            ProviderKind::Plugin => plugins.iter().any(|p| p == name),
+            ProviderKind::External => config.external.contains_key(name),
+        })
+    };
+
+    let sections = [
+        ("modules_left", &config.layout.modules_left),
+        ("modules_center", &config.layout.modules_center),
+        ("modules_right", &config.layout.modules_right),
+    ];
+    for (key, specs) in sections {
+        let inherited = overrides.is_some_and(|t| !t.contains_key(key));
+        // Already checked at the top level, unless the bar resolves names differently
+        if inherited && !overrides.is_some_and(|t| t.contains_key("providers")) {
+            continue;
+        }
+        let path = if inherited {
+            format!("layout.{}", key)
+        } else {
+            format!("{}layout.{}", prefix, key)
+        };
+        for (idx, spec) in specs.iter().enumerate() {
+            let (name, _) = parse_module_spec(spec);
+            if resolves(name) {
+                continue;
+            }
+            diagnostics.push(Diagnostic {
+                severity: Severity::Error,
+                message: format!(
+                    "`{}`: no built-in, installed or external widget named \"{}\"",
+                    path, name
+                ),
+                location: locator.find(&format!("{}.{}", path, idx)),
+            });
+        }
+    }
+}
+
+/// Finds where a key path is defined. Includes override the files that include
+/// them, so the last file defining the key wins.
+struct Locator {
+    files: Vec<(PathBuf, String)>,
+}
+
+impl Locator {
+    fn new(files: &[PathBuf]) -> Self {
+        let files = files
+            .iter()
+            .filter_map(|f| Some((f.clone(), std::fs::read_to_string(f).ok()?)))
+            .collect();
+        Self { files }
+    }
+
+    /// Location of the key `path`; dotted, numeric segments index into arrays.
+    fn find(&self, path: &str) -> Option<Location> {
+        self.locate(path, |(key, _)| key)
+    }
+
+    /// Location of the value assigned to `path`.
+    fn find_value(&self, path: &str) -> Option<Location> {
+        self.locate(path, |(_, value)| value)
+    }
+
+    fn locate(&self, path: &str, pick: fn((usize, usize)) -> usize) -> Option<Location> {
+        if path.is_empty() {
+            return None;
+        }
+        self.files.iter().rev().find_map(|(file, content)| {
+            let offset = pick(find_offsets(content, path)?);
+            let (line, column) = line_column(content, offset);
+            Some(Location {
+                file: file.clone(),
+                line,
+                column,
+            })
+        })
+    }
+}
+
+/// Byte offsets of the key and the value at `path`.
+fn find_offsets(content: &str, path: &str) -> Option<(usize, usize)> {
+    let root = DeTable::parse(content).ok()?;
+    let mut table = root.get_ref();
+    let mut segments = path.split('.').peekable();
+
+    while let Some(segment) = segments.next() {
+        let (key, value) = table.iter().find(|(k, _)| *k.get_ref() == segment)?;
+        let mut value = value;
+        // Array elements: take the index if one follows
+        while let (DeValue::Array(items), Some(idx)) = (
+            value.get_ref(),
+            segments.peek().and_then(|s| s.parse::<usize>().ok()),
+        ) {
+            segments.next();
+            value = items.get(idx)?;
+            if segments.peek().is_none() {
+                return Some((value.span().start, value.span().start));
+            }
+        }
+        match (value.get_ref(), segments.peek()) {
+            (_, None) => return Some((key.span().start, value.span().start)),
+            (DeValue::Table(inner), Some(_)) => table = inner,
+            _ => return None,
+        }
+    }
+    None
+}
+
+/// 1-based line and column (in chars) of a byte offset.
+fn line_column(content: &str, offset: usize) -> (usize, usize) {
+    let before = &content[..offset.min(content.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().map_or(0, |l| l.chars().count()) + 1;
+    (line, column)
+}
+
+/// Field names a serde-derived struct accepts, taken from its `Deserialize` impl.
+fn struct_fields<'de, T: Deserialize<'de>>() -> &'static [&'static str] {
+    struct Probe<'a>(&'a mut &'static [&'static str]);
+
+    impl<'de> Deserializer<'de> for Probe<'_> {
+        type Error = de::value::Error;
+
+        fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
+            Err(de::Error::custom("only structs are probed"))
+        }
+
+        fn deserialize_struct<V: Visitor<'de>>(
+            self,
+            _name: &'static str,
+            fields: &'static [&'static str],
+            _visitor: V,
+        ) -> Result<V::Value, Self::Error> {
+            *self.0 = fields;
+            Err(de::Error::custom("probed"))
+        }
+
+        serde::forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+            bytes byte_buf option unit unit_struct newtype_struct seq tuple
+            tuple_struct map enum identifier ignored_any
+        }
+    }
+
+    let mut fields: &'static [&'static str] = &[];
+    let _ = T::deserialize(Probe(&mut fields));
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("hyprbar.conf");
+        std::fs::write(
+            &path,
+            "[window]\nheight = 30\nanchor = \"east\"\nhieght = 2\n\n\
+             [layout]\nmodules_left = [\"separator\", \"nope#a\"]\n",
+        )
+        .unwrap();
+
+        let diagnostics = check_config(&path, &[]);
+        let messages: Vec<String> = diagnostics.iter().map(|d| d.to_string()).collect();
+        let at = |line: usize, column: usize| format!("hyprbar.conf:{}:{}: ", line, column);
+
+        assert_eq!(diagnostics.len(), 3, "{:?}", messages);
+        assert!(
+            messages
+                .iter()
+                .any(|m| m.contains(&at(4, 1)) && m.contains("window.hieght"))
+        );
+        assert!(
+            messages
+                .iter()
+                .any(|m| m.contains(&at(3, 10)) && m.contains("top, bottom, left, right"))
+        );
+        assert!(
+            messages
+                .iter()
+                .any(|m| m.contains(&at(7, 30)) && m.contains("\"nope\""))
+        );
+    }
+
+    #[test]
+    fn test_check_bars() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("hyprbar.conf");
+        std::fs::write(
+            &path,
+            "[window]\nheight = 30\nanchor = \"top\"\n\n\
+             [[bar]]\nwindow = { monitor = \"DP-1\", anchor = \"up\" }\n\n\
+             [[bar]]\nmonitr = \"HDMI-A-1\"\n[bar.layout]\nmodules_right = [\"nope\"]\n",
+        )
+        .unwrap();
+
+        let diagnostics = check_config(&path, &[]);
+        let messages: Vec<String> = diagnostics.iter().map(|d| d.to_string()).collect();
+        assert_eq!(diagnostics.len(), 3, "{:?}", messages);
+        assert!(messages[0].contains(":6:39: `bar.0.window.anchor`"));
+        assert!(messages[1].contains(":9:1: unknown key `bar.1.monitr`"));
+        assert!(messages[2].contains(":11:18: `bar.1.layout.modules_right`"));
+    }
+
+    #[test]
+    fn test_check_syntax_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("hyprbar.conf");
+        std::fs::write(&path, "include = [\"extra.conf\"]\n").unwrap();
+        std::fs::write(dir.path().join("extra.conf"), "[window]\nheight = \n").unwrap();
+
+        let diagnostics = check_config(&path, &[]);
+        assert_eq!(diagnostics.len(), 1);
+        let location = diagnostics[0].location.as_ref().unwrap();
+        assert!(location.file.ends_with("extra.conf"));
+        assert_eq!(location.line, 2);
+    }
+}
@@ -11,6 +11,10 @@ pub struct Cli {
     #[arg(long, global = true)]
     pub debug: bool,
 
+    /// Bar config file (overrides $HYPRBAR_CONFIG and the XDG default)
+    #[arg(long, global = true, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
     /// Start the bar daemon process
     #[arg(long)]
     pub start: bool,
@@ -23,6 +27,10 @@ pub struct Cli {
     #[arg(long)]
     pub restart: bool,
 
+    /// Keep auto-hidden bars shown, or let them hide again
+    #[arg(long)]
+    pub toggle_reveal: bool,
+
     /// Manage autostart settings (generates script)
     #[arg(long)]
     pub autostart: bool,
@@ -52,6 +60,12 @@ pub enum Commands {
         path: PathBuf,
     },
 
+    /// Validate the config file and its includes
+    Check {
+        /// Config file to check (defaults to the bar's config)
+        path: Option<PathBuf>,
+    },
+
     /// List installed plugins
     #[command(alias = "ls")]
     List,
@@ -2,28 +2,19 @@ use crate::config::BarConfig;
 use anyhow::{Context, Result};
 use hyprink::config::Config;
 use std::env;
+use std::ffi::OsString;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 use toml::Value;
 
 pub fn load_bar_config(_config_ink: &Config) -> BarConfig {
-    let home = env::var("HOME").unwrap_or_else(|_| ".".to_string());
-    let config_dir = PathBuf::from(home).join(".config").join("hypr");
-    let config_path = config_dir.join("hyprbar.conf");
+    let config_path = bar_config_path();
 
     if config_path.exists() {
-        match load_recursive_config(&config_path, &config_dir) {
-            Ok(value) => match value.try_into() {
-                Ok(cfg) => return cfg,
-                Err(e) => hyprlog::internal::error(
-                    "CONFIG",
-                    &format!("Failed to deserialize config: {}", e),
-                ),
-            },
-            Err(e) => hyprlog::internal::error(
-                "CONFIG",
-                &format!("Failed to load config with includes: {}", e),
-            ),
+        match read_bar_config(&config_path) {
+            Ok((cfg, _)) => return cfg,
+            Err(e) => hyprlog::internal::error("CONFIG", &format!("{:#}", e)),
         }
     }
 
@@ -31,7 +22,118 @@ pub fn load_bar_config(_config_ink: &Config) -> BarConfig {
     BarConfig::default()
 }
 
-fn load_recursive_config(path: &Path, base_dir: &Path) -> Result<Value> {
+/// Set from `--config`; takes precedence over the environment.
+static CONFIG_PATH_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+
+/// Uses `path` as the bar config for the rest of the process. Only the first
+/// call has an effect.
+pub fn set_config_path(path: &Path) {
+    let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let _ = CONFIG_PATH_OVERRIDE.set(path);
+}
+
+/// The bar config every module reads: `--config`, else `$HYPRBAR_CONFIG`,
+/// else `$XDG_CONFIG_HOME/hypr/hyprbar.conf` (`~/.config` when unset).
+pub fn bar_config_path() -> PathBuf {
+    resolve_bar_config_path(CONFIG_PATH_OVERRIDE.get().map(PathBuf::as_path), |key| {
+        env::var_os(key)
+    })
+}
+
+/// The default location, ignoring `--config` and `$HYPRBAR_CONFIG`.
+pub fn default_bar_config_path() -> PathBuf {
+    config_home(|key| env::var_os(key))
+        .join("hypr")
+        .join("hyprbar.conf")
+}
+
+/// Directory for hyprbar's own files such as `autostart.sh`.
+pub fn hyprbar_config_dir() -> PathBuf {
+    config_home(|key| env::var_os(key)).join("hyprbar")
+}
+
+fn resolve_bar_config_path(cli: Option<&Path>, var: impl Fn(&str) -> Option<OsString>) -> PathBuf {
+    if let Some(path) = cli {
+        return path.to_path_buf();
+    }
+    if let Some(path) = var("HYPRBAR_CONFIG").filter(|p| !p.is_empty()) {
+        return PathBuf::from(path);
+    }
+    config_home(var).join("hypr").join("hyprbar.conf")
+}
+
+/// `$XDG_CONFIG_HOME`, or `~/.config`. Relative values are ignored as the
+/// XDG spec requires.
+fn config_home(var: impl Fn(&str) -> Option<OsString>) -> PathBuf {
+    var("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .unwrap_or_else(|| {
+            let home = var("HOME").unwrap_or_else(|| ".".into());
+            PathBuf::from(home).join(".config")
+        })
+}
+
+/// Files a config was assembled from, plus its `include` globs so files added
+/// later can be picked up too.
+#[derive(Debug, Default, Clone)]
+pub struct ConfigSources {
+    pub files: Vec<PathBuf>,
+    pub patterns: Vec<glob::Pattern>,
+}
+
+impl ConfigSources {
+    /// True if a change to `path` can affect the config.
+    pub fn contains(&self, path: &Path) -> bool {
+        self.files.iter().any(|f| f == path) || self.patterns.iter().any(|p| p.matches_path(path))
+    }
+
+    /// Directories to watch: those of every file and of every glob whose
+    /// directory part is a plain path.
+    pub fn dirs(&self) -> Vec<PathBuf> {
+        let pattern_dirs = self.patterns.iter().filter_map(|p| {
+            let parent = Path::new(p.as_str()).parent()?;
+            let literal = !parent.to_string_lossy().contains(['*', '?', '[', ']']);
+            literal.then(|| parent.to_path_buf())
+        });
+        let mut dirs: Vec<PathBuf> = Vec::new();
+        for dir in self
+            .files
+            .iter()
+            .filter_map(|f| f.parent().map(Path::to_path_buf))
+            .chain(pattern_dirs)
+        {
+            if !dirs.contains(&dir) {
+                dirs.push(dir);
+            }
+        }
+        dirs
+    }
+}
+
+/// Loads the bar config at `path` with all includes. Errors name the file and,
+/// for syntax errors, the line and column.
+pub fn read_bar_config(path: &Path) -> Result<(BarConfig, ConfigSources)> {
+    let base_dir = path.parent().unwrap_or(Path::new("."));
+    let mut sources = ConfigSources::default();
+    let value = load_recursive_config(path, base_dir, &mut sources)?;
+    let config: BarConfig = value
+        .try_into()
+        .with_context(|| format!("Failed to deserialize config {:?}", path))?;
+    config
+        .bars()
+        .with_context(|| format!("Invalid [[bar]] entry in {:?}", path))?;
+    Ok((config, sources))
+}
+
+/// Reads `path` and merges its `include`s into it, recording every file read in
+/// `sources`. On error the failing file is the last one in `sources.files`.
+pub fn load_recursive_config(
+    path: &Path,
+    base_dir: &Path,
+    sources: &mut ConfigSources,
+) -> Result<Value> {
+    sources.files.push(path.to_path_buf());
     let content = fs::read_to_string(path)
         .with_context(|| format!("Failed to read config file: {:?}", path))?;
 
@@ -55,6 +157,9 @@ fn load_recursive_config(path: &Path, base_dir: &Path) -> Result<Value> {
         // If it looks like a glob (or just a path), try globbing it
         // We use pattern_path.to_string_lossy() because glob::glob expects a string pattern
         let pattern = pattern_path.to_string_lossy();
+        if let Ok(compiled) = glob::Pattern::new(&pattern) {
+            sources.patterns.push(compiled);
+        }
 
         match glob::glob(&pattern) {
             Ok(paths) => {
@@ -64,7 +169,7 @@ fn load_recursive_config(path: &Path, base_dir: &Path) -> Result<Value> {
                         Ok(path) => {
                             found_any = true;
                             // Recursively load the included file
-                            let included_value = load_recursive_config(&path, base_dir)?;
+                            let included_value = load_recursive_config(&path, base_dir, sources)?;
                             merge_toml_values(&mut root_value, included_value);
                         }
                         Err(e) => {
@@ -139,3 +244,50 @@ pub fn get_pid_file_path() -> PathBuf {
 
     runtime_dir.join("hyprbar.pid")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_bar_config_path() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |key: &str| {
+                vars.iter()
+                    .find(|(k, _)| *k == key)
+                    .map(|(_, v)| OsString::from(v))
+            }
+        };
+
+        let home = env(&[("HOME", "/home/u")]);
+        assert_eq!(
+            resolve_bar_config_path(None, home),
+            Path::new("/home/u/.config/hypr/hyprbar.conf")
+        );
+
+        let xdg = env(&[("HOME", "/home/u"), ("XDG_CONFIG_HOME", "/cfg")]);
+        assert_eq!(
+            resolve_bar_config_path(None, xdg),
+            Path::new("/cfg/hypr/hyprbar.conf")
+        );
+
+        let relative_xdg = env(&[("HOME", "/home/u"), ("XDG_CONFIG_HOME", "cfg")]);
+        assert_eq!(
+            resolve_bar_config_path(None, relative_xdg),
+            Path::new("/home/u/.config/hypr/hyprbar.conf")
+        );
+
+        let var = env(&[
+            ("XDG_CONFIG_HOME", "/cfg"),
+            ("HYPRBAR_CONFIG", "/etc/bar.conf"),
+        ]);
+        assert_eq!(
+            resolve_bar_config_path(None, var),
+            Path::new("/etc/bar.conf")
+        );
+        assert_eq!(
+            resolve_bar_config_path(Some(Path::new("/tmp/cli.conf")), var),
+            Path::new("/tmp/cli.conf")
+        );
+    }
+}
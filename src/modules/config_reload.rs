@@ -0,0 +1,267 @@
+use crate::config::BarConfig;
+use crate::external::ExternalProvider;
+use crate::modules::bars::Bar;
+use crate::modules::config::{ConfigSources, read_bar_config};
+use crate::modules::fs_watch::DirWatcher;
+use crate::modules::logging::*;
+use crate::modules::providers::Providers;
+use crate::modules::wayland_integration::{close_bar, sync_bars};
+use crate::renderer::WidgetSection;
+use crate::state::BarState;
+use crate::wayland::state::{BarSurface, WaylandState};
+use crate::wayland::{configure_bar_surface, destroy_popup_surface};
+use anyhow::{Context, Result};
+use smithay_client_toolkit::reexports::client::QueueHandle;
+use smithay_client_toolkit::shell::WaylandSurface;
+use std::os::fd::BorrowedFd;
+use std::path::{Path, PathBuf};
+
+/// Watches the bar config and every file it includes, and re-reads it on change.
+pub struct ConfigReloader {
+    path: PathBuf,
+    watcher: DirWatcher,
+    sources: ConfigSources,
+}
+
+impl ConfigReloader {
+    pub fn new(path: &Path) -> Result<Self> {
+        let dir = path
+            .parent()
+            .with_context(|| format!("Config path {:?} has no directory", path))?;
+        let mut reloader = Self {
+            path: path.to_path_buf(),
+            watcher: DirWatcher::new(dir)?,
+            sources: ConfigSources::default(),
+        };
+        if let Ok((_, sources)) = read_bar_config(path) {
+            reloader.set_sources(sources);
+        }
+        log_debug("CONFIG", &format!("Watching {:?} for changes", path));
+        Ok(reloader)
+    }
+
+    /// Becomes readable when config files change; polled by the event loop.
+    pub fn fd(&self) -> BorrowedFd<'_> {
+        self.watcher.fd()
+    }
+
+    fn set_sources(&mut self, sources: ConfigSources) {
+        for dir in sources.dirs() {
+            if let Err(e) = self.watcher.watch(&dir) {
+                log_warn("CONFIG", &format!("{:#}", e));
+            }
+        }
+        self.sources = sources;
+    }
+
+    /// Returns the reloaded config if one of its files changed. A config that
+    /// fails to load is logged and `None` is returned, so the running one stays.
+    pub fn poll(&mut self) -> Option<BarConfig> {
+        let changed = self.watcher.changed_files();
+        let path = changed
+            .iter()
+            .find(|p| **p == self.path || self.sources.contains(p))?;
+        log_info("CONFIG", &format!("{:?} changed, reloading", path));
+
+        match read_bar_config(&self.path) {
+            Ok((config, sources)) => {
+                self.set_sources(sources);
+                Some(config)
+            }
+            Err(e) => {
+                log_error("CONFIG", &format!("{:#}", e));
+                log_warn("CONFIG", "Keeping the current config");
+                None
+            }
+        }
+    }
+}
+
+/// What a reload has to rebuild. Everything else (colors, widget options) is
+/// read from `BarState.config` on the next frame.
+#[derive(Debug, Default, PartialEq)]
+pub struct ConfigChanges {
+    /// Sections whose widgets are recreated.
+    pub sections: Vec<WidgetSection>,
+    /// Font family or size changed; the text renderer is recreated.
+    pub font: bool,
+    /// Thickness, anchor edge or placement changed; the layer surface is reconfigured.
+    pub surface: bool,
+    /// Output pattern changed; the bar is closed and reopened where it matches.
+    pub monitor: bool,
+}
+
+impl ConfigChanges {
+    pub fn between(old: &BarConfig, new: &BarConfig) -> Self {
+        // These affect how every widget instance is created
+        let all = old.layout.providers != new.layout.providers
+            || old.external != new.external
+            || old.fault != new.fault;
+        let sections = [
+            (
+                WidgetSection::Left,
+                &old.layout.modules_left,
+                &new.layout.modules_left,
+            ),
+            (
+                WidgetSection::Center,
+                &old.layout.modules_center,
+                &new.layout.modules_center,
+            ),
+            (
+                WidgetSection::Right,
+                &old.layout.modules_right,
+                &new.layout.modules_right,
+            ),
+        ]
+        .into_iter()
+        .filter(|(_, old, new)| all || old != new)
+        .map(|(section, _, _)| section)
+        .collect();
+
+        let (old_font_size, old_height) = old.window.calculate_dimensions();
+        let (new_font_size, new_height) = new.window.calculate_dimensions();
+
+        Self {
+            sections,
+            font: old.style.font != new.style.font || old_font_size != new_font_size,
+            surface: old_height != new_height
+                || old.window.anchor != new.window.anchor
+                || old.window.layer != new.window.layer
+                || old.window.margins() != new.window.margins()
+                || old.window.exclusive != new.window.exclusive
+                || old.window.width != new.window.width
+                || old.window.autohide != new.window.autohide
+                || old.window.autohide_trigger != new.window.autohide_trigger,
+            monitor: old.window.monitor != new.window.monitor,
+        }
+    }
+}
+
+/// Swaps a reloaded config into the running bars. Bars whose entry is gone or
+/// whose `window.monitor` changed are closed, the rest are updated in place,
+/// and bars for new entries or newly matched outputs are opened.
+pub fn apply_config(
+    config: BarConfig,
+    root: &mut BarState,
+    bars: &mut Vec<Bar>,
+    providers: &mut Providers,
+    wayland_state: &mut WaylandState,
+    qh: &QueueHandle<WaylandState>,
+) {
+    let bar_configs = match config.bars() {
+        Ok(bar_configs) => bar_configs,
+        Err(e) => {
+            log_error("CONFIG", &format!("Invalid [[bar]] entry: {}", e));
+            log_warn("CONFIG", "Keeping the current config");
+            return;
+        }
+    };
+
+    if config.external != root.config.external || config.fault != root.config.fault {
+        providers.external = ExternalProvider::new(&config);
+    }
+    root.config = config;
+    root.config_revision += 1;
+
+    while let Some(index) = bars.iter().position(|b| {
+        bar_configs
+            .get(b.entry)
+            .is_none_or(|c| c.window.monitor != b.state.config.window.monitor)
+    }) {
+        close_bar(wayland_state, bars, index);
+    }
+    for (bar, surface) in bars.iter_mut().zip(wayland_state.bars.iter_mut()) {
+        apply_bar_config(bar_configs[bar.entry].clone(), bar, providers, surface);
+    }
+    sync_bars(wayland_state, qh, root, bars, providers);
+    log_info("CONFIG", "Config reloaded");
+}
+
+/// Applies a reloaded config to one bar, rebuilding only what changed.
+fn apply_bar_config(
+    config: BarConfig,
+    bar: &mut Bar,
+    providers: &Providers,
+    surface: &mut BarSurface,
+) {
+    let changes = ConfigChanges::between(&bar.state.config, &config);
+    log_debug(
+        "CONFIG",
+        &format!("Bar {} reload changes: {:?}", bar.label(), changes),
+    );
+
+    let bar_state = &mut bar.state;
+    let renderer = &mut bar.renderer;
+    bar_state.config = config;
+    bar_state.config_revision += 1;
+    let config = &bar_state.config;
+
+    if !changes.sections.is_empty() {
+        let provider = providers.chain(config);
+        for &section in &changes.sections {
+            let names = match section {
+                WidgetSection::Left => &config.layout.modules_left,
+                WidgetSection::Center => &config.layout.modules_center,
+                WidgetSection::Right => &config.layout.modules_right,
+            };
+            renderer.rebuild_section(section, names, config, &bar_state.config_ink, &provider);
+        }
+    }
+
+    if changes.font {
+        let (font_size, _) = config.window.calculate_dimensions();
+        match surface.set_font(&config.style.font, font_size) {
+            Ok(()) => {
+                log_info("CONFIG", &format!("Font reloaded ({}px)", font_size));
+            }
+            Err(e) => log_error(
+                "CONFIG",
+                &format!("Failed to load font, keeping the old one: {:#}", e),
+            ),
+        }
+    }
+
+    if changes.surface {
+        let (_, height) = config.window.calculate_dimensions();
+        configure_bar_surface(&surface.layer, &config.window, surface.autohide.hidden);
+        surface.layer.commit();
+        // Popups are positioned relative to the old edge
+        renderer.clear_active_popup();
+        log_info(
+            "CONFIG",
+            &format!("Bar resized to {}px ({})", height, config.window.anchor),
+        );
+    }
+
+    if renderer.active_popup().is_none() {
+        destroy_popup_surface(surface);
+    }
+    // Colors may have changed without any cell changing
+    surface.invalidate_frames();
+    surface.redraw_requested = true;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_changes() {
+        let old = BarConfig::default();
+        let mut new = old.clone();
+        assert_eq!(ConfigChanges::between(&old, &new), ConfigChanges::default());
+
+        new.layout.modules_right = vec!["datetime".to_string()];
+        new.style.bg = "#000000".to_string();
+        let changes = ConfigChanges::between(&old, &new);
+        assert_eq!(changes.sections, vec![WidgetSection::Right]);
+        assert!(!changes.font && !changes.surface);
+
+        new.fault.retry = false;
+        new.window.anchor = "bottom".to_string();
+        let changes = ConfigChanges::between(&old, &new);
+        assert_eq!(changes.sections.len(), 3);
+        assert!(changes.surface);
+    }
+}
@@ -1,4 +1,4 @@
-use crate::modules::config::{get_pid_file_path, get_socket_path};
+use crate::modules::config::{bar_config_path, get_pid_file_path, get_socket_path};
 use anyhow::{Context, Result};
 use hyprink::config::Config;
 use hyprlog;
@@ -44,10 +44,12 @@ pub async fn spawn_bar_daemon(config_ink: &Arc<Config>, debug: bool) -> Result<(
             fs::remove_file(&pid_file_path).ok(); // Ignore error if cannot remove
         }
     }
-    // Spawn self with internal-run and debug flag
-    // Spawn self with internal-run
+    // Spawn self with internal-run, pinned to the config this process resolved
     let mut command = Command::new(self_exe);
-    command.arg("internal-run");
+    command
+        .arg("internal-run")
+        .arg("--config")
+        .arg(bar_config_path());
 
     if debug {
         command.arg("--debug");
@@ -118,6 +120,32 @@ pub async fn terminate_bar_daemon(config_ink: &Arc<Config>) -> Result<()> {
     Ok(())
 }
 
+/// Asks the running daemon to toggle keeping auto-hidden bars shown (SIGUSR1).
+pub fn toggle_reveal(config_ink: &Arc<Config>) -> Result<()> {
+    let pid_file_path = get_pid_file_path();
+
+    if !pid_file_path.exists() {
+        let msg = config_ink
+            .layout
+            .labels
+            .get("bar_not_found")
+            .cloned()
+            .unwrap_or_else(|| "no daemon found".to_string());
+        hyprlog::internal::info("DAEMON", &msg);
+        return Ok(());
+    }
+
+    let pid_str = fs::read_to_string(&pid_file_path)
+        .context(format!("Failed to read PID from {:?}", pid_file_path))?;
+    let pid = pid_str
+        .trim()
+        .parse::<i32>()
+        .context(format!("Failed to parse PID from '{}'", pid_str))?;
+    signal::kill(Pid::from_raw(pid), Signal::SIGUSR1)
+        .context(format!("Failed to send SIGUSR1 to PID {}", pid))?;
+    Ok(())
+}
+
 pub async fn restart_bar_daemon(config_ink: &Arc<Config>, debug: bool) -> Result<()> {
     let msg = config_ink
         .layout
@@ -0,0 +1,69 @@
+use anyhow::{Context, Result};
+use nix::errno::Errno;
+use nix::sys::inotify::{AddWatchFlags, InitFlags, Inotify, WatchDescriptor};
+use std::os::fd::{AsFd, BorrowedFd};
+use std::path::{Path, PathBuf};
+
+/// Non-blocking inotify watch on one or more directories.
+/// Reports files that were finished writing or moved into place.
+pub struct DirWatcher {
+    inotify: Inotify,
+    dirs: Vec<(WatchDescriptor, PathBuf)>,
+}
+
+impl DirWatcher {
+    pub fn new(dir: &Path) -> Result<Self> {
+        let inotify = Inotify::init(InitFlags::IN_NONBLOCK | InitFlags::IN_CLOEXEC)
+            .context("Failed to initialize inotify")?;
+        let mut watcher = Self {
+            inotify,
+            dirs: Vec::new(),
+        };
+        watcher.watch(dir)?;
+        Ok(watcher)
+    }
+
+    /// Adds another directory; watching the same directory twice is a no-op.
+    pub fn watch(&mut self, dir: &Path) -> Result<()> {
+        let wd = self
+            .inotify
+            .add_watch(
+                dir,
+                AddWatchFlags::IN_CLOSE_WRITE | AddWatchFlags::IN_MOVED_TO,
+            )
+            .with_context(|| format!("Failed to watch {:?}", dir))?;
+        if !self.dirs.iter().any(|(known, _)| *known == wd) {
+            self.dirs.push((wd, dir.to_path_buf()));
+        }
+        Ok(())
+    }
+
+    /// File descriptor that becomes readable when changes are pending.
+    pub fn fd(&self) -> BorrowedFd<'_> {
+        self.inotify.as_fd()
+    }
+
+    /// Drains pending events and returns the changed paths, deduplicated, in event order.
+    pub fn changed_files(&self) -> Vec<PathBuf> {
+        let mut changed: Vec<PathBuf> = Vec::new();
+        loop {
+            match self.inotify.read_events() {
+                Ok(events) => {
+                    for event in events {
+                        let dir = self.dirs.iter().find(|(wd, _)| *wd == event.wd);
+                        if let (Some((_, dir)), Some(name)) = (dir, event.name) {
+                            let path = dir.join(name);
+                            if !changed.contains(&path) {
+                                changed.push(path);
+                            }
+                        }
+                    }
+                }
+                Err(Errno::EAGAIN) => break,
+                Err(Errno::EINTR) => continue,
+                Err(_) => break,
+            }
+        }
+        changed
+    }
+}
@@ -0,0 +1,104 @@
+use crate::modules::bars::Bar;
+use crate::modules::fs_watch::DirWatcher;
+use crate::modules::logging::*;
+use crate::plugin_loader::PluginManager;
+use anyhow::Result;
+use std::os::fd::BorrowedFd;
+use std::path::Path;
+
+/// Watches the widgets directory and swaps rebuilt plugins into a running bar.
+pub struct PluginReloader {
+    watcher: DirWatcher,
+}
+
+impl PluginReloader {
+    pub fn new(widgets_dir: &Path) -> Result<Self> {
+        std::fs::create_dir_all(widgets_dir)?;
+        let watcher = DirWatcher::new(widgets_dir)?;
+        log_debug(
+            "RELOAD",
+            &format!("Watching {:?} for plugin changes", widgets_dir),
+        );
+        Ok(Self { watcher })
+    }
+
+    /// Becomes readable when plugin files change; polled by the event loop.
+    pub fn fd(&self) -> BorrowedFd<'_> {
+        self.watcher.fd()
+    }
+
+    /// Reloads changed plugins and rebuilds their widget slots on every bar.
+    /// Returns true if any widget instance was replaced.
+    pub fn process(&self, plugins: &mut PluginManager, bars: &mut [Bar]) -> bool {
+        let mut changed = false;
+
+        for path in self.watcher.changed_files() {
+            if path.extension().is_none_or(|ext| ext != "so") {
+                continue;
+            }
+            let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+
+            let reload = match plugins.reload_plugin(&path) {
+                Ok(Some(reload)) => reload,
+                Ok(None) => {
+                    log_debug("RELOAD", &format!("Skipping disabled plugin {}", file_name));
+                    continue;
+                }
+                Err(e) => {
+                    log_error(
+                        "RELOAD",
+                        &format!(
+                            "Failed to reload {}: {} (keeping old version)",
+                            file_name, e
+                        ),
+                    );
+                    continue;
+                }
+            };
+
+            let Some(old_widget) = &reload.old_widget else {
+                log_info(
+                    "RELOAD",
+                    &format!("Loaded new plugin {} ({})", file_name, reload.widget),
+                );
+                continue;
+            };
+
+            // Old instances have to go before their library is unloaded
+            let (replaced, failed) = bars.iter_mut().fold((0, 0), |(replaced, failed), bar| {
+                let (r, f) = bar
+                    .renderer
+                    .replace_widgets(old_widget, &reload.widget, plugins);
+                (replaced + r, failed + f)
+            });
+            if let Some(library) = reload.old_library {
+                if failed > 0 {
+                    plugins.retire(library);
+                } else {
+                    drop(library);
+                }
+            }
+
+            changed |= replaced > 0;
+            if failed > 0 {
+                log_warn(
+                    "RELOAD",
+                    &format!(
+                        "Reloaded {}: {} instance(s) replaced, {} kept on the old version",
+                        file_name, replaced, failed
+                    ),
+                );
+            } else {
+                log_info(
+                    "RELOAD",
+                    &format!(
+                        "Reloaded {} ({}): {} instance(s) replaced",
+                        file_name, reload.widget, replaced
+                    ),
+                );
+            }
+        }
+
+        changed
+    }
+}
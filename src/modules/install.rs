@@ -14,7 +14,17 @@ pub async fn install_widget(path: &Path, _config_ink: &Config) -> Result<()> {
         .ok_or_else(|| anyhow::anyhow!("Invalid path"))?;
     let target = data_dir.join(file_name);
 
-    fs::copy(path, &target).await?;
+    // Refuse plugins built against a different ABI before they end up in the registry
+    unsafe {
+        let lib = libloading::Library::new(path)?;
+        crate::plugin_loader::load_vtable(&lib)?;
+    }
+
+    // Copy next to the target and rename over it: the running bar may have the old
+    // file mapped, and the rename is what its plugin watcher picks up.
+    let staging = data_dir.join(format!(".{}.tmp", file_name.to_string_lossy()));
+    fs::copy(path, &staging).await?;
+    fs::rename(&staging, &target).await?;
     hyprlog::internal::info("INSTALL", &format!("Widget installed to: {:?}", target));
 
     // Update Registry
@@ -1,11 +1,17 @@
 pub mod autostart;
+pub mod bars;
 pub mod bootstrap;
 pub mod build;
+pub mod check;
 pub mod cli;
 pub mod config;
+pub mod config_reload;
 pub mod daemon;
+pub mod fs_watch;
+pub mod hot_reload;
 pub mod install;
 pub mod logging;
+pub mod providers;
 pub mod registry;
 pub mod runner;
 pub mod watcher;
@@ -0,0 +1,29 @@
+use crate::builtin::BuiltinProvider;
+use crate::config::{BarConfig, ProviderKind};
+use crate::external::ExternalProvider;
+use crate::plugin_loader::PluginManager;
+use crate::widget::{CompositeProvider, WidgetProvider};
+
+/// Every source of widgets the bar owns. Kept for the daemon's lifetime so
+/// widget slots can be rebuilt after plugin or config reloads.
+pub struct Providers {
+    pub builtin: BuiltinProvider,
+    pub plugins: PluginManager,
+    pub external: ExternalProvider,
+}
+
+impl Providers {
+    /// Chains the providers in `layout.providers` order.
+    pub fn chain(&self, config: &BarConfig) -> CompositeProvider<'_> {
+        let mut chain = CompositeProvider::new();
+        for kind in &config.layout.providers {
+            let provider: &dyn WidgetProvider = match kind {
+                ProviderKind::Builtin => &self.builtin,
+                ProviderKind::Plugin => &self.plugins,
+                ProviderKind::External => &self.external,
+            };
+            chain.push(provider);
+        }
+        chain
+    }
+}
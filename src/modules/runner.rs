@@ -1,18 +1,23 @@
 use anyhow::{Context, Result};
 
 use crate::modules::bootstrap;
+use crate::modules::config::bar_config_path;
+use crate::modules::config_reload::{self, ConfigReloader};
+use crate::modules::hot_reload::PluginReloader;
 use crate::modules::logging::*;
 use crate::modules::wayland_integration;
 
+use crate::bus::BusMessage;
 use crate::config::BarConfig;
 use hyprink::config::Config;
 use std::sync::Arc;
+use tokio::sync::broadcast::error::TryRecvError;
 
 pub async fn run_server(initial_config_ink: Arc<Config>, initial_config: BarConfig) -> Result<()> {
     log_debug("BAR", "Starting server initialization");
 
     // Initialize application components using the bootstrap module
-    let (config_ink, config, bar_state, _plugin_manager, mut renderer) =
+    let (config_ink, mut root, mut providers) =
         bootstrap::init_application(initial_config_ink, initial_config)
             .await
             .context("Failed to bootstrap application")?;
@@ -30,14 +35,16 @@ pub async fn run_server(initial_config_ink: Arc<Config>, initial_config: BarConf
     };
 
     let msg_loop = get_msg("bar_start_loop", "Starting Wayland event loop");
-    let msg_exit = get_msg("bar_exit", "Exiting...");
 
     // 6. Initialize Wayland & Smart Scaling
     log_debug("WAYLAND", "Initializing Wayland integration");
-    let (mut wayland_state, mut event_queue, _layer_surface) =
-        wayland_integration::init_wayland_integration(&config)
-            .await
-            .context("Failed to initialize Wayland integration")?;
+    let (mut wayland_state, mut event_queue) = wayland_integration::init_wayland_integration()
+        .await
+        .context("Failed to initialize Wayland integration")?;
+    let qh = event_queue.handle();
+
+    let mut bars = Vec::new();
+    wayland_integration::sync_bars(&mut wayland_state, &qh, &root, &mut bars, &providers);
 
     log_info("WAYLAND", "Wayland integration initialized");
 
@@ -45,31 +52,97 @@ pub async fn run_server(initial_config_ink: Arc<Config>, initial_config: BarConf
     log_info("BAR", &msg_loop);
     log_debug(
         "WAYLAND",
-        &format!(
-            "Configured: {}x{}",
-            wayland_state.width, wayland_state.height
-        ),
+        &format!("Opened {} bar surface(s)", wayland_state.bars.len()),
     );
 
+    // Hot-reload rebuilt widget plugins
+    let plugin_reloader = dirs::data_local_dir()
+        .map(|dir| dir.join("hyprbar/widgets"))
+        .and_then(|dir| match PluginReloader::new(&dir) {
+            Ok(reloader) => Some(reloader),
+            Err(e) => {
+                log_warn("RELOAD", &format!("Plugin hot-reload disabled: {}", e));
+                None
+            }
+        });
+
+    // Live config reload
+    let mut config_reloader = match ConfigReloader::new(&bar_config_path()) {
+        Ok(reloader) => Some(reloader),
+        Err(e) => {
+            log_warn("CONFIG", &format!("Config reload disabled: {:#}", e));
+            None
+        }
+    };
+
+    // Reveal requests for auto-hidden bars arrive as bus signals
+    let mut bus_rx = root.bus.subscribe();
+
+    // Runs until SIGTERM/SIGINT or the compositor goes away. No bars is a valid
+    // state: they come back when a matching output is connected.
     loop {
+        let wake_fds: Vec<_> = plugin_reloader
+            .iter()
+            .map(|r| r.fd())
+            .chain(config_reloader.iter().map(|r| r.fd()))
+            .chain(crate::wake::fd())
+            .collect();
+
         if let Err(e) = wayland_integration::handle_wayland_events(
             &mut wayland_state,
             &mut event_queue,
-            &mut renderer,
-            &bar_state,
-            &config,
+            &mut bars,
+            &wake_fds,
         ) {
             log_error("WAYLAND", &format!("Event handling error: {}", e));
-            log_warn("BAR", "Attempting to continue after error");
+            log_info("BAR", &get_msg("bar_exit", "Exiting..."));
             return Err(e);
         }
 
-        if wayland_state.exit {
-            log_info("BAR", &msg_exit);
-            break;
+        // Widgets that changed in the background report it through `needs_redraw`
+        crate::wake::drain();
+
+        loop {
+            match bus_rx.try_recv() {
+                Ok(BusMessage::Signal(signal)) => {
+                    wayland_integration::handle_bus_signal(&mut wayland_state, &signal)
+                }
+                Ok(_) | Err(TryRecvError::Lagged(_)) => {}
+                Err(_) => break,
+            }
+        }
+
+        // Faulted widgets are retried on fresh instances once their backoff ran out
+        for bar in &mut bars {
+            bar.renderer
+                .respawn_faulted(&providers.chain(&bar.state.config));
+        }
+
+        if let Some(reloader) = &plugin_reloader
+            && reloader.process(&mut providers.plugins, &mut bars)
+        {
+            for surface in &mut wayland_state.bars {
+                surface.redraw_requested = true;
+            }
         }
-    }
 
-    log_debug("BAR", "Server shutdown complete");
-    Ok(())
+        if let Some(new_config) = config_reloader.as_mut().and_then(|r| r.poll()) {
+            config_reload::apply_config(
+                new_config,
+                &mut root,
+                &mut bars,
+                &mut providers,
+                &mut wayland_state,
+                &qh,
+            );
+        }
+
+        wayland_integration::remove_closed_bars(&mut wayland_state, &mut bars);
+        if std::mem::take(&mut wayland_state.outputs_changed) {
+            wayland_integration::sync_bars(&mut wayland_state, &qh, &root, &mut bars, &providers);
+            if bars.is_empty() {
+                log_info("BAR", "No output to show a bar on, waiting for one");
+            }
+        }
+    }
 }
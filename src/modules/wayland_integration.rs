@@ -1,75 +1,187 @@
-use crate::config::BarConfig;
+use crate::bus::{SIGNAL_CONCEAL, SIGNAL_REVEAL, SIGNAL_TOGGLE_REVEAL};
+use crate::config::WindowConfig;
 use crate::event::WidgetEvent;
+use crate::modules::bars::{Bar, wanted_bars};
 use crate::modules::logging::*;
+use crate::modules::providers::Providers;
 use crate::renderer::BarRenderer;
 use crate::state::BarState;
 use crate::wayland::init as init_wayland;
-use crate::wayland::state::WaylandState;
-use crate::wayland::{create_popup_surface, destroy_popup_surface};
+use crate::wayland::state::{BarSurface, WaylandState};
+use crate::wayland::{
+    configure_bar_surface, create_bar_surface, create_popup_surface, destroy_bar_surface,
+    destroy_popup_surface, dispatch_with_timeout, find_output,
+};
 use anyhow::{Context, Result};
-use smithay_client_toolkit::reexports::client::EventQueue;
-use smithay_client_toolkit::shell::wlr_layer::LayerSurface;
-use std::time::Duration;
+use smithay_client_toolkit::compositor::CompositorState;
+use smithay_client_toolkit::reexports::client::{EventQueue, QueueHandle};
+use smithay_client_toolkit::shell::WaylandSurface;
+use std::os::fd::BorrowedFd;
+use std::time::{Duration, Instant};
 
-pub async fn init_wayland_integration(
-    config: &BarConfig,
-) -> Result<(WaylandState, EventQueue<WaylandState>, LayerSurface)> {
+pub async fn init_wayland_integration() -> Result<(WaylandState, EventQueue<WaylandState>)> {
     log_info("WAYLAND", "Initializing Wayland integration");
 
+    let (mut wayland_state, event_queue) =
+        init_wayland().context("Failed to initialize Wayland")?;
+    log_info(
+        "WAYLAND",
+        &format!("Outputs: {}", wayland_state.output_names().join(", ")),
+    );
+    // Bars are opened by the first `sync_bars`
+    wayland_state.outputs_changed = false;
+    Ok((wayland_state, event_queue))
+}
+
+fn create_surface(
+    wayland_state: &WaylandState,
+    qh: &QueueHandle<WaylandState>,
+    bar: &Bar,
+) -> Result<BarSurface> {
+    let config = &bar.state.config;
     let (font_size, window_height) = config.window.calculate_dimensions();
     log_debug(
         "WAYLAND",
         &format!(
-            "Layout: Height {}px, Font {}px (Scale: {}, Pixel: {})",
-            window_height, font_size, config.window.scale_font, config.window.pixel_font
+            "Bar {} layout: Height {}px, Font {}px (Scale: {}, Pixel: {})",
+            bar.label(),
+            window_height,
+            font_size,
+            config.window.scale_font,
+            config.window.pixel_font
         ),
     );
 
-    let monitor = if config.window.monitor.is_empty() {
-        log_debug("WAYLAND", "No monitor specified, using default");
-        None
-    } else {
-        log_info(
-            "WAYLAND",
-            &format!("Target monitor: {}", config.window.monitor),
-        );
-        Some(config.window.monitor.clone())
+    let output = match &bar.output {
+        Some(name) => {
+            let output = find_output(wayland_state, name);
+            if output.is_none() {
+                log_warn(
+                    "WAYLAND",
+                    &format!("Output {} is gone, using default", name),
+                );
+            }
+            output
+        }
+        None => {
+            log_debug("WAYLAND", "No monitor specified, using default");
+            None
+        }
     };
 
-    let anchor = if config.window.anchor == "bottom" {
-        "bottom"
-    } else {
-        "top"
-    };
-    log_debug("WAYLAND", &format!("Bar anchor: {}", anchor));
+    let edge = config.window.edge();
+    log_debug("WAYLAND", &format!("Bar anchor: {:?}", edge));
 
-    init_wayland(
-        window_height,
-        config.window.anchor == "bottom",
-        monitor,
-        config.style.font.clone(),
+    create_bar_surface(
+        wayland_state,
+        qh,
+        &config.window,
+        output,
+        &config.style.font,
         font_size,
     )
-    .context("Failed to initialize Wayland")
+    .with_context(|| format!("Failed to create surface for bar {}", bar.label()))
+}
+
+/// Opens a surface for `bar` and inserts both at `index`.
+pub fn open_bar(
+    wayland_state: &mut WaylandState,
+    qh: &QueueHandle<WaylandState>,
+    bars: &mut Vec<Bar>,
+    index: usize,
+    bar: Bar,
+) -> Result<()> {
+    let surface = create_surface(wayland_state, qh, &bar)?;
+    wayland_state.bars.insert(index, surface);
+    bars.insert(index, bar);
+    Ok(())
+}
+
+/// Closes the surface of the bar at `index` and drops its widgets.
+pub fn close_bar(wayland_state: &mut WaylandState, bars: &mut Vec<Bar>, index: usize) {
+    let bar = bars.remove(index);
+    destroy_bar_surface(wayland_state.bars.remove(index));
+    log_info("WAYLAND", &format!("Closed bar {}", bar.label()));
+}
+
+/// Drops bars whose surface the compositor closed.
+pub fn remove_closed_bars(wayland_state: &mut WaylandState, bars: &mut Vec<Bar>) {
+    while let Some(index) = wayland_state.bars.iter().position(|s| s.closed) {
+        close_bar(wayland_state, bars, index);
+    }
+}
+
+/// Matches the open bars to the connected outputs: bars whose output went away
+/// are closed and outputs that gained a bar get one. Called at startup, after
+/// output hotplug and after a config reload.
+pub fn sync_bars(
+    wayland_state: &mut WaylandState,
+    qh: &QueueHandle<WaylandState>,
+    root: &BarState,
+    bars: &mut Vec<Bar>,
+    providers: &Providers,
+) {
+    let configs = match root.config.bars() {
+        Ok(configs) => configs,
+        Err(e) => {
+            log_error("WAYLAND", &format!("Invalid [[bar]] entry: {}", e));
+            return;
+        }
+    };
+    let wanted = wanted_bars(&configs, &wayland_state.output_names());
+
+    while let Some(index) = bars
+        .iter()
+        .position(|b| !wanted.iter().any(|(e, o)| *e == b.entry && *o == b.output))
+    {
+        close_bar(wayland_state, bars, index);
+    }
+
+    // Open the missing ones; `bars` is a subsequence of `wanted`, keep that order
+    let mut index = 0;
+    for (entry, output) in wanted {
+        if bars
+            .get(index)
+            .is_some_and(|b| b.entry == entry && b.output == output)
+        {
+            index += 1;
+            continue;
+        }
+        let bar = Bar::new(entry, output, configs[entry].clone(), root, providers);
+        let label = bar.label();
+        match open_bar(wayland_state, qh, bars, index, bar) {
+            Ok(()) => {
+                log_info("WAYLAND", &format!("Opened bar {}", label));
+                index += 1;
+            }
+            Err(e) => log_error("WAYLAND", &format!("{:#}", e)),
+        }
+    }
 }
 
 pub fn handle_wayland_events(
     wayland_state: &mut WaylandState,
     event_queue: &mut EventQueue<WaylandState>,
-    renderer: &mut BarRenderer,
-    bar_state: &BarState,
-    config: &BarConfig,
+    bars: &mut [Bar],
+    wake_fds: &[BorrowedFd<'_>],
 ) -> Result<()> {
     let qh = event_queue.handle();
 
-    if wayland_state.configured {
-        let width = u16::try_from(wayland_state.width).unwrap_or(u16::MAX);
-        let height = u16::try_from(wayland_state.height).unwrap_or(u16::MAX);
+    for (surface, bar) in wayland_state.bars.iter_mut().zip(bars.iter_mut()) {
+        update_autohide(surface, &bar.state.config.window);
+        if !surface.configured {
+            continue;
+        }
+        let renderer = &mut bar.renderer;
+        // Cell metrics are physical pixels, so the grid is too
+        let width = u16::try_from(surface.to_physical(surface.width)).unwrap_or(u16::MAX);
+        let height = u16::try_from(surface.to_physical(surface.height)).unwrap_or(u16::MAX);
 
-        if width > 0 && height > 0 {
+        // The trigger strip of a hidden bar keeps the grid of the full one
+        if width > 0 && height > 0 && !surface.autohide.hidden {
             // Calculate grid size based on font metrics
-            let char_w = wayland_state.text_renderer.char_width as u16;
-            let char_h = wayland_state.text_renderer.char_height as u16;
+            let char_w = surface.text_renderer.char_width as u16;
+            let char_h = surface.text_renderer.char_height as u16;
 
             let cols = width / char_w;
             let rows = height / char_h;
@@ -86,88 +198,205 @@ pub fn handle_wayland_events(
             }
         }
 
-        if wayland_state.redraw_requested {
-            renderer.render_frame(bar_state, Duration::from_millis(16))?;
-            wayland_state.draw(
+        if surface.redraw_requested || (!surface.autohide.hidden && renderer.needs_redraw()) {
+            redraw(
+                surface,
                 &qh,
-                renderer.buffer(),
-                &bar_state.config_ink,
-                config
-                    .style
-                    .window_bg
-                    .as_deref()
-                    .unwrap_or(&config.style.bg),
+                &wayland_state.compositor_state,
+                renderer,
+                &bar.state,
             )?;
         }
     }
 
-    if let Err(e) = event_queue.blocking_dispatch(wayland_state) {
+    // Sleep until the compositor sends something or the next widget deadline.
+    // While a frame callback is outstanding, the callback itself wakes us up.
+    // Hidden bars don't render; they only wait for the pointer.
+    let now = Instant::now();
+    let timeout = wayland_state
+        .bars
+        .iter()
+        .zip(bars.iter())
+        .filter(|(surface, _)| !surface.frame_pending && !surface.autohide.hidden)
+        .filter_map(|(_, bar)| bar.renderer.next_wakeup())
+        .chain(
+            wayland_state
+                .bars
+                .iter()
+                .filter_map(|surface| surface.autohide.next_wakeup(now)),
+        )
+        .min();
+
+    if let Err(e) = dispatch_with_timeout(event_queue, wayland_state, timeout, wake_fds) {
         log_error("WAYLAND", &format!("Dispatch failed: {}", e));
-        return Err(e).context("Wayland dispatch failed");
+        return Err(e);
     }
 
-    // Process Input Events
-    // We do this after dispatch to handle events received this turn
-    for event in wayland_state.input_events.drain(..) {
-        let char_w = wayland_state.text_renderer.char_width as f64;
-        let char_h = wayland_state.text_renderer.char_height as f64;
-
-        // Extract pixel coordinates if present, else use last known cursor pos
-        let (px, py) = match event {
-            WidgetEvent::Motion { x, y } | WidgetEvent::Click { x, y, .. } => (x as f64, y as f64),
-            _ => (wayland_state.cursor_x, wayland_state.cursor_y),
+    for (index, bar) in bars.iter_mut().enumerate() {
+        let Some(surface) = wayland_state.bars.get_mut(index) else {
+            break;
         };
+        process_input(surface, &mut bar.renderer);
 
-        // Convert to Cell Coordinates
-        if char_w > 0.0 && char_h > 0.0 {
-            let cx = (px / char_w) as u16;
-            let cy = (py / char_h) as u16;
-
-            // Create a Cell-based event
-            let mut cell_event = event;
-            match &mut cell_event {
-                WidgetEvent::Motion { x, y } | WidgetEvent::Click { x, y, .. } => {
-                    *x = cx;
-                    *y = cy;
-                }
-                _ => {} // Leave/Enter/Scroll don't carry x/y in the enum variant usually (except scroll maybe?)
-            }
+        // Popup handling: check if widget wants a popup
+        handle_popup_lifecycle(wayland_state, index, &qh, bar)?;
+
+        // Render again if input events triggered a redraw
+        let surface = &mut wayland_state.bars[index];
+        if surface.configured && surface.redraw_requested {
+            redraw(
+                surface,
+                &qh,
+                &wayland_state.compositor_state,
+                &mut bar.renderer,
+                &bar.state,
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Collapses an auto-hidden bar to its trigger strip once the pointer has
+/// been away for `autohide_delay`, and expands it again when the pointer, a
+/// popup or a reveal request wants it shown.
+fn update_autohide(surface: &mut BarSurface, window: &WindowConfig) {
+    let wanted = !window.autohide || surface.pointer_inside || surface.popup.is_some();
+    let delay = Duration::from_millis(window.autohide_delay);
+    let Some(hidden) = surface.autohide.update(wanted, Instant::now(), delay) else {
+        return;
+    };
+    configure_bar_surface(&surface.layer, window, hidden);
+    surface.layer.commit();
+    // Nothing is drawn until the compositor configured the new size
+    surface.configured = false;
+    log_debug(
+        "WAYLAND",
+        if hidden { "Bar hidden" } else { "Bar revealed" },
+    );
+}
 
+/// Applies a reveal request from the event bus to every bar: `bar_reveal`
+/// keeps auto-hidden bars shown, `bar_conceal` lets them hide again and
+/// `bar_toggle_reveal` switches between the two. Other signals are ignored.
+pub fn handle_bus_signal(wayland_state: &mut WaylandState, signal: &str) {
+    for surface in &mut wayland_state.bars {
+        surface.autohide.pinned = match signal {
+            SIGNAL_REVEAL => true,
+            SIGNAL_CONCEAL => false,
+            SIGNAL_TOGGLE_REVEAL => !surface.autohide.pinned,
+            _ => return,
+        };
+    }
+}
+
+/// Feeds the pointer events a bar and its popup received this turn to their widgets.
+fn process_input(surface: &mut BarSurface, renderer: &mut BarRenderer) {
+    // The trigger strip only reveals the bar; its positions aren't cells
+    if surface.autohide.hidden {
+        surface.input_events.clear();
+        surface.popup_input_events.clear();
+        return;
+    }
+    // We do this after dispatch to handle events received this turn
+    for event in std::mem::take(&mut surface.input_events) {
+        if let Some((cx, cy, cell_event)) = to_cell_event(surface, event, surface.cursor) {
             renderer.process_input(cx, cy, cell_event);
         }
     }
+    // Popup cells start at the popup's corner, like its buffer
+    for event in std::mem::take(&mut surface.popup_input_events) {
+        if let Some((_, _, cell_event)) = to_cell_event(surface, event, surface.popup_cursor) {
+            renderer.process_popup_input(cell_event);
+        }
+    }
+}
 
-    // Popup handling: check if widget wants a popup
-    handle_popup_lifecycle(wayland_state, &qh, renderer, bar_state, config)?;
+/// Converts a pointer event in logical pixels to cells. Events without a
+/// position are placed at `cursor`. Returns the cell along with the event.
+fn to_cell_event(
+    surface: &BarSurface,
+    event: WidgetEvent,
+    cursor: (f64, f64),
+) -> Option<(u16, u16, WidgetEvent)> {
+    let char_w = surface.text_renderer.char_width as f64;
+    let char_h = surface.text_renderer.char_height as f64;
+    if char_w <= 0.0 || char_h <= 0.0 {
+        return None;
+    }
 
-    // Render again if input events triggered a redraw
-    if wayland_state.configured && wayland_state.redraw_requested {
-        renderer.render_frame(bar_state, Duration::from_millis(16))?;
-        wayland_state.draw(
-            &qh,
-            renderer.buffer(),
-            &bar_state.config_ink,
-            config
-                .style
-                .window_bg
-                .as_deref()
-                .unwrap_or(&config.style.bg),
-        )?;
+    // Extract pixel coordinates if present, else use last known cursor pos
+    let (px, py) = match event {
+        WidgetEvent::Motion { x, y } | WidgetEvent::Click { x, y, .. } => (x as f64, y as f64),
+        _ => cursor,
+    };
+    // Pointer positions are logical, cell metrics physical
+    let cx = (px * surface.scale / char_w) as u16;
+    let cy = (py * surface.scale / char_h) as u16;
+
+    let mut cell_event = event;
+    if let WidgetEvent::Motion { x, y } | WidgetEvent::Click { x, y, .. } = &mut cell_event {
+        *x = cx;
+        *y = cy;
     }
+    Some((cx, cy, cell_event))
+}
 
-    Ok(())
+/// Renders and commits a frame, unless the compositor hasn't released the last one yet.
+/// `redraw_requested` stays set in that case so the frame callback picks it up.
+fn redraw(
+    surface: &mut BarSurface,
+    qh: &QueueHandle<WaylandState>,
+    compositor: &CompositorState,
+    renderer: &mut BarRenderer,
+    bar_state: &BarState,
+) -> Result<()> {
+    if surface.frame_pending {
+        surface.redraw_requested = true;
+        return Ok(());
+    }
+
+    let config = &bar_state.config;
+    if surface.autohide.hidden {
+        return surface.draw_blank(qh);
+    }
+
+    let dt = renderer.frame_dt();
+    renderer.render_frame(bar_state, dt)?;
+    surface.draw(
+        qh,
+        renderer.buffer(),
+        &bar_state.config_ink,
+        config
+            .style
+            .window_bg
+            .as_deref()
+            .unwrap_or(&config.style.bg),
+        config.style.opaque_region.then_some(compositor),
+    )
 }
 
 fn handle_popup_lifecycle(
     wayland_state: &mut WaylandState,
-    qh: &smithay_client_toolkit::reexports::client::QueueHandle<WaylandState>,
-    renderer: &mut BarRenderer,
-    bar_state: &BarState,
-    config: &BarConfig,
+    index: usize,
+    qh: &QueueHandle<WaylandState>,
+    bar: &mut Bar,
 ) -> Result<()> {
-    let char_w = wayland_state.text_renderer.char_width;
-    let char_h = wayland_state.text_renderer.char_height;
-    let anchor_bottom = config.window.anchor == "bottom";
+    let renderer = &mut bar.renderer;
+    let bar_state = &bar.state;
+    let config = &bar_state.config;
+
+    if std::mem::take(&mut wayland_state.bars[index].popup_dismissed) {
+        destroy_popup_surface(&mut wayland_state.bars[index]);
+        renderer.dismiss_popup();
+    }
+
+    let surface = &wayland_state.bars[index];
+    let char_w = surface.text_renderer.char_width as u32;
+    let char_h = surface.text_renderer.char_height as u32;
+    // Cell metrics are physical pixels; popup sizes and positions are logical
+    let logical = |physical: u32| surface.to_logical(physical);
+    let edge = config.window.edge();
 
     // Check if hovered widget wants a popup
     if let Some((request, popup_info)) = renderer.check_popup_request() {
@@ -180,35 +409,51 @@ fn handle_popup_lifecycle(
         };
 
         if needs_create {
-            // Calculate popup position in pixels
-            // Widget left edge in pixels
-            let widget_left_px = popup_info.widget_area.x as i32 * char_w as i32;
+            let popup_width_px = logical(request.width as u32 * char_w);
+            let popup_height_px = logical(request.height as u32 * char_h);
 
-            let popup_width_px = request.width as u32 * char_w as u32;
-            let popup_height_px = request.height as u32 * char_h as u32;
+            // The widget on the bar, which the popup is anchored to
+            let area = popup_info.widget_area;
+            let widget_rect = if edge.is_vertical() {
+                let y = logical(area.y as u32 * char_h);
+                let h = logical(area.height as u32 * char_h);
+                (0, y as i32, surface.width as i32, h as i32)
+            } else {
+                let x = logical(area.x as u32 * char_w);
+                let w = logical(area.width as u32 * char_w);
+                (x as i32, 0, w as i32, surface.height as i32)
+            };
 
-            // Base position: widget left edge + offsets
-            // offset 0,0 = popup starts at widget's left edge
-            let popup_x = widget_left_px + request.offset_x as i32 + config.popup.offset_x as i32;
-            let popup_y = request.offset_y as i32 + config.popup.offset_y as i32;
+            // Offsets are along the bar and away from it, so a vertical bar
+            // opens its popups sideways and `offset_y` moves them down
+            let offset_x = request.offset_x as i32 + config.popup.offset_x as i32;
+            let offset_y = request.offset_y as i32 + config.popup.offset_y as i32;
+            let offset = if edge.is_vertical() {
+                (offset_y, offset_x)
+            } else {
+                (offset_x, offset_y)
+            };
 
             log_debug(
                 "POPUP",
                 &format!(
-                    "Creating at ({}, {}) - config offset: ({}, {})",
-                    popup_x, popup_y, config.popup.offset_x, config.popup.offset_y
+                    "Creating for {:?} widget at {:?} - offset: {:?}",
+                    popup_info.section, widget_rect, offset
                 ),
             );
 
             // Create the popup surface
             create_popup_surface(
                 wayland_state,
+                index,
                 qh,
                 popup_width_px,
                 popup_height_px,
-                popup_x,
-                popup_y,
-                anchor_bottom,
+                widget_rect,
+                offset,
+                edge,
+                popup_info.section,
+                request.persist,
             )?;
 
             // Update renderer state
@@ -218,18 +463,28 @@ fn handle_popup_lifecycle(
         // No popup wanted, destroy if active
         if renderer.active_popup().is_some() {
             log_debug("POPUP", "Widget no longer requests popup, destroying");
-            destroy_popup_surface(wayland_state);
+            destroy_popup_surface(&mut wayland_state.bars[index]);
             renderer.clear_active_popup();
         }
     }
 
     // Render popup if configured
-    if wayland_state.popup_configured
-        && wayland_state.popup_redraw_requested
+    let surface = &mut wayland_state.bars[index];
+    if surface.popup_configured
+        && surface.popup_redraw_requested
         && let Some(buf) = renderer.render_popup(bar_state)
     {
         let bg = config.style.popup_bg.as_deref().unwrap_or(&config.style.bg);
-        wayland_state.draw_popup(qh, buf, &bar_state.config_ink, bg)?;
+        surface.draw_popup(
+            qh,
+            buf,
+            &bar_state.config_ink,
+            bg,
+            config
+                .style
+                .opaque_region
+                .then_some(&wayland_state.compositor_state),
+        )?;
     }
 
     Ok(())
@@ -1,27 +1,180 @@
+use crate::abi::{
+    self, AbiBusMessage, AbiCell, AbiPopupRequest, AbiState, AbiStr, WidgetHandle, WidgetVTable,
+};
+use crate::bus::BusMessage;
+use crate::config::ProviderKind;
 use crate::widget::{PopupRequest, Widget};
 use libloading::{Library, Symbol};
 use ratatui::buffer::Buffer;
 use ratatui::layout::Rect;
 use std::collections::HashMap;
 use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast::{self, error::TryRecvError};
 
-// Type of the creator function in the plugin
-type WidgetCreator = unsafe extern "Rust" fn() -> Box<dyn Widget>;
-
+use crate::modules::logging::*;
 use crate::modules::registry::Registry;
 
-/// Wraps a plugin-loaded widget to safely intercept popup methods.
-/// Popup calls through the Rust vtable across .so boundaries are unreliable
-/// (unstable ABI), so we only forward them if the plugin explicitly exports
-/// `_has_popup() -> true` via the stable C ABI.
+type VTableGetter = unsafe extern "C" fn() -> *const WidgetVTable;
+
+/// Checks that `lib` was built against the same plugin ABI as this binary and
+/// returns its widget vtable.
+///
+/// The returned reference is only valid while `lib` stays loaded.
+///
+/// # Safety
+/// `lib` must be a hyprbar plugin (or at least not export the ABI symbols with other types).
+pub unsafe fn load_vtable(lib: &Library) -> anyhow::Result<&'static WidgetVTable> {
+    let version: Symbol<*const u32> =
+        unsafe { lib.get(abi::ABI_VERSION_SYMBOL) }.map_err(|_| {
+            anyhow::anyhow!(
+                "plugin does not export the hyprbar ABI (built for an older hyprbar?); \
+             rebuild it with `hyprbar::export_widget!`"
+            )
+        })?;
+    let version = unsafe { **version };
+    if version != abi::ABI_VERSION {
+        anyhow::bail!(
+            "plugin ABI version {} does not match hyprbar ABI version {}; rebuild the plugin",
+            version,
+            abi::ABI_VERSION
+        );
+    }
+
+    let getter: Symbol<VTableGetter> = unsafe { lib.get(abi::VTABLE_SYMBOL) }?;
+    let vtable = unsafe { getter().as_ref() }
+        .ok_or_else(|| anyhow::anyhow!("plugin returned a null widget vtable"))?;
+    if vtable.abi_version != abi::ABI_VERSION {
+        anyhow::bail!(
+            "plugin vtable ABI version {} does not match hyprbar ABI version {}",
+            vtable.abi_version,
+            abi::ABI_VERSION
+        );
+    }
+    Ok(vtable)
+}
+
+/// Host-side handle to a widget instance living inside a plugin.
+/// Every call goes through the plugin's C vtable; buffers and state are marshaled
+/// into the `#[repr(C)]` types from [`crate::abi`].
 struct PluginWidget {
-    inner: Box<dyn Widget>,
-    has_popup: bool,
+    vtable: &'static WidgetVTable,
+    handle: WidgetHandle,
+    name: String,
+    /// Bar config serialized for the plugin, keyed by `BarState::config_revision`.
+    config_json: Mutex<Option<(u64, String)>>,
+    /// Theme colors serialized for the plugin, keyed by the host's hyprink config.
+    theme_json: Mutex<Option<(Arc<hyprink::config::Config>, String)>>,
+    /// Subscription to the host bus, replayed on the plugin's bus on the next call.
+    bus: Mutex<Option<broadcast::Receiver<BusMessage>>>,
+}
+
+// SAFETY: the instance is owned exclusively by this wrapper and only reached through it;
+// the bar drives all widgets from a single thread.
+unsafe impl Send for PluginWidget {}
+unsafe impl Sync for PluginWidget {}
+
+impl PluginWidget {
+    fn new(vtable: &'static WidgetVTable) -> Option<Self> {
+        let handle = unsafe { (vtable.create)() };
+        if handle.is_null() {
+            return None;
+        }
+        let name = unsafe { (vtable.name)(handle).as_str() }.to_string();
+        Some(Self {
+            vtable,
+            handle,
+            name,
+            config_json: Mutex::new(None),
+            theme_json: Mutex::new(None),
+            bus: Mutex::new(None),
+        })
+    }
+
+    /// Re-raises a panic the plugin caught during the last call, so the renderer's
+    /// fault isolation sees plugin and built-in widgets the same way.
+    fn resume_plugin_panic(&self) {
+        let report = unsafe { (self.vtable.last_panic)(self.handle).as_str() };
+        if !report.is_empty() {
+            std::panic::resume_unwind(Box::new(report.to_string()));
+        }
+    }
+
+    fn with_state<R>(
+        &self,
+        state: &crate::state::BarState,
+        f: impl FnOnce(*const AbiState) -> R,
+    ) -> R {
+        let mut cache = self.config_json.lock().unwrap_or_else(|e| e.into_inner());
+        if cache
+            .as_ref()
+            .is_none_or(|(rev, _)| *rev != state.config_revision)
+        {
+            let json = serde_json::to_string(&state.config).unwrap_or_default();
+            *cache = Some((state.config_revision, json));
+        }
+        let json = cache.as_ref().map(|(_, json)| json.as_str()).unwrap_or("");
+
+        let mut theme = self.theme_json.lock().unwrap_or_else(|e| e.into_inner());
+        if theme
+            .as_ref()
+            .is_none_or(|(ink, _)| !Arc::ptr_eq(ink, &state.config_ink))
+        {
+            let json = serde_json::to_string(&state.config_ink.theme.colors).unwrap_or_default();
+            *theme = Some((state.config_ink.clone(), json));
+        }
+        let theme = theme.as_ref().map(|(_, json)| json.as_str()).unwrap_or("");
+
+        let messages = self.drain_bus(state);
+        let abi_messages: Vec<AbiBusMessage> = messages.iter().map(AbiBusMessage::from).collect();
+
+        let abi_state = AbiState {
+            revision: state.config_revision,
+            config_json: AbiStr::new(json),
+            theme_json: AbiStr::new(theme),
+            messages: abi_messages.as_ptr(),
+            message_count: abi_messages.len(),
+        };
+        f(&abi_state)
+    }
+
+    /// Messages sent on the host bus since the previous call. The first call subscribes.
+    fn drain_bus(&self, state: &crate::state::BarState) -> Vec<BusMessage> {
+        let mut bus = self.bus.lock().unwrap_or_else(|e| e.into_inner());
+        let rx = bus.get_or_insert_with(|| state.bus.subscribe());
+        let mut messages = Vec::new();
+        loop {
+            match rx.try_recv() {
+                Ok(msg) => messages.push(msg),
+                Err(TryRecvError::Lagged(_)) => {}
+                Err(_) => break,
+            }
+        }
+        messages
+    }
+
+    fn draw_cells(&self, area: Rect, buf: &mut Buffer, draw: impl FnOnce(&mut [AbiCell])) {
+        let area = area.intersection(buf.area);
+        if area.is_empty() {
+            return;
+        }
+        let mut cells = abi::read_cells(buf, area);
+        draw(&mut cells);
+        abi::write_cells(buf, area, &cells);
+    }
+}
+
+impl Drop for PluginWidget {
+    fn drop(&mut self) {
+        unsafe { (self.vtable.destroy)(self.handle) };
+    }
 }
 
 impl Widget for PluginWidget {
     fn name(&self) -> &str {
-        self.inner.name()
+        &self.name
     }
 
     fn render(
@@ -31,43 +184,119 @@ impl Widget for PluginWidget {
         state: &crate::state::BarState,
         dt: std::time::Duration,
     ) {
-        self.inner.render(area, buf, state, dt);
+        let (vtable, handle) = (self.vtable, self.handle);
+        let dt = abi::duration_to_micros(dt);
+        let area = area.intersection(buf.area);
+        self.with_state(state, |abi_state| {
+            self.draw_cells(area, buf, |cells| unsafe {
+                (vtable.render)(
+                    handle,
+                    area.into(),
+                    cells.as_mut_ptr(),
+                    cells.len(),
+                    abi_state,
+                    dt,
+                )
+            })
+        });
+        self.resume_plugin_panic();
     }
 
     fn update(&mut self, dt: std::time::Duration, state: &crate::state::BarState) {
-        self.inner.update(dt, state);
+        let dt = abi::duration_to_micros(dt);
+        self.with_state(state, |abi_state| unsafe {
+            (self.vtable.update)(self.handle, dt, abi_state)
+        });
+        self.resume_plugin_panic();
     }
 
     fn width(&self, state: &crate::state::BarState) -> u16 {
-        self.inner.width(state)
+        let width = self.with_state(state, |abi_state| unsafe {
+            (self.vtable.width)(self.handle, abi_state)
+        });
+        self.resume_plugin_panic();
+        width
     }
 
     fn set_instance_config(&mut self, name: String) {
-        self.inner.set_instance_config(name);
+        unsafe { (self.vtable.set_instance_config)(self.handle, AbiStr::new(&name)) };
+        self.resume_plugin_panic();
     }
 
     fn handle_event(&mut self, event: crate::event::WidgetEvent) {
-        self.inner.handle_event(event);
+        unsafe { (self.vtable.handle_event)(self.handle, event.into()) };
+        self.resume_plugin_panic();
     }
 
-    fn popup_request(&self) -> Option<PopupRequest> {
-        if !self.has_popup {
-            return None;
+    fn handle_popup_event(&mut self, event: crate::event::WidgetEvent) {
+        unsafe { (self.vtable.handle_popup_event)(self.handle, event.into()) };
+        self.resume_plugin_panic();
+    }
+
+    fn next_update(&self) -> Option<Duration> {
+        let next = unsafe { (self.vtable.next_update)(self.handle) };
+        self.resume_plugin_panic();
+        match next {
+            abi::NO_UPDATE => None,
+            us => Some(Duration::from_micros(us)),
         }
-        self.inner.popup_request()
+    }
+
+    fn needs_redraw(&self) -> bool {
+        let redraw = unsafe { (self.vtable.needs_redraw)(self.handle) };
+        self.resume_plugin_panic();
+        redraw
+    }
+
+    fn popup_request(&self) -> Option<PopupRequest> {
+        let mut request = AbiPopupRequest::default();
+        let wants_popup = unsafe { (self.vtable.popup_request)(self.handle, &mut request) };
+        self.resume_plugin_panic();
+        wants_popup.then(|| request.into())
     }
 
     fn render_popup(&mut self, area: Rect, buf: &mut Buffer, state: &crate::state::BarState) {
-        if !self.has_popup {
-            return;
-        }
-        self.inner.render_popup(area, buf, state);
+        let (vtable, handle) = (self.vtable, self.handle);
+        let area = area.intersection(buf.area);
+        self.with_state(state, |abi_state| {
+            self.draw_cells(area, buf, |cells| unsafe {
+                (vtable.render_popup)(
+                    handle,
+                    area.into(),
+                    cells.as_mut_ptr(),
+                    cells.len(),
+                    abi_state,
+                )
+            })
+        });
+        self.resume_plugin_panic();
     }
 }
 
+struct LoadedPlugin {
+    library: Library,
+    widget: String,
+}
+
+/// Result of [`PluginManager::reload_plugin`].
+pub struct PluginReload {
+    /// Widget name exported by the new build.
+    pub widget: String,
+    /// Widget name of the build that was replaced, if the plugin was already loaded.
+    pub old_widget: Option<String>,
+    /// The replaced library. Every instance created from it must be dropped first;
+    /// hand it to [`PluginManager::retire`] if that isn't possible.
+    pub old_library: Option<Library>,
+}
+
 pub struct PluginManager {
-    libraries: Vec<Library>,                          // Keep libs loaded
-    creators: HashMap<String, (WidgetCreator, bool)>, // (creator, has_popup)
+    // Keep libs loaded, keyed by file name
+    libraries: HashMap<String, LoadedPlugin>,
+    // Points into `libraries` (or `retired`); a library is only dropped once its
+    // instances are gone
+    creators: HashMap<String, &'static WidgetVTable>,
+    // Replaced libraries that still have live instances
+    retired: Vec<Library>,
     pub registry: Registry,
 }
 
@@ -77,16 +306,110 @@ impl Default for PluginManager {
     }
 }
 
+/// Copies `path` to a unique temp file. dlopen() caches libraries by path, so loading a
+/// rebuilt plugin from its original location would hand back the old image.
+fn shadow_copy(path: &Path) -> anyhow::Result<std::path::PathBuf> {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| anyhow::anyhow!("Invalid plugin path: {:?}", path))?;
+    let shadow = std::env::temp_dir().join(format!(
+        "hyprbar-{}-{}-{}",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed),
+        file_name.to_string_lossy()
+    ));
+    std::fs::copy(path, &shadow)?;
+    Ok(shadow)
+}
+
 impl PluginManager {
     pub fn new() -> Self {
         let registry = Registry::load().unwrap_or_default();
         Self {
-            libraries: Vec::new(),
+            libraries: HashMap::new(),
             creators: HashMap::new(),
+            retired: Vec::new(),
             registry,
         }
     }
 
+    /// Opens a plugin and checks its ABI. Returns the library, its vtable and widget name.
+    unsafe fn open(path: &Path) -> anyhow::Result<(Library, &'static WidgetVTable, String)> {
+        let lib = unsafe { Library::new(path) }?;
+
+        // Refuse incompatible plugins before calling anything else in them
+        let vtable = unsafe { load_vtable(&lib) }?;
+
+        // Instantiate once to get the widget name (internal name, not filename)
+        let name = PluginWidget::new(vtable)
+            .map(|w| w.name.clone())
+            .ok_or_else(|| anyhow::anyhow!("plugin failed to create widget"))?;
+        Ok((lib, vtable, name))
+    }
+
+    /// Opens a [`shadow_copy`] of `path`, so the file tools write to is never
+    /// the one mapped into the bar.
+    unsafe fn open_shadowed(
+        path: &Path,
+    ) -> anyhow::Result<(Library, &'static WidgetVTable, String)> {
+        let shadow = shadow_copy(path)?;
+        let opened = unsafe { Self::open(&shadow) };
+        // The mapping stays valid after unlinking
+        let _ = std::fs::remove_file(&shadow);
+        opened
+    }
+
+    /// Loads every enabled `.so` in `dir`. New ones are registered if `register` is
+    /// true, otherwise they are loaded without touching the registry. Failures are logged.
+    pub fn load_dir(&mut self, dir: &Path, register: bool) {
+        log_debug("PLUGINS", &format!("Scanning for plugins in {:?}", dir));
+
+        if !dir.exists() {
+            log_warn(
+                "PLUGINS",
+                &format!("Widgets directory does not exist: {:?}", dir),
+            );
+            return;
+        }
+        match std::fs::read_dir(dir) {
+            Ok(entries) => {
+                for entry in entries.flatten() {
+                    let path = entry.path();
+                    if path.extension().is_some_and(|ext| ext == "so") {
+                        log_debug("PLUGINS", &format!("Loading plugin: {:?}", path));
+                        let loaded = if register {
+                            self.load_plugin(&path, true, true)
+                        } else if self.is_disabled(&path) {
+                            Ok(())
+                        } else {
+                            self.load_plugin(&path, false, false)
+                        };
+                        if let Err(e) = loaded {
+                            log_error("PLUGINS", &format!("Failed to load {:?}: {}", path, e));
+                        }
+                    }
+                }
+            }
+            Err(e) => {
+                log_warn("PLUGINS", &format!("Cannot read widgets dir: {}", e));
+            }
+        }
+    }
+
+    /// Whether the registry has `path` disabled.
+    fn is_disabled(&self, path: &Path) -> bool {
+        path.file_name()
+            .and_then(|s| s.to_str())
+            .and_then(|name| self.registry.plugins.get(name))
+            .is_some_and(|entry| !entry.enabled)
+    }
+
+    /// Names of the widgets the loaded plugins provide.
+    pub fn widget_names(&self) -> impl Iterator<Item = &str> {
+        self.creators.keys().map(String::as_str)
+    }
+
     /// Loads a plugin from a path.
     /// If register_if_missing is true, it adds it to the registry (enabled).
     /// If check_enabled is true, it only loads if enabled in registry.
@@ -122,7 +445,8 @@ impl PluginManager {
         }
 
         unsafe {
-            let lib = Library::new(path_ref)?;
+            let (lib, vtable, name) = Self::open_shadowed(path_ref)
+                .map_err(|e| anyhow::anyhow!("{}: {}", path_ref.display(), e))?;
 
             // Extract Metadata if registering
             if register_if_missing {
@@ -149,40 +473,81 @@ impl PluginManager {
                     .install(file_name.clone(), path_ref.to_path_buf(), metadata)?;
             }
 
-            let func: Symbol<WidgetCreator> = lib.get(b"_create_widget")?;
-
-            // Check if plugin explicitly declares popup support (stable C ABI)
-            let has_popup = lib
-                .get::<extern "C" fn() -> bool>(b"_has_popup")
-                .map(|f| f())
-                .unwrap_or(false);
-
-            // Invoke once to get the widget name (internal name, not filename)
             // Note: Registry uses filename as key currently.
             // This might cause mismatch if filename != widget name.
             // But for simple "enable/disable", filename is safer as it maps to disk.
-            let temp_widget = func();
-            let name = temp_widget.name().to_string();
+            self.creators.insert(name.clone(), vtable);
+            self.libraries.insert(
+                file_name,
+                LoadedPlugin {
+                    library: lib,
+                    widget: name,
+                },
+            );
+        }
+        Ok(())
+    }
 
-            // Store the raw function pointer.
-            // The library is kept alive in `self.libraries`, so this is safe *enough*.
-            let func_ptr = *func;
+    /// Loads a new build of `path` next to the running one.
+    /// Returns `None` if the plugin is disabled. New plugins are registered like at startup.
+    pub fn reload_plugin(&mut self, path: &Path) -> anyhow::Result<Option<PluginReload>> {
+        let file_name = path
+            .file_name()
+            .and_then(|s| s.to_str())
+            .unwrap_or_default()
+            .to_string();
 
-            self.libraries.push(lib);
-            self.creators.insert(name.clone(), (func_ptr, has_popup));
+        if !self.libraries.contains_key(&file_name) {
+            self.load_plugin(path, true, true)?;
+            return Ok(self.libraries.get(&file_name).map(|p| PluginReload {
+                widget: p.widget.clone(),
+                old_widget: None,
+                old_library: None,
+            }));
         }
-        Ok(())
+
+        if self
+            .registry
+            .plugins
+            .get(&file_name)
+            .is_some_and(|entry| !entry.enabled)
+        {
+            return Ok(None);
+        }
+
+        let (library, vtable, widget) = unsafe { Self::open_shadowed(path) }?;
+
+        let old = self.libraries.insert(
+            file_name,
+            LoadedPlugin {
+                library,
+                widget: widget.clone(),
+            },
+        );
+        if let Some(old) = &old
+            && old.widget != widget
+        {
+            self.creators.remove(&old.widget);
+        }
+        self.creators.insert(widget.clone(), vtable);
+
+        Ok(Some(PluginReload {
+            widget,
+            old_widget: old.as_ref().map(|o| o.widget.clone()),
+            old_library: old.map(|o| o.library),
+        }))
+    }
+
+    /// Keeps a replaced library loaded because instances of it are still alive.
+    pub fn retire(&mut self, library: Library) {
+        self.retired.push(library);
     }
 }
 
 impl crate::widget::WidgetProvider for PluginManager {
-    fn create_widget(&self, name: &str) -> Option<Box<dyn Widget>> {
-        if let Some(&(creator, has_popup)) = self.creators.get(name) {
-            unsafe {
-                let inner = creator();
-                return Some(Box::new(PluginWidget { inner, has_popup }));
-            }
-        }
-        None
+    fn create_widget(&self, name: &str) -> Option<(Box<dyn Widget>, ProviderKind)> {
+        let vtable = self.creators.get(name)?;
+        let widget = PluginWidget::new(vtable)?;
+        Some((Box::new(widget), ProviderKind::Plugin))
     }
 }
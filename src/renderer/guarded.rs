@@ -0,0 +1,390 @@
+use crate::config::FaultConfig;
+use crate::event::WidgetEvent;
+use crate::isolation::catch_panic;
+use crate::modules::logging::*;
+use crate::state::BarState;
+use crate::ui::style::ThemeExt;
+use crate::widget::{PopupRequest, Widget};
+use ratatui::prelude::*;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+struct Fault {
+    failures: u32,
+    /// `None` when retries are disabled.
+    retry_at: Option<Instant>,
+    /// Whether the placeholder has been drawn since the last failure.
+    shown: bool,
+    /// The instance that panicked was replaced by a fresh one, which the next call tries.
+    respawned: bool,
+}
+
+impl Fault {
+    /// The fault after `failures` consecutive failures; its retry is due after
+    /// the backoff, if retries are on.
+    fn after(failures: u32, config: &FaultConfig) -> Self {
+        Self {
+            failures,
+            retry_at: config
+                .retry
+                .then(|| Instant::now() + config.backoff(failures)),
+            shown: false,
+            respawned: false,
+        }
+    }
+
+    /// True while calls into the widget are skipped.
+    fn suspended(&self) -> bool {
+        !self.respawned
+    }
+}
+
+/// Wraps every widget slot so a panicking widget can't take down the bar.
+/// A widget that panics is marked faulted, replaced by `fault.placeholder`
+/// and either retried with exponential backoff or left disabled. A retry
+/// runs on a new instance the renderer creates through [`respawn`](Self::respawn),
+/// never on the one that panicked mid-call.
+pub(crate) struct GuardedWidget {
+    inner: Box<dyn Widget>,
+    name: String,
+    config: FaultConfig,
+    fault: Mutex<Option<Fault>>,
+}
+
+impl GuardedWidget {
+    pub(crate) fn new(inner: Box<dyn Widget>, config: &FaultConfig) -> Self {
+        let name = inner.name().to_string();
+        Self {
+            inner,
+            name,
+            config: config.clone(),
+            fault: Mutex::new(None),
+        }
+    }
+
+    fn fault(&self) -> std::sync::MutexGuard<'_, Option<Fault>> {
+        self.fault.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn placeholder(&self) -> String {
+        self.config.placeholder.replace("{name}", &self.name)
+    }
+
+    /// Runs a widget call unless the widget is suspended, recording panics as faults.
+    fn guard<R>(
+        fault: &Mutex<Option<Fault>>,
+        name: &str,
+        config: &FaultConfig,
+        call: &str,
+        f: impl FnOnce() -> R,
+    ) -> Option<R> {
+        let mut fault = fault.lock().unwrap_or_else(|e| e.into_inner());
+        if fault.as_ref().is_some_and(Fault::suspended) {
+            return None;
+        }
+
+        match catch_panic(f) {
+            Ok(r) => {
+                if let Some(old) = fault.take() {
+                    log_info(
+                        "WIDGET",
+                        &format!("{} recovered after {} failure(s)", name, old.failures),
+                    );
+                }
+                Some(r)
+            }
+            Err(report) => {
+                let failures = fault.as_ref().map_or(0, |f| f.failures) + 1;
+                let retry_in = config.retry.then(|| config.backoff(failures));
+                *fault = Some(Fault::after(failures, config));
+                log_error(
+                    "WIDGET",
+                    &format!("{} panicked in {}: {}", name, call, report),
+                );
+                match retry_in {
+                    Some(delay) => log_warn(
+                        "WIDGET",
+                        &format!("{} faulted, retrying in {:?}", name, delay),
+                    ),
+                    None => log_warn("WIDGET", &format!("{} faulted, disabled", name)),
+                }
+                None
+            }
+        }
+    }
+
+    /// Time from now until a faulted widget is due to be recreated. `None`
+    /// while it is healthy, disabled or already recreated.
+    pub(crate) fn retry_in(&self) -> Option<Duration> {
+        let fault = self.fault();
+        let fault = fault.as_ref().filter(|f| !f.respawned)?;
+        fault
+            .retry_at
+            .map(|t| t.saturating_duration_since(Instant::now()))
+    }
+
+    /// Replaces the instance that panicked with `inner`, freshly created by its
+    /// provider. The next call retries the widget on it.
+    pub(crate) fn respawn(&mut self, inner: Box<dyn Widget>) {
+        self.inner = inner;
+        if let Some(fault) = self.fault().as_mut() {
+            fault.respawned = true;
+        }
+    }
+
+    /// Replaces whatever the faulted widget half-drew with the placeholder.
+    fn draw_placeholder(&mut self, area: Rect, buf: &mut Buffer, color: Color) {
+        if let Some(fault) = self.fault().as_mut() {
+            fault.shown = true;
+        }
+        for pos in area.positions() {
+            if let Some(cell) = buf.cell_mut(pos) {
+                cell.reset();
+            }
+        }
+        buf.set_stringn(
+            area.x,
+            area.y,
+            self.placeholder(),
+            area.width as usize,
+            Style::default().fg(color),
+        );
+    }
+
+    /// Counts a failed recreation like a panic, so the next attempt backs off.
+    pub(crate) fn postpone_retry(&self) {
+        let mut fault = self.fault();
+        let failures = fault.as_ref().map_or(0, |f| f.failures) + 1;
+        *fault = Some(Fault::after(failures, &self.config));
+    }
+}
+
+impl Widget for GuardedWidget {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn render(&mut self, area: Rect, buf: &mut Buffer, state: &BarState, dt: Duration) {
+        let inner = &mut self.inner;
+        let rendered = Self::guard(&self.fault, &self.name, &self.config, "render", || {
+            inner.render(area, buf, state, dt)
+        });
+        if rendered.is_some() {
+            return;
+        }
+
+        let color = match state.config_ink.resolve_color("error") {
+            Color::Reset => Color::Red,
+            c => c,
+        };
+        self.draw_placeholder(area, buf, color);
+    }
+
+    fn update(&mut self, dt: Duration, state: &BarState) {
+        let inner = &mut self.inner;
+        Self::guard(&self.fault, &self.name, &self.config, "update", || {
+            inner.update(dt, state)
+        });
+    }
+
+    fn width(&self, state: &BarState) -> u16 {
+        Self::guard(&self.fault, &self.name, &self.config, "width", || {
+            self.inner.width(state)
+        })
+        .unwrap_or_else(|| Line::from(self.placeholder()).width() as u16)
+    }
+
+    fn height(&self, state: &BarState) -> u16 {
+        Self::guard(&self.fault, &self.name, &self.config, "height", || {
+            self.inner.height(state)
+        })
+        .unwrap_or(1)
+    }
+
+    fn set_instance_config(&mut self, name: String) {
+        let inner = &mut self.inner;
+        Self::guard(
+            &self.fault,
+            &self.name,
+            &self.config,
+            "set_instance_config",
+            || inner.set_instance_config(name),
+        );
+    }
+
+    fn handle_event(&mut self, event: WidgetEvent) {
+        let inner = &mut self.inner;
+        Self::guard(
+            &self.fault,
+            &self.name,
+            &self.config,
+            "handle_event",
+            || inner.handle_event(event),
+        );
+    }
+
+    fn next_update(&self) -> Option<Duration> {
+        // The renderer schedules the retry from `retry_in`
+        if self.fault().as_ref().is_some_and(Fault::suspended) {
+            return None;
+        }
+        Self::guard(&self.fault, &self.name, &self.config, "next_update", || {
+            self.inner.next_update()
+        })
+        .flatten()
+    }
+
+    fn needs_redraw(&self) -> bool {
+        match self.fault().as_ref() {
+            Some(fault) if fault.suspended() => return !fault.shown,
+            // A recreated widget still has to replace the placeholder
+            Some(_) => return true,
+            None => {}
+        }
+        Self::guard(
+            &self.fault,
+            &self.name,
+            &self.config,
+            "needs_redraw",
+            || self.inner.needs_redraw(),
+        )
+        .unwrap_or(true)
+    }
+
+    fn popup_request(&self) -> Option<PopupRequest> {
+        Self::guard(
+            &self.fault,
+            &self.name,
+            &self.config,
+            "popup_request",
+            || self.inner.popup_request(),
+        )
+        .flatten()
+    }
+
+    fn handle_popup_event(&mut self, event: WidgetEvent) {
+        let inner = &mut self.inner;
+        Self::guard(
+            &self.fault,
+            &self.name,
+            &self.config,
+            "handle_popup_event",
+            || inner.handle_popup_event(event),
+        );
+    }
+
+    fn render_popup(&mut self, area: Rect, buf: &mut Buffer, state: &BarState) {
+        let inner = &mut self.inner;
+        Self::guard(
+            &self.fault,
+            &self.name,
+            &self.config,
+            "render_popup",
+            || inner.render_popup(area, buf, state),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Counts `handle_event` calls and panics in them if `panics` is set.
+    struct Faulty {
+        calls: Arc<AtomicUsize>,
+        panics: bool,
+    }
+
+    impl Widget for Faulty {
+        fn name(&self) -> &str {
+            "faulty"
+        }
+
+        fn render(&mut self, _area: Rect, _buf: &mut Buffer, _state: &BarState, _dt: Duration) {}
+
+        fn width(&self, _state: &BarState) -> u16 {
+            1
+        }
+
+        fn handle_event(&mut self, _event: WidgetEvent) {
+            self.calls.fetch_add(1, Ordering::Relaxed);
+            if self.panics {
+                panic!("boom");
+            }
+        }
+    }
+
+    fn faulty(panics: bool) -> (Box<dyn Widget>, Arc<AtomicUsize>) {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let widget = Faulty {
+            calls: calls.clone(),
+            panics,
+        };
+        (Box::new(widget), calls)
+    }
+
+    fn guarded(retry: bool, retry_delay: u64) -> (GuardedWidget, Arc<AtomicUsize>) {
+        let config = FaultConfig {
+            retry,
+            retry_delay,
+            ..FaultConfig::default()
+        };
+        let (widget, calls) = faulty(true);
+        (GuardedWidget::new(widget, &config), calls)
+    }
+
+    #[test]
+    fn test_fault_placeholder() {
+        let (mut widget, calls) = guarded(true, 60_000);
+        assert!(!widget.needs_redraw());
+        widget.handle_event(WidgetEvent::Enter);
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+
+        // The placeholder is asked for once
+        assert!(widget.needs_redraw());
+        let mut buf = Buffer::empty(Rect::new(0, 0, 10, 1));
+        widget.draw_placeholder(buf.area, &mut buf, Color::Red);
+        assert_eq!(buf.cell((0, 0)).unwrap().symbol(), "⚠");
+        assert_eq!(buf.cell((2, 0)).unwrap().symbol(), "f");
+        assert!(!widget.needs_redraw());
+
+        // Suspended: calls are skipped and only the retry is scheduled
+        widget.handle_event(WidgetEvent::Enter);
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+        assert_eq!(widget.next_update(), None);
+        assert!(widget.retry_in().is_some_and(|d| d > Duration::ZERO));
+    }
+
+    #[test]
+    fn test_fault_retry() {
+        let (mut widget, calls) = guarded(true, 0);
+        widget.handle_event(WidgetEvent::Enter);
+        assert_eq!(widget.retry_in(), Some(Duration::ZERO));
+
+        // The instance that panicked isn't called again, even once the retry is due
+        widget.handle_event(WidgetEvent::Enter);
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+
+        let (fresh, fresh_calls) = faulty(false);
+        widget.respawn(fresh);
+        assert_eq!(widget.retry_in(), None);
+        assert!(widget.needs_redraw());
+        widget.handle_event(WidgetEvent::Enter);
+        assert_eq!(fresh_calls.load(Ordering::Relaxed), 1);
+        assert!(widget.fault().is_none());
+        assert!(!widget.needs_redraw());
+    }
+
+    #[test]
+    fn test_fault_disabled() {
+        let (mut widget, calls) = guarded(false, 0);
+        widget.handle_event(WidgetEvent::Enter);
+        assert_eq!(widget.retry_in(), None);
+        assert_eq!(widget.next_update(), None);
+
+        widget.handle_event(WidgetEvent::Enter);
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+    }
+}
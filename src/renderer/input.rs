@@ -2,6 +2,7 @@ use super::BarRenderer;
 use super::types::WidgetSection;
 use crate::event::WidgetEvent;
 use crate::modules::logging::*;
+use crate::widget::Widget;
 
 impl BarRenderer {
     pub fn process_input(&mut self, x: u16, y: u16, event: WidgetEvent) {
@@ -1,4 +1,5 @@
 use super::BarRenderer;
+use super::guarded::GuardedWidget;
 use super::types::{HitWidget, WidgetSection};
 use crate::state::BarState;
 use crate::widget::Widget;
@@ -6,15 +7,19 @@ use ratatui::prelude::*;
 use std::time::Duration;
 
 impl BarRenderer {
+    /// Lays out one section along the bar's main axis. In a vertical bar
+    /// `Left`/`Right` alignment means top/bottom and each widget is centered
+    /// across the bar's width.
     #[allow(clippy::too_many_arguments)]
     pub(crate) fn render_section(
         buffer: &mut Buffer,
         area: Rect,
-        widgets: &mut [Box<dyn Widget>],
+        widgets: &mut [GuardedWidget],
         hit_map: &mut Vec<HitWidget>,
         section: WidgetSection,
         state: &BarState,
         align: Alignment,
+        direction: Direction,
         dt: Duration,
         padding: u16,
     ) {
@@ -22,18 +27,32 @@ impl BarRenderer {
             return;
         }
 
-        let widget_widths: Vec<u16> = widgets.iter().map(|d| d.width(state)).collect();
-        let total_width: u16 = widget_widths.iter().sum();
+        let vertical = direction == Direction::Vertical;
+        let extents: Vec<u16> = widgets
+            .iter()
+            .map(|d| Self::extent(d, state, direction))
+            .collect();
+        let total: u16 = extents.iter().sum();
 
-        let mut current_x = match align {
-            Alignment::Left => area.x,
-            Alignment::Center => area.x + (area.width.saturating_sub(total_width)) / 2,
-            Alignment::Right => area.x + area.width.saturating_sub(total_width),
+        let (start, length) = if vertical {
+            (area.y, area.height)
+        } else {
+            (area.x, area.width)
+        };
+        let mut current = match align {
+            Alignment::Left => start,
+            Alignment::Center => start + (length.saturating_sub(total)) / 2,
+            Alignment::Right => start + length.saturating_sub(total),
         };
 
         for (i, widget) in widgets.iter_mut().enumerate() {
-            let w = widget_widths[i];
-            let render_area = Rect::new(current_x, area.y, w, area.height);
+            let extent = extents[i];
+            let render_area = if vertical {
+                let w = widget.width(state).min(area.width);
+                Rect::new(area.x + (area.width - w) / 2, current, w, extent)
+            } else {
+                Rect::new(current, area.y, extent, area.height)
+            };
             let intersection = render_area.intersection(area);
             if !intersection.is_empty() {
                 widget.render(intersection, buffer, state, dt);
@@ -43,7 +62,7 @@ impl BarRenderer {
                     index: i,
                 });
             }
-            current_x += w + padding;
+            current += extent + padding;
         }
     }
 
@@ -52,37 +71,80 @@ impl BarRenderer {
         area: Rect,
         state: &BarState,
         padding: u16,
+        direction: Direction,
     ) -> Vec<Rect> {
-        let left_w = Self::calc_width(&self.left_widgets, state, padding);
-        let right_w = Self::calc_width(&self.right_widgets, state, padding);
-        let center_w = Self::calc_width(&self.center_widgets, state, padding);
-
-        let total_available = area.width;
-        let final_left_w = left_w.min(total_available);
-        let remaining_after_left = total_available.saturating_sub(final_left_w);
-        let final_right_w = right_w.min(remaining_after_left);
-        let remaining_for_center = remaining_after_left.saturating_sub(final_right_w);
-        let final_center_w = center_w.min(remaining_for_center);
-
-        let left_rect = Rect::new(area.x, area.y, final_left_w, area.height);
-        let right_x = area.x + area.width - final_right_w;
-        let right_rect = Rect::new(right_x, area.y, final_right_w, area.height);
+        let lengths = [
+            Self::calc_extent(&self.left_widgets, state, padding, direction),
+            Self::calc_extent(&self.center_widgets, state, padding, direction),
+            Self::calc_extent(&self.right_widgets, state, padding, direction),
+        ];
 
-        let ideal_center_x = area.x + (area.width.saturating_sub(final_center_w)) / 2;
-        let min_center_x = left_rect.x + left_rect.width;
-        let max_center_x = right_rect.x.saturating_sub(final_center_w);
-        let final_center_x = ideal_center_x.clamp(min_center_x, max_center_x);
-        let center_rect = Rect::new(final_center_x, area.y, final_center_w, area.height);
-
-        vec![left_rect, center_rect, right_rect]
+        if direction == Direction::Vertical {
+            flex_spans(area.height, lengths)
+                .into_iter()
+                .map(|(y, h)| Rect::new(area.x, area.y + y, area.width, h))
+                .collect()
+        } else {
+            flex_spans(area.width, lengths)
+                .into_iter()
+                .map(|(x, w)| Rect::new(area.x + x, area.y, w, area.height))
+                .collect()
+        }
     }
 
-    pub(crate) fn calc_width(widgets: &[Box<dyn Widget>], state: &BarState, padding: u16) -> u16 {
+    /// Cells a section takes up along the main axis, padding included.
+    pub(crate) fn calc_extent(
+        widgets: &[GuardedWidget],
+        state: &BarState,
+        padding: u16,
+        direction: Direction,
+    ) -> u16 {
         if widgets.is_empty() {
             return 0;
         }
-        let sum: u16 = widgets.iter().map(|d| d.width(state)).sum();
+        let sum: u16 = widgets
+            .iter()
+            .map(|d| Self::extent(d, state, direction))
+            .sum();
         let gaps = (widgets.len() as u16).saturating_sub(1);
         sum + gaps * padding
     }
+
+    fn extent(widget: &dyn Widget, state: &BarState, direction: Direction) -> u16 {
+        match direction {
+            Direction::Horizontal => widget.width(state),
+            Direction::Vertical => widget.height(state),
+        }
+    }
+}
+
+/// Places the left/center/right sections on an axis of `total` cells as
+/// (offset, length) spans. The outer sections keep their edges and win space
+/// first; the center one is centered but never overlaps them.
+fn flex_spans(total: u16, [left, center, right]: [u16; 3]) -> [(u16, u16); 3] {
+    let left = left.min(total);
+    let remaining_after_left = total.saturating_sub(left);
+    let right = right.min(remaining_after_left);
+    let center = center.min(remaining_after_left.saturating_sub(right));
+
+    let right_start = total - right;
+    let ideal_center = (total.saturating_sub(center)) / 2;
+    let max_center = right_start.saturating_sub(center);
+    let center_start = ideal_center.clamp(left, max_center);
+
+    [(0, left), (center_start, center), (right_start, right)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flex_spans() {
+        assert_eq!(flex_spans(40, [5, 6, 3]), [(0, 5), (17, 6), (37, 3)]);
+        // The center is pushed aside by a long left section
+        assert_eq!(flex_spans(40, [20, 10, 3]), [(0, 20), (20, 10), (37, 3)]);
+        // Sections are truncated from the center out when space runs out
+        assert_eq!(flex_spans(10, [6, 4, 8]), [(0, 6), (6, 0), (6, 4)]);
+    }
 }
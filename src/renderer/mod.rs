@@ -1,34 +1,46 @@
+mod guarded;
 mod input;
 mod layout;
 mod popup;
+mod schedule;
 mod types;
 mod widgets;
 
+pub use schedule::FRAME_INTERVAL;
 pub use types::{ActivePopup, WidgetSection};
 
-use crate::config::BarConfig;
+use crate::config::{BarConfig, FaultConfig};
 use crate::modules::logging::*;
 use crate::state::BarState;
 use crate::widget::Widget;
 use anyhow::Result;
+use guarded::GuardedWidget;
 use hyprink::config::Config;
 use ratatui::prelude::*;
-use std::time::Duration;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
 use tachyonfx::{Effect, Interpolation, fx};
-use types::HitWidget;
+use types::{HitWidget, SlotInfo};
 
 pub struct BarRenderer {
     buffer: Buffer,
     effects: Vec<Effect>,
     pub width: u16,
     pub height: u16,
-    pub(crate) left_widgets: Vec<Box<dyn Widget>>,
-    pub(crate) center_widgets: Vec<Box<dyn Widget>>,
-    pub(crate) right_widgets: Vec<Box<dyn Widget>>,
+    pub(crate) left_widgets: Vec<GuardedWidget>,
+    pub(crate) center_widgets: Vec<GuardedWidget>,
+    pub(crate) right_widgets: Vec<GuardedWidget>,
     pub(crate) hit_map: Vec<HitWidget>,
     pub(crate) hovered_widget: Option<(WidgetSection, usize)>,
     pub(crate) popup_buffer: Option<Buffer>,
     pub(crate) active_popup: Option<ActivePopup>,
+    /// Widget whose popup the compositor dismissed; it stays closed until
+    /// the widget stops asking for it.
+    pub(crate) dismissed_popup: Option<(WidgetSection, usize)>,
+    pub(crate) last_frame: Instant,
+    /// Alias and provider each widget was created with, for rebuilding slots on reload.
+    pub(crate) slots: HashMap<(WidgetSection, usize), SlotInfo>,
+    pub(crate) fault_config: FaultConfig,
 }
 
 impl BarRenderer {
@@ -75,13 +87,13 @@ impl BarRenderer {
         }
 
         log_debug("RENDER", "Initializing left widgets");
-        let left_widgets =
+        let (left_widgets, left_slots) =
             Self::init_widgets(&config.layout.modules_left, config, config_ink, provider);
         log_debug("RENDER", "Initializing center widgets");
-        let center_widgets =
+        let (center_widgets, center_slots) =
             Self::init_widgets(&config.layout.modules_center, config, config_ink, provider);
         log_debug("RENDER", "Initializing right widgets");
-        let right_widgets =
+        let (right_widgets, right_slots) =
             Self::init_widgets(&config.layout.modules_right, config, config_ink, provider);
 
         let total_widgets = left_widgets.len() + center_widgets.len() + right_widgets.len();
@@ -96,6 +108,20 @@ impl BarRenderer {
             ),
         );
 
+        let slots = [
+            (WidgetSection::Left, left_slots),
+            (WidgetSection::Center, center_slots),
+            (WidgetSection::Right, right_slots),
+        ]
+        .into_iter()
+        .flat_map(|(section, slots)| {
+            slots
+                .into_iter()
+                .enumerate()
+                .map(move |(idx, slot)| ((section, idx), slot))
+        })
+        .collect();
+
         Self {
             buffer: Buffer::empty(area),
             effects,
@@ -108,6 +134,10 @@ impl BarRenderer {
             hovered_widget: None,
             popup_buffer: None,
             active_popup: None,
+            dismissed_popup: None,
+            last_frame: Instant::now(),
+            slots,
+            fault_config: config.fault.clone(),
         }
     }
 
@@ -125,6 +155,8 @@ impl BarRenderer {
     }
 
     pub fn render_frame(&mut self, state: &BarState, dt: Duration) -> Result<()> {
+        self.mark_frame();
+
         for widget in self.left_widgets.iter_mut() {
             widget.update(dt, state);
         }
@@ -141,9 +173,15 @@ impl BarRenderer {
 
         let strategy = state.config.layout.strategy.as_str();
         let padding = state.config.layout.padding;
+        // Left/right bars stack their sections top, middle and bottom
+        let direction = if state.config.window.edge().is_vertical() {
+            Direction::Vertical
+        } else {
+            Direction::Horizontal
+        };
 
         let chunks = if strategy == "flex" {
-            self.calculate_flex_rects(area, state, padding)
+            self.calculate_flex_rects(area, state, padding, direction)
         } else {
             let layout_constraints = [
                 Constraint::Percentage(state.config.layout.left as u16),
@@ -152,7 +190,7 @@ impl BarRenderer {
             ];
 
             let rects = Layout::default()
-                .direction(Direction::Horizontal)
+                .direction(direction)
                 .constraints(layout_constraints)
                 .split(area);
 
@@ -167,6 +205,7 @@ impl BarRenderer {
             WidgetSection::Left,
             state,
             Alignment::Left,
+            direction,
             dt,
             padding,
         );
@@ -178,6 +217,7 @@ impl BarRenderer {
             WidgetSection::Center,
             state,
             Alignment::Center,
+            direction,
             dt,
             padding,
         );
@@ -189,6 +229,7 @@ impl BarRenderer {
             WidgetSection::Right,
             state,
             Alignment::Right,
+            direction,
             dt,
             padding,
         );
@@ -1,33 +1,30 @@
 use super::BarRenderer;
 use super::types::{ActivePopup, WidgetSection};
+use crate::event::WidgetEvent;
 use crate::modules::logging::*;
 use crate::state::BarState;
-use crate::widget::PopupRequest;
+use crate::widget::{PopupRequest, Widget};
 use ratatui::prelude::*;
 
 impl BarRenderer {
-    pub fn check_popup_request(&self) -> Option<(PopupRequest, ActivePopup)> {
+    pub fn check_popup_request(&mut self) -> Option<(PopupRequest, ActivePopup)> {
+        if let Some((section, index)) = self.dismissed_popup
+            && self.wanted_popup(section, index).is_none()
+        {
+            self.dismissed_popup = None;
+        }
+
         // If there's an active popup, check if that widget still wants it
         if let Some(active) = &self.active_popup {
-            let widget = match active.section {
-                WidgetSection::Left => self.left_widgets.get(active.index),
-                WidgetSection::Center => self.center_widgets.get(active.index),
-                WidgetSection::Right => self.right_widgets.get(active.index),
-            };
-            if let Some(w) = widget
-                && let Some(request) = w.popup_request()
-            {
-                if request.persist {
-                    return Some((request, *active));
-                }
-                if self.hovered_widget == Some((active.section, active.index)) {
-                    return Some((request, *active));
-                }
-            }
-            return None;
+            return self
+                .wanted_popup(active.section, active.index)
+                .map(|request| (request, *active));
         }
 
         let (section, idx) = self.hovered_widget?;
+        if self.dismissed_popup == Some((section, idx)) {
+            return None;
+        }
 
         let widget = match section {
             WidgetSection::Left => self.left_widgets.get(idx)?,
@@ -53,6 +50,19 @@ impl BarRenderer {
         ))
     }
 
+    /// The popup a widget asks for and still gets: a persistent one, or a
+    /// hover one while the widget is hovered.
+    fn wanted_popup(&self, section: WidgetSection, index: usize) -> Option<PopupRequest> {
+        let widget = match section {
+            WidgetSection::Left => self.left_widgets.get(index),
+            WidgetSection::Center => self.center_widgets.get(index),
+            WidgetSection::Right => self.right_widgets.get(index),
+        };
+        widget?
+            .popup_request()
+            .filter(|request| request.persist || self.hovered_widget == Some((section, index)))
+    }
+
     pub fn active_popup(&self) -> Option<&ActivePopup> {
         self.active_popup.as_ref()
     }
@@ -81,6 +91,39 @@ impl BarRenderer {
         self.popup_buffer = None;
     }
 
+    /// Closes the active popup after the compositor dismissed it, e.g. for a
+    /// click outside, and tells its widget. The widget doesn't get it back
+    /// until it asks anew.
+    pub fn dismiss_popup(&mut self) {
+        if let Some(active) = self.active_popup {
+            self.dismissed_popup = Some((active.section, active.index));
+            let widget = match active.section {
+                WidgetSection::Left => self.left_widgets.get_mut(active.index),
+                WidgetSection::Center => self.center_widgets.get_mut(active.index),
+                WidgetSection::Right => self.right_widgets.get_mut(active.index),
+            };
+            if let Some(widget) = widget {
+                widget.handle_event(WidgetEvent::PopupDismissed);
+            }
+        }
+        self.clear_active_popup();
+    }
+
+    /// Hands pointer input on the popup to the widget that opened it.
+    pub fn process_popup_input(&mut self, event: WidgetEvent) {
+        let Some(popup) = self.active_popup else {
+            return;
+        };
+        let widget = match popup.section {
+            WidgetSection::Left => self.left_widgets.get_mut(popup.index),
+            WidgetSection::Center => self.center_widgets.get_mut(popup.index),
+            WidgetSection::Right => self.right_widgets.get_mut(popup.index),
+        };
+        if let Some(widget) = widget {
+            widget.handle_popup_event(event);
+        }
+    }
+
     pub fn render_popup(&mut self, state: &BarState) -> Option<&Buffer> {
         let popup = self.active_popup?;
         let buf = self.popup_buffer.as_mut()?;
@@ -0,0 +1,180 @@
+use super::BarRenderer;
+use super::guarded::GuardedWidget;
+use crate::widget::Widget;
+use std::time::{Duration, Instant};
+
+/// Frame interval used while animations are running (~60 fps).
+pub const FRAME_INTERVAL: Duration = Duration::from_millis(16);
+
+impl BarRenderer {
+    /// Real time elapsed since the last rendered frame.
+    pub fn frame_dt(&self) -> Duration {
+        self.last_frame.elapsed()
+    }
+
+    /// Returns true while bar-level effects are still running.
+    pub fn is_animating(&self) -> bool {
+        !self.effects.is_empty()
+    }
+
+    /// How long the event loop may sleep before the next frame is due.
+    /// `None` means nothing is scheduled and the bar can wait for input.
+    pub fn next_wakeup(&self) -> Option<Duration> {
+        if self.is_animating() {
+            return Some(FRAME_INTERVAL.saturating_sub(self.frame_dt()));
+        }
+
+        let elapsed = self.frame_dt();
+        let updates = self.all_widgets().filter_map(|w| w.next_update()).map(|d| {
+            // Animating widgets are paced like bar effects instead of spinning
+            if d.is_zero() {
+                FRAME_INTERVAL.saturating_sub(elapsed)
+            } else {
+                d.saturating_sub(elapsed)
+            }
+        });
+        // Retries of faulted widgets are timed from now, not from the last frame
+        let retries = self.all_widgets().filter_map(GuardedWidget::retry_in);
+        updates.chain(retries).min()
+    }
+
+    /// Returns true if a widget asked for a redraw or a scheduled update is due.
+    pub fn needs_redraw(&self) -> bool {
+        self.all_widgets().any(|w| w.needs_redraw()) || self.next_wakeup() == Some(Duration::ZERO)
+    }
+
+    pub(crate) fn mark_frame(&mut self) {
+        self.last_frame = Instant::now();
+    }
+
+    fn all_widgets(&self) -> impl Iterator<Item = &GuardedWidget> {
+        self.left_widgets
+            .iter()
+            .chain(self.center_widgets.iter())
+            .chain(self.right_widgets.iter())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::FaultConfig;
+    use crate::state::BarState;
+    use ratatui::buffer::Buffer;
+    use ratatui::layout::Rect;
+    use ratatui::style::Color;
+    use std::collections::HashMap;
+    use tachyonfx::{Interpolation, fx};
+
+    /// Asks for an update after a fixed delay.
+    struct Scheduled(Option<Duration>);
+
+    impl Widget for Scheduled {
+        fn name(&self) -> &str {
+            "scheduled"
+        }
+
+        fn render(&mut self, _area: Rect, _buf: &mut Buffer, _state: &BarState, _dt: Duration) {}
+
+        fn width(&self, _state: &BarState) -> u16 {
+            1
+        }
+
+        fn next_update(&self) -> Option<Duration> {
+            self.0
+        }
+    }
+
+    fn section(updates: &[Option<Duration>]) -> Vec<GuardedWidget> {
+        updates
+            .iter()
+            .map(|d| GuardedWidget::new(Box::new(Scheduled(*d)), &FaultConfig::default()))
+            .collect()
+    }
+
+    /// A renderer whose last frame was drawn `ago`; `None` freezes it at "just now".
+    fn renderer(
+        sections: [&[Option<Duration>]; 3],
+        animating: bool,
+        ago: Option<Duration>,
+    ) -> BarRenderer {
+        let mut effects = Vec::new();
+        if animating {
+            effects.push(fx::fade_from(
+                Color::Black,
+                Color::Reset,
+                (800, Interpolation::SineInOut),
+            ));
+        }
+        // An instant in the future makes `frame_dt` zero, so the results are exact
+        let last_frame = match ago {
+            Some(ago) => Instant::now() - ago,
+            None => Instant::now() + Duration::from_secs(3600),
+        };
+        BarRenderer {
+            buffer: Buffer::empty(Rect::new(0, 0, 10, 1)),
+            effects,
+            width: 10,
+            height: 1,
+            left_widgets: section(sections[0]),
+            center_widgets: section(sections[1]),
+            right_widgets: section(sections[2]),
+            hit_map: Vec::new(),
+            hovered_widget: None,
+            popup_buffer: None,
+            active_popup: None,
+            dismissed_popup: None,
+            last_frame,
+            slots: HashMap::new(),
+            fault_config: FaultConfig::default(),
+        }
+    }
+
+    const SECOND: Option<Duration> = Some(Duration::from_secs(1));
+
+    #[test]
+    fn test_wakeup_idle() {
+        let bar = renderer([&[None], &[], &[None, None]], false, None);
+        assert_eq!(bar.next_wakeup(), None);
+        assert!(!bar.needs_redraw());
+    }
+
+    #[test]
+    fn test_wakeup_minimum() {
+        let bar = renderer(
+            [&[Some(Duration::from_secs(5))], &[None, SECOND], &[]],
+            false,
+            None,
+        );
+        assert_eq!(bar.next_wakeup(), SECOND);
+        assert!(!bar.needs_redraw());
+
+        // Time since the last frame counts towards the delay
+        let bar = renderer([&[], &[], &[SECOND]], false, Some(Duration::from_secs(2)));
+        assert_eq!(bar.next_wakeup(), Some(Duration::ZERO));
+        assert!(bar.needs_redraw());
+    }
+
+    #[test]
+    fn test_wakeup_zero_update() {
+        let zero = Some(Duration::ZERO);
+        let bar = renderer([&[zero], &[SECOND], &[]], false, None);
+        assert_eq!(bar.next_wakeup(), Some(FRAME_INTERVAL));
+        assert!(!bar.needs_redraw());
+
+        let bar = renderer([&[zero], &[], &[]], false, Some(FRAME_INTERVAL));
+        assert_eq!(bar.next_wakeup(), Some(Duration::ZERO));
+        assert!(bar.needs_redraw());
+    }
+
+    #[test]
+    fn test_wakeup_animating() {
+        let bar = renderer([&[None], &[SECOND], &[]], true, None);
+        assert_eq!(bar.next_wakeup(), Some(FRAME_INTERVAL));
+        assert!(!bar.needs_redraw());
+
+        let bar = renderer([&[None], &[], &[]], true, Some(FRAME_INTERVAL));
+        assert_eq!(bar.next_wakeup(), Some(Duration::ZERO));
+        assert!(bar.needs_redraw());
+    }
+}
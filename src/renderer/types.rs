@@ -1,12 +1,24 @@
+use crate::config::ProviderKind;
 use ratatui::prelude::Rect;
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum WidgetSection {
     Left,
     Center,
     Right,
 }
 
+/// How the widget in a slot was created, for rebuilding it after reloads.
+#[derive(Debug, Clone)]
+pub(crate) struct SlotInfo {
+    /// Widget name the provider was asked for.
+    pub name: String,
+    /// Instance alias from the module spec.
+    pub alias: String,
+    /// Provider that created the widget.
+    pub source: ProviderKind,
+}
+
 pub(crate) struct HitWidget {
     pub area: Rect,
     pub section: WidgetSection,
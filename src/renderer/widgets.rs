@@ -1,19 +1,23 @@
 use super::BarRenderer;
-use crate::config::BarConfig;
+use super::guarded::GuardedWidget;
+use super::types::{SlotInfo, WidgetSection};
+use crate::config::{BarConfig, ProviderKind, parse_module_spec};
 use crate::modules::logging::*;
-use crate::widget::Widget;
+use crate::widget::{Widget, WidgetProvider};
 use hyprink::config::Config;
+use std::time::Duration;
 
 impl BarRenderer {
     pub(crate) fn init_widgets(
         names: &[String],
-        _config: &BarConfig,
+        config: &BarConfig,
         config_ink: &Config,
-        provider: &dyn crate::widget::WidgetProvider,
-    ) -> Vec<Box<dyn Widget>> {
+        provider: &dyn WidgetProvider,
+    ) -> (Vec<GuardedWidget>, Vec<SlotInfo>) {
         log_debug("WIDGET", &format!("Initializing {} widgets", names.len()));
 
-        let mut widgets: Vec<Box<dyn Widget>> = Vec::new();
+        let mut widgets = Vec::new();
+        let mut slots = Vec::new();
         let log_fmt = config_ink
             .layout
             .labels
@@ -24,13 +28,7 @@ impl BarRenderer {
         for raw_name in names {
             log_debug("WIDGET", &format!("Parsing widget spec: {}", raw_name));
 
-            let (name, alias) = if let Some((n, a)) = raw_name.split_once('.') {
-                (n, a)
-            } else {
-                raw_name
-                    .split_once('#')
-                    .unwrap_or((raw_name.as_str(), raw_name.as_str()))
-            };
+            let (name, alias) = parse_module_spec(raw_name);
 
             log_debug(
                 "WIDGET",
@@ -38,8 +36,9 @@ impl BarRenderer {
             );
 
             match provider.create_widget(name) {
-                Some(mut plugin_widget) => {
-                    plugin_widget.set_instance_config(alias.to_string());
+                Some((widget, source)) => {
+                    let mut widget = GuardedWidget::new(widget, &config.fault);
+                    widget.set_instance_config(alias.to_string());
 
                     let display_name = if name != alias {
                         format!("{} as {}", name, alias)
@@ -49,9 +48,14 @@ impl BarRenderer {
 
                     let msg = log_fmt
                         .replace("{0}", &display_name)
-                        .replace("{1}", "Plugin");
+                        .replace("{1}", &format!("{:?}", source));
                     log_info("WIDGET", &msg);
-                    widgets.push(plugin_widget);
+                    widgets.push(widget);
+                    slots.push(SlotInfo {
+                        name: name.to_string(),
+                        alias: alias.to_string(),
+                        source,
+                    });
                 }
                 None => {
                     log_error("WIDGET", &format!("Failed to create widget: {}", name));
@@ -64,6 +68,132 @@ impl BarRenderer {
             "WIDGET",
             &format!("Loaded {} widgets successfully", widgets.len()),
         );
-        widgets
+        (widgets, slots)
+    }
+
+    /// Recreates every instance of `old_name` that the plugin provider created from
+    /// `provider` as `new_name`, keeping its slot and alias. Instances of the same name
+    /// from other providers stay. Returns `(replaced, failed)`; failed instances are
+    /// left untouched.
+    pub fn replace_widgets(
+        &mut self,
+        old_name: &str,
+        new_name: &str,
+        provider: &dyn WidgetProvider,
+    ) -> (usize, usize) {
+        let (mut replaced, mut failed) = (0, 0);
+        let sections = [
+            (WidgetSection::Left, &mut self.left_widgets),
+            (WidgetSection::Center, &mut self.center_widgets),
+            (WidgetSection::Right, &mut self.right_widgets),
+        ];
+
+        for (section, widgets) in sections {
+            for (idx, slot) in widgets.iter_mut().enumerate() {
+                let Some(info) = self.slots.get_mut(&(section, idx)) else {
+                    continue;
+                };
+                if slot.name() != old_name || info.source != ProviderKind::Plugin {
+                    continue;
+                }
+                match provider.create_widget(new_name) {
+                    Some((widget, source)) => {
+                        let mut widget = GuardedWidget::new(widget, &self.fault_config);
+                        widget.set_instance_config(info.alias.clone());
+                        info.name = new_name.to_string();
+                        info.source = source;
+                        *slot = widget;
+                        replaced += 1;
+                    }
+                    None => {
+                        log_error(
+                            "WIDGET",
+                            &format!(
+                                "Failed to recreate {} in {:?} slot {}",
+                                new_name, section, idx
+                            ),
+                        );
+                        failed += 1;
+                    }
+                }
+            }
+        }
+        (replaced, failed)
+    }
+
+    /// Recreates the faulted widgets whose retry is due through `provider`, so
+    /// the retry runs on a fresh instance instead of the one that panicked.
+    /// A widget the provider can't create again backs off as if it panicked.
+    pub fn respawn_faulted(&mut self, provider: &dyn WidgetProvider) {
+        let sections = [
+            (WidgetSection::Left, &mut self.left_widgets),
+            (WidgetSection::Center, &mut self.center_widgets),
+            (WidgetSection::Right, &mut self.right_widgets),
+        ];
+
+        for (section, widgets) in sections {
+            for (idx, slot) in widgets.iter_mut().enumerate() {
+                if slot.retry_in() != Some(Duration::ZERO) {
+                    continue;
+                }
+                let Some(info) = self.slots.get_mut(&(section, idx)) else {
+                    continue;
+                };
+                match provider.create_widget(&info.name) {
+                    Some((widget, source)) => {
+                        log_debug("WIDGET", &format!("Retrying {}", info.name));
+                        slot.respawn(widget);
+                        slot.set_instance_config(info.alias.clone());
+                        info.source = source;
+                    }
+                    None => {
+                        log_error(
+                            "WIDGET",
+                            &format!("Failed to recreate {} for a retry", info.name),
+                        );
+                        slot.postpone_retry();
+                    }
+                }
+            }
+        }
+    }
+
+    /// Recreates every widget of `section` from `names`, e.g. after the layout changed
+    /// in a config reload. An open popup belonging to the section is dropped.
+    pub fn rebuild_section(
+        &mut self,
+        section: WidgetSection,
+        names: &[String],
+        config: &BarConfig,
+        config_ink: &Config,
+        provider: &dyn WidgetProvider,
+    ) {
+        log_debug("WIDGET", &format!("Rebuilding {:?} widgets", section));
+        self.fault_config = config.fault.clone();
+        let (widgets, slots) = Self::init_widgets(names, config, config_ink, provider);
+
+        self.slots.retain(|(s, _), _| *s != section);
+        self.slots.extend(
+            slots
+                .into_iter()
+                .enumerate()
+                .map(|(idx, slot)| ((section, idx), slot)),
+        );
+        self.hit_map.retain(|hit| hit.section != section);
+        if self.hovered_widget.is_some_and(|(s, _)| s == section) {
+            self.hovered_widget = None;
+        }
+        if self.active_popup.is_some_and(|p| p.section == section) {
+            self.clear_active_popup();
+        }
+        if self.dismissed_popup.is_some_and(|(s, _)| s == section) {
+            self.dismissed_popup = None;
+        }
+
+        match section {
+            WidgetSection::Left => self.left_widgets = widgets,
+            WidgetSection::Center => self.center_widgets = widgets,
+            WidgetSection::Right => self.right_widgets = widgets,
+        }
     }
 }
@@ -8,6 +8,8 @@ pub struct BarState {
     pub config_ink: Arc<Config>,
     pub config: BarConfig,
     pub bus: crate::bus::EventBus,
+    /// Bumped whenever `config` is replaced, so plugins know to re-read it.
+    pub config_revision: u64,
 }
 
 impl BarState {
@@ -17,6 +19,7 @@ impl BarState {
             config_ink,
             config,
             bus,
+            config_revision: 0,
         }
     }
 }
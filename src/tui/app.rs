@@ -134,6 +134,8 @@ impl App {
                     // Use CLI start command
                     Command::new(&self_exe)
                         .arg("--start")
+                        .arg("--config")
+                        .arg(crate::modules::config::bar_config_path())
                         .stdout(Stdio::null())
                         .stderr(Stdio::null())
                         .spawn()?;
@@ -148,11 +150,7 @@ impl App {
                 let editor = std::env::var("EDITOR").unwrap_or_else(|_| "nano".to_string());
 
                 // 2. Resolve Config Path
-                let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
-                let config_path = std::path::PathBuf::from(home)
-                    .join(".config")
-                    .join("hyprbar")
-                    .join("hyprbar.toml");
+                let config_path = crate::modules::config::bar_config_path();
 
                 // 3. Detect Terminal
                 let terminal = std::env::var("TERMINAL").ok().or_else(|| {
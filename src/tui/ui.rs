@@ -1,5 +1,6 @@
 use super::app::App;
 use crate::config::BarConfig;
+use crate::ui::style::Rgba;
 use ratatui::{
     Frame,
     layout::{Alignment, Constraint, Direction, Layout, Rect},
@@ -146,11 +147,9 @@ fn resolve_color(config: &BarConfig, key: &str) -> Color {
 }
 
 fn parse_hex(val: &str) -> Color {
-    if val.starts_with('#') && val.len() == 7 {
-        let r = u8::from_str_radix(&val[1..3], 16).unwrap_or(255);
-        let g = u8::from_str_radix(&val[3..5], 16).unwrap_or(255);
-        let b = u8::from_str_radix(&val[5..7], 16).unwrap_or(255);
-        return Color::Rgb(r, g, b);
+    // The terminal can't show alpha; translucent colors are previewed opaque
+    if val.starts_with('#') {
+        return Rgba::from_hex(val).color();
     }
     Color::Reset
 }
@@ -48,8 +48,7 @@ impl Container {
                 theme.resolve_color("panel_border"),
             ),
             ContainerVariant::Glass => (
-                // Glass usually implies semi-transparent, handled by specialized renderers or composition
-                // For now, we map it to a specific key
+                // Translucent when the theme gives `glass_bg` an alpha (#RRGGBBAA)
                 theme.resolve_bg("glass_bg"),
                 theme.resolve_color("accent"),
             ),
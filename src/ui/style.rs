@@ -1,6 +1,92 @@
 use hyprink::config::Config;
 use hyprink::factory::ColorResolver;
 use ratatui::style::Color;
+use std::collections::HashMap;
+
+/// A color with an alpha channel, as written in the config and theme.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rgba {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Rgba {
+    /// Parses `#RGB`, `#RRGGBB` or `#RRGGBBAA`; anything else is left to hyprink
+    /// and comes back opaque.
+    pub fn from_hex(value: &str) -> Self {
+        Self::parse(value).unwrap_or_else(|| {
+            let c = ColorResolver::hex_to_color(value);
+            Self {
+                r: c.r,
+                g: c.g,
+                b: c.b,
+                a: 255,
+            }
+        })
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        let hex = value.strip_prefix('#')?;
+        if !hex.is_ascii() {
+            return None;
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+        match hex.len() {
+            3 => {
+                let digit = |i: usize| u8::from_str_radix(&hex[i..=i], 16).ok().map(|d| d * 17);
+                Some(Self {
+                    r: digit(0)?,
+                    g: digit(1)?,
+                    b: digit(2)?,
+                    a: 255,
+                })
+            }
+            6 | 8 => Some(Self {
+                r: channel(0)?,
+                g: channel(2)?,
+                b: channel(4)?,
+                a: if hex.len() == 8 { channel(6)? } else { 255 },
+            }),
+            _ => None,
+        }
+    }
+
+    /// The color without its alpha, for ratatui styles.
+    pub fn color(self) -> Color {
+        Color::Rgb(self.r, self.g, self.b)
+    }
+
+    /// This color blended over an opaque `backdrop`.
+    pub fn over(self, backdrop: Rgba) -> Self {
+        let mix = |c: u8, b: u8| {
+            ((c as u16 * self.a as u16 + b as u16 * (255 - self.a) as u16 + 127) / 255) as u8
+        };
+        Self {
+            r: mix(self.r, backdrop.r),
+            g: mix(self.g, backdrop.g),
+            b: mix(self.b, backdrop.b),
+            a: 255,
+        }
+    }
+}
+
+/// Alpha of the translucent theme backgrounds (`bg` and the `*_bg` keys),
+/// keyed by the color cells carry. ratatui colors have no alpha channel, so
+/// the blitter looks cell backgrounds up here; other theme colors and all text
+/// are drawn opaque.
+pub fn translucent_colors(config: &Config) -> HashMap<Color, u8> {
+    config
+        .theme
+        .colors
+        .iter()
+        .filter(|(key, _)| *key == "bg" || key.ends_with("_bg"))
+        .map(|(_, value)| Rgba::from_hex(value))
+        .filter(|c| c.a < 255)
+        .map(|c| (c.color(), c.a))
+        .collect()
+}
 
 pub trait ThemeExt {
     fn resolve_color(&self, key: &str) -> Color;
@@ -12,15 +98,41 @@ impl ThemeExt for Config {
         self.theme
             .colors
             .get(key)
-            .map(|s| {
-                let c = ColorResolver::hex_to_color(s);
-                Color::Rgb(c.r, c.g, c.b)
-            })
+            .map(|s| Rgba::from_hex(s).color())
             .unwrap_or(Color::Reset)
     }
 
     fn resolve_bg(&self, key: &str) -> Color {
-        // Backgrounds might have transparency or special handling in the future
+        // A translucent background keeps its alpha through `translucent_colors`
         self.resolve_color(key)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rgba() {
+        let rgba = |r, g, b, a| Some(Rgba { r, g, b, a });
+        assert_eq!(Rgba::parse("#1e1e2e"), rgba(0x1e, 0x1e, 0x2e, 255));
+        assert_eq!(Rgba::parse("#1E1E2E80"), rgba(0x1e, 0x1e, 0x2e, 0x80));
+        assert_eq!(Rgba::parse("#f0a"), rgba(255, 0, 170, 255));
+        assert_eq!(Rgba::parse("#12345"), None);
+        assert_eq!(Rgba::parse("1e1e2e"), None);
+        assert_eq!(Rgba::parse("#zzzzzz"), None);
+    }
+
+    #[test]
+    fn test_blend_over() {
+        let black = Rgba::from_hex("#000000");
+        assert_eq!(
+            Rgba::from_hex("#ffffff80").over(black),
+            Rgba::from_hex("#808080")
+        );
+        assert_eq!(
+            Rgba::from_hex("#1e1e2e").over(black),
+            Rgba::from_hex("#1e1e2e")
+        );
+    }
+}
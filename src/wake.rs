@@ -0,0 +1,38 @@
+//! Wakes the bar's event loop from background threads and tasks.
+//!
+//! The loop sleeps in `poll()` until Wayland traffic or the next widget deadline.
+//! Widgets whose content changes asynchronously (child processes, shell commands)
+//! call [`wake`] after setting their `needs_redraw` flag so the change shows up
+//! immediately instead of at the next unrelated wakeup.
+
+use nix::sys::eventfd::{EfdFlags, EventFd};
+use std::os::fd::{AsFd, BorrowedFd};
+use std::sync::OnceLock;
+
+fn eventfd() -> Option<&'static EventFd> {
+    static EVENTFD: OnceLock<Option<EventFd>> = OnceLock::new();
+    EVENTFD
+        .get_or_init(|| {
+            EventFd::from_value_and_flags(0, EfdFlags::EFD_NONBLOCK | EfdFlags::EFD_CLOEXEC).ok()
+        })
+        .as_ref()
+}
+
+/// Interrupts the event loop's current wait.
+pub fn wake() {
+    if let Some(fd) = eventfd() {
+        let _ = fd.write(1);
+    }
+}
+
+/// File descriptor the event loop polls alongside the Wayland socket.
+pub fn fd() -> Option<BorrowedFd<'static>> {
+    eventfd().map(|fd| fd.as_fd())
+}
+
+/// Clears pending wakeups; called by the event loop after it woke up.
+pub fn drain() {
+    if let Some(fd) = eventfd() {
+        let _ = fd.read();
+    }
+}
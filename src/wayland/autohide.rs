@@ -0,0 +1,77 @@
+use std::time::{Duration, Instant};
+
+/// Whether an auto-hidden bar is shown, and when it collapses again.
+#[derive(Debug, Default)]
+pub struct AutoHide {
+    /// Collapsed to the trigger strip at its edge.
+    pub hidden: bool,
+    /// Kept shown by a reveal request until it is withdrawn.
+    pub pinned: bool,
+    hide_at: Option<Instant>,
+}
+
+impl AutoHide {
+    /// Advances to `now`. A bar that is `wanted` (pointer on it, popup open)
+    /// or pinned is shown right away; otherwise it hides once it went
+    /// `delay` without being wanted. Returns the new `hidden` on a change.
+    pub fn update(&mut self, wanted: bool, now: Instant, delay: Duration) -> Option<bool> {
+        if wanted || self.pinned {
+            self.hide_at = None;
+            return self.set_hidden(false);
+        }
+        if self.hidden {
+            return None;
+        }
+        let hide_at = *self.hide_at.get_or_insert(now + delay);
+        if now < hide_at {
+            return None;
+        }
+        self.hide_at = None;
+        self.set_hidden(true)
+    }
+
+    /// Time left until the bar hides, so the event loop wakes up for it.
+    pub fn next_wakeup(&self, now: Instant) -> Option<Duration> {
+        self.hide_at.map(|at| at.saturating_duration_since(now))
+    }
+
+    fn set_hidden(&mut self, hidden: bool) -> Option<bool> {
+        if self.hidden == hidden {
+            return None;
+        }
+        self.hidden = hidden;
+        Some(hidden)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hide_after_delay() {
+        let delay = Duration::from_millis(500);
+        let start = Instant::now();
+        let mut autohide = AutoHide::default();
+
+        // The pointer left: the bar stays until the delay ran out
+        assert_eq!(autohide.update(false, start, delay), None);
+        assert_eq!(autohide.next_wakeup(start), Some(delay));
+        assert_eq!(autohide.update(false, start + delay / 2, delay), None);
+        assert_eq!(autohide.update(false, start + delay, delay), Some(true));
+        assert_eq!(autohide.next_wakeup(start + delay), None);
+
+        // Hovering the strip reveals it at once, leaving restarts the delay
+        assert_eq!(autohide.update(true, start + delay, delay), Some(false));
+        assert_eq!(autohide.update(false, start + delay * 2, delay), None);
+        assert_eq!(autohide.update(true, start + delay * 2, delay), None);
+        assert_eq!(autohide.next_wakeup(start + delay * 2), None);
+
+        // A pinned bar doesn't hide until it is released
+        autohide.pinned = true;
+        assert_eq!(autohide.update(false, start + delay * 4, delay), None);
+        autohide.pinned = false;
+        assert_eq!(autohide.update(false, start + delay * 4, delay), None);
+        assert_eq!(autohide.update(false, start + delay * 5, delay), Some(true));
+    }
+}
@@ -1,11 +1,16 @@
+use crate::ui::style::{Rgba, translucent_colors};
+use crate::wayland::shape_cache::{ShapeKey, ShapedGlyph, ShapedSegment};
 use crate::wayland::text::TextRenderer;
-use cosmic_text::{Attrs, Buffer, Color as CosmicColor, Family, Metrics, Shaping};
+use cosmic_text::{Attrs, CacheKeyFlags, Family, Shaping, Style, SwashContent, Weight};
 use hyprink::config::Config;
-use hyprink::factory::ColorResolver;
 
-use ratatui::buffer::Buffer as RatatuiBuffer;
-use ratatui::style::Color;
+use ratatui::buffer::{Buffer as RatatuiBuffer, CellWidth};
+use ratatui::style::{Color, Modifier};
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Range;
+use std::sync::Arc;
 
+/// Blits a whole frame: the background and every cell.
 pub fn blit_buffer_to_pixels(
     buffer: &RatatuiBuffer,
     pixels: &mut [u8],
@@ -15,247 +20,587 @@ pub fn blit_buffer_to_pixels(
     text_renderer: &mut TextRenderer, // Mutable for SwashCache/FontSystem
     bg_color_hex: &str,
 ) {
-    // 1. Fill background (same as before)
-    let default_bg_color = ColorResolver::hex_to_color(bg_color_hex);
-    let (bg_r, bg_g, bg_b) = (default_bg_color.r, default_bg_color.g, default_bg_color.b);
-
-    for chunk in pixels.chunks_exact_mut(4) {
-        chunk[0] = bg_b;
-        chunk[1] = bg_g;
-        chunk[2] = bg_r;
-        chunk[3] = 255;
-    }
+    let mut blitter = Blitter::new(buffer, height, config_ink, text_renderer, bg_color_hex);
+    let mut canvas = Canvas::new(pixels, width, height);
+
+    // 1. Fill background; `#RRGGBBAA` makes the whole bar translucent
+    canvas.clear(blitter.fill);
 
+    // 2. Iterate by ROW to find contiguous text runs. Glyphs are clipped to
+    // their row like in `blit_changed_cells`, so redrawing a single row later
+    // gives the same pixels
     let area = buffer.area();
-    let grid_width = area.width as usize;
-    let grid_height = area.height as usize;
-    let char_w = text_renderer.char_width;
-    let char_h = text_renderer.char_height;
-
-    let total_content_height = grid_height * char_h;
-    let start_y_offset = if (height as usize) > total_content_height {
-        (height as usize - total_content_height) / 2
-    } else {
-        0
-    };
+    for y_cell in 0..area.height as usize {
+        let rect = (
+            0,
+            blitter.start_y_offset + y_cell * blitter.char_h,
+            width as usize,
+            blitter.char_h,
+        );
+        blitter.row(
+            buffer,
+            y_cell,
+            0..area.width as usize,
+            &mut canvas.region(rect),
+        );
+    }
+}
 
-    let fb_width = width as usize;
-    let fb_height = height as usize;
+/// Re-blits only the cells of `buffer` that differ from `previous`, the frame
+/// `pixels` currently shows, and returns the damaged `(x, y, width, height)`
+/// pixel rectangles. Both buffers must have the same area.
+#[allow(clippy::too_many_arguments)]
+pub fn blit_changed_cells(
+    previous: &RatatuiBuffer,
+    buffer: &RatatuiBuffer,
+    pixels: &mut [u8],
+    width: u32,
+    height: u32,
+    config_ink: &Config,
+    text_renderer: &mut TextRenderer,
+    bg_color_hex: &str,
+) -> Vec<(u32, u32, u32, u32)> {
+    let mut blitter = Blitter::new(buffer, height, config_ink, text_renderer, bg_color_hex);
+    let grid_width = buffer.area().width as usize;
+    let (char_w, char_h) = (blitter.char_w, blitter.char_h);
+
+    let mut damage = Vec::new();
+    for (y_cell, (from, to)) in changed_spans(previous, buffer) {
+        // One more cell on each side takes the overhang of italic or bold glyphs
+        let redraw = from.saturating_sub(1)..(to + 1).min(grid_width);
+        let x = redraw.start * char_w;
+        // The last cell also owns the pixels right of the grid
+        let right = if redraw.end == grid_width {
+            width as usize
+        } else {
+            redraw.end * char_w
+        };
+        let rect = (
+            x,
+            blitter.start_y_offset + y_cell * char_h,
+            right.saturating_sub(x),
+            char_h,
+        );
+
+        let mut canvas = Canvas::new(pixels, width, height).clipped(rect);
+        canvas.clear(blitter.fill);
+        // Neighbours outside the rectangle may still reach into it
+        let context = from.saturating_sub(2)..(to + 2).min(grid_width);
+        blitter.row(buffer, y_cell, context, &mut canvas);
+
+        let [x0, y0, x1, y1] = canvas.clip;
+        if x1 > x0 && y1 > y0 {
+            damage.push((x0 as u32, y0 as u32, (x1 - x0) as u32, (y1 - y0) as u32));
+        }
+    }
+    damage
+}
+
+/// Changed cells per row as `(first, end)` column ranges; a changed wide
+/// character covers all of its cells.
+fn changed_spans(
+    previous: &RatatuiBuffer,
+    buffer: &RatatuiBuffer,
+) -> BTreeMap<usize, (usize, usize)> {
+    let area = buffer.area();
+    let mut spans = BTreeMap::new();
+    for (x, y, cell) in previous.diff(buffer) {
+        let from = (x - area.x) as usize;
+        let to = (from + (cell.cell_width() as usize).max(1)).min(area.width as usize);
+        spans
+            .entry((y - area.y) as usize)
+            .and_modify(|(a, b): &mut (usize, usize)| {
+                *a = (*a).min(from);
+                *b = (*b).max(to);
+            })
+            .or_insert((from, to));
+    }
+    spans
+}
 
-    // 2. Iterate by ROW to find contiguous text runs
-    for y_cell in 0..grid_height {
-        let mut current_run = String::new();
-        let mut current_fg = Color::Reset;
-        let mut current_bg = Color::Reset;
-        let mut run_start_x = 0;
+/// The last frame blitted for a surface: its cells and pixels. The next frame
+/// only re-blits the cells that changed.
+#[derive(Default)]
+pub struct FrameCache {
+    cells: Option<RatatuiBuffer>,
+    pixels: Vec<u8>,
+    width: u32,
+    height: u32,
+    bg_color_hex: String,
+}
 
-        for x_cell in 0..grid_width {
-            let i = y_cell * grid_width + x_cell;
-            let cell = &buffer.content()[i];
+impl FrameCache {
+    /// Forces the next frame to be drawn in full, e.g. after the fonts or the
+    /// theme changed.
+    pub fn invalidate(&mut self) {
+        self.cells = None;
+    }
 
-            let cell_fg = cell.fg;
-            let cell_bg = cell.bg;
-            let symbol = cell.symbol();
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
 
-            // Check if we should break the run
-            if current_run.is_empty() {
-                current_run.push_str(symbol);
-                current_fg = cell_fg;
-                current_bg = cell_bg;
-                run_start_x = x_cell;
-            } else if cell_fg == current_fg && cell_bg == current_bg {
-                current_run.push_str(symbol);
-            } else {
-                // Style mismatch, flush current run
-                flush_run(
-                    &current_run,
-                    run_start_x,
-                    x_cell - run_start_x,
-                    y_cell,
-                    current_fg,
-                    current_bg,
-                    text_renderer,
+    /// Brings the kept pixels up to date with `buffer` and returns the damaged
+    /// `(x, y, width, height)` rectangles in pixels: the whole frame when its
+    /// size or background changed, nothing when no cell did.
+    pub fn update(
+        &mut self,
+        buffer: &RatatuiBuffer,
+        width: u32,
+        height: u32,
+        config_ink: &Config,
+        text_renderer: &mut TextRenderer,
+        bg_color_hex: &str,
+    ) -> Vec<(u32, u32, u32, u32)> {
+        let reusable = self.cells.take().filter(|cells| {
+            cells.area == buffer.area
+                && (self.width, self.height) == (width, height)
+                && self.bg_color_hex == bg_color_hex
+        });
+
+        let damage = match &reusable {
+            Some(previous) => blit_changed_cells(
+                previous,
+                buffer,
+                &mut self.pixels,
+                width,
+                height,
+                config_ink,
+                text_renderer,
+                bg_color_hex,
+            ),
+            None => {
+                self.pixels.resize((width * height * 4) as usize, 0);
+                self.width = width;
+                self.height = height;
+                self.bg_color_hex = bg_color_hex.to_string();
+                blit_buffer_to_pixels(
+                    buffer,
+                    &mut self.pixels,
+                    width,
+                    height,
                     config_ink,
-                    pixels,
-                    fb_width,
-                    fb_height,
-                    start_y_offset,
-                    char_w,
-                    char_h,
-                    bg_r,
-                    bg_g,
-                    bg_b,
+                    text_renderer,
+                    bg_color_hex,
                 );
+                vec![(0, 0, width, height)]
+            }
+        };
 
-                // Start new run
-                current_run.clear();
-                current_run.push_str(symbol);
-                current_fg = cell_fg;
-                current_bg = cell_bg;
-                run_start_x = x_cell;
+        let mut cells = reusable.unwrap_or_else(|| buffer.clone());
+        cells.clone_from(buffer);
+        self.cells = Some(cells);
+        damage
+    }
+}
+
+/// One ratatui cell's symbol and the cells it covers.
+struct Grapheme<'a> {
+    x: usize,
+    cells: usize,
+    symbol: &'a str,
+}
+
+/// Frame pixels, with drawing confined to a clip rectangle `[x0, y0, x1, y1]`.
+struct Canvas<'a> {
+    pixels: &'a mut [u8],
+    width: usize,
+    clip: [usize; 4],
+}
+
+impl<'a> Canvas<'a> {
+    fn new(pixels: &'a mut [u8], width: u32, height: u32) -> Self {
+        Self {
+            pixels,
+            width: width as usize,
+            clip: [0, 0, width as usize, height as usize],
+        }
+    }
+
+    fn clipped(mut self, (x, y, w, h): (usize, usize, usize, usize)) -> Self {
+        let [x0, y0, x1, y1] = self.clip;
+        self.clip = [
+            x.clamp(x0, x1),
+            y.clamp(y0, y1),
+            (x + w).clamp(x0, x1),
+            (y + h).clamp(y0, y1),
+        ];
+        self
+    }
+
+    /// The part of this canvas inside `(x, y, w, h)`.
+    fn region(&mut self, rect: (usize, usize, usize, usize)) -> Canvas<'_> {
+        Canvas {
+            pixels: self.pixels,
+            width: self.width,
+            clip: self.clip,
+        }
+        .clipped(rect)
+    }
+
+    /// Replaces everything inside the clip rectangle with `fill`.
+    fn clear(&mut self, fill: [u8; 4]) {
+        let [x0, y0, x1, y1] = self.clip;
+        for y in y0..y1 {
+            let row = &mut self.pixels[(y * self.width + x0) * 4..(y * self.width + x1) * 4];
+            for chunk in row.chunks_exact_mut(4) {
+                chunk.copy_from_slice(&fill);
             }
         }
+    }
 
-        // Flush end of row
-        if !current_run.is_empty() {
-            flush_run(
-                &current_run,
-                run_start_x,
-                grid_width - run_start_x,
-                y_cell,
-                current_fg,
-                current_bg,
-                text_renderer,
-                config_ink,
-                pixels,
-                fb_width,
-                fb_height,
-                start_y_offset,
-                char_w,
-                char_h,
-                bg_r,
-                bg_g,
-                bg_b,
-            );
+    /// Composites a premultiplied pixel, if it lies inside the clip rectangle.
+    fn blend(&mut self, x: i32, y: i32, src: [u8; 4]) {
+        let [x0, y0, x1, y1] = self.clip;
+        if x < x0 as i32 || y < y0 as i32 || x >= x1 as i32 || y >= y1 as i32 {
+            return;
+        }
+        let offset = (y as usize * self.width + x as usize) * 4;
+        blend_over(&mut self.pixels[offset..offset + 4], src);
+    }
+
+    /// Blends `src` over the rectangle `(x, y, w, h)`.
+    fn fill_rect(&mut self, (x, y, w, h): (usize, usize, usize, usize), src: [u8; 4]) {
+        for py in y..y + h {
+            for px in x..x + w {
+                self.blend(px as i32, py as i32, src);
+            }
         }
     }
 }
 
-#[allow(clippy::too_many_arguments)]
-fn flush_run(
-    text: &str,
-    start_x_cell: usize,
-    width_in_cells: usize,
-    y_cell: usize,
-    fg: Color,
-    bg: Color,
-    text_renderer: &mut TextRenderer,
-    config_ink: &Config,
-    pixels: &mut [u8],
-    width: usize,
-    height: usize,
-    start_y_offset: usize,
+/// What every run of a frame is drawn with.
+struct Blitter<'a> {
+    text_renderer: &'a mut TextRenderer,
+    config_ink: &'a Config,
+    translucent: HashMap<Color, u8>,
+    window_bg: Color,
+    /// Premultiplied window background.
+    fill: [u8; 4],
     char_w: usize,
     char_h: usize,
-    _default_bg_r: u8,
-    _default_bg_g: u8,
-    _default_bg_b: u8,
-) {
-    let resolved_fg = resolve_color(fg, config_ink, Color::White);
-    let resolved_bg = resolve_color(bg, config_ink, Color::Reset); // Reset means transparent/default
-
-    /* debug!(
-        "Flush run: '{}' (fg={:?}->{:?}, bg={:?}->{:?})",
-        text, fg, resolved_fg, bg, resolved_bg
-    ); */
-
-    // Draw Background rect
-    if bg != Color::Reset {
-        let (br, bg, bb) = color_to_rgb(resolved_bg);
-        // Correctly use the passed width in cells
-        let run_width_cells = width_in_cells;
-
-        let rect_x = start_x_cell * char_w;
-        let rect_y = start_y_offset + y_cell * char_h;
-        let rect_w = run_width_cells * char_w;
-        let rect_h = char_h;
-
-        for y in 0..rect_h {
-            for x in 0..rect_w {
-                let px_x = rect_x + x;
-                let px_y = rect_y + y;
-                if px_x >= width || px_y >= height {
+    /// Rows are centered vertically in the frame.
+    start_y_offset: usize,
+}
+
+impl<'a> Blitter<'a> {
+    fn new(
+        buffer: &RatatuiBuffer,
+        height: u32,
+        config_ink: &'a Config,
+        text_renderer: &'a mut TextRenderer,
+        bg_color_hex: &str,
+    ) -> Self {
+        let window_bg = Rgba::from_hex(bg_color_hex);
+        let char_w = text_renderer.char_width;
+        let char_h = text_renderer.char_height;
+
+        let total_content_height = buffer.area().height as usize * char_h;
+        let start_y_offset = if (height as usize) > total_content_height {
+            (height as usize - total_content_height) / 2
+        } else {
+            0
+        };
+
+        Self {
+            text_renderer,
+            config_ink,
+            translucent: translucent_colors(config_ink),
+            window_bg: window_bg.color(),
+            fill: premultiply(window_bg),
+            char_w,
+            char_h,
+            start_y_offset,
+        }
+    }
+
+    /// Draws the cells `columns` of a row as runs of equally styled cells. A
+    /// wide character owns the cells after it, which ratatui blanks; they are
+    /// skipped the way ratatui's own diff does.
+    fn row(
+        &mut self,
+        buffer: &RatatuiBuffer,
+        y_cell: usize,
+        columns: Range<usize>,
+        canvas: &mut Canvas,
+    ) {
+        let grid_width = buffer.area().width as usize;
+        let mut current_run: Vec<Grapheme> = Vec::new();
+        let mut current_style = (Color::Reset, Color::Reset, Modifier::empty());
+
+        let mut x_cell = columns.start;
+        while x_cell < columns.end {
+            let cell = &buffer.content()[y_cell * grid_width + x_cell];
+            let cells = (cell.cell_width() as usize).clamp(1, grid_width - x_cell);
+            let style = (cell.fg, cell.bg, cell.modifier);
+
+            // Style mismatch, flush current run
+            if !current_run.is_empty() && style != current_style {
+                self.flush_run(&current_run, y_cell, current_style, canvas);
+                current_run.clear();
+            }
+
+            // Start new run
+            if current_run.is_empty() {
+                current_style = style;
+            }
+            current_run.push(Grapheme {
+                x: x_cell,
+                cells,
+                symbol: cell.symbol(),
+            });
+            x_cell += cells;
+        }
+
+        // Flush end of row
+        if !current_run.is_empty() {
+            self.flush_run(&current_run, y_cell, current_style, canvas);
+        }
+    }
+
+    fn flush_run(
+        &mut self,
+        run: &[Grapheme],
+        y_cell: usize,
+        (fg, bg, modifier): (Color, Color, Modifier),
+        canvas: &mut Canvas,
+    ) {
+        let resolved_fg = resolve_color(fg, self.config_ink, Color::White);
+        let resolved_bg = resolve_color(bg, self.config_ink, Color::Reset); // Reset means transparent/default
+        let (resolved_fg, resolved_bg) =
+            apply_reversed(resolved_fg, resolved_bg, modifier, self.window_bg);
+        let (char_w, char_h) = (self.char_w, self.char_h);
+
+        let width_in_cells = run.iter().map(|g| g.cells).sum::<usize>();
+        let rect_x = run[0].x * char_w;
+        let rect_y = self.start_y_offset + y_cell * char_h;
+        let rect_w = width_in_cells * char_w;
+
+        // Draw Background rect
+        if resolved_bg != Color::Reset {
+            let (r, g, b) = color_to_rgb(resolved_bg);
+            let a = self.translucent.get(&resolved_bg).copied().unwrap_or(255);
+            canvas.fill_rect(
+                (rect_x, rect_y, rect_w, char_h),
+                premultiply(Rgba { r, g, b, a }),
+            );
+        }
+
+        // Hidden text keeps its background only
+        if modifier.contains(Modifier::HIDDEN) {
+            return;
+        }
+
+        // Bold and italic use the family's own faces; without them bold is
+        // overstruck one pixel to the right and italic is slanted by cosmic-text
+        let bold = modifier.contains(Modifier::BOLD);
+        let italic = modifier.contains(Modifier::ITALIC);
+        let strokes = if bold && !self.text_renderer.has_bold {
+            2
+        } else {
+            1
+        };
+
+        // Rasterize; dim text is drawn at half intensity over its background
+        let (fr, fg, fb) = color_to_rgb(resolved_fg);
+        let fa = if modifier.contains(Modifier::DIM) {
+            127
+        } else {
+            255
+        };
+
+        // A strictly monospace font shapes the whole run so ligatures survive;
+        // otherwise every grapheme is shaped on its own
+        let segments: Vec<&[Grapheme]> = if self.text_renderer.monospace {
+            vec![run]
+        } else {
+            run.chunks(1).collect()
+        };
+        let mut baseline = None;
+        for segment in segments {
+            let shaped = shape_segment(self.text_renderer, segment, bold, italic, char_w);
+            baseline.get_or_insert(shaped.baseline);
+            let origin_x = (segment[0].x * char_w) as i32;
+
+            for glyph in &shaped.glyphs {
+                let (clip_from, clip_to) = glyph.clip.map_or((i32::MIN, i32::MAX), |(from, to)| {
+                    (origin_x + from, origin_x + to)
+                });
+                let Some(image) = self
+                    .text_renderer
+                    .swash_cache
+                    .get_image(&mut self.text_renderer.font_system, glyph.cache_key)
+                else {
                     continue;
+                };
+                let left = origin_x + glyph.x + image.placement.left;
+                let top = rect_y as i32 + glyph.y - image.placement.top;
+                let glyph_w = image.placement.width as usize;
+
+                for dy in 0..image.placement.height as usize {
+                    for dx in 0..glyph_w {
+                        let i = dy * glyph_w + dx;
+                        // Outline glyphs come as coverage of the text color (times its
+                        // alpha); color glyphs (COLR/CBDT emoji) keep their own colors
+                        let src = match image.content {
+                            SwashContent::Mask => Rgba {
+                                r: fr,
+                                g: fg,
+                                b: fb,
+                                a: ((image.data[i] as u16 * fa as u16 + 127) / 255) as u8,
+                            },
+                            SwashContent::Color => Rgba {
+                                r: image.data[i * 4],
+                                g: image.data[i * 4 + 1],
+                                b: image.data[i * 4 + 2],
+                                a: image.data[i * 4 + 3],
+                            },
+                            SwashContent::SubpixelMask => continue,
+                        };
+                        let src = premultiply(src);
+                        if src[3] == 0 {
+                            continue;
+                        }
+                        for stroke in 0..strokes {
+                            let px_x = left + dx as i32 + stroke;
+                            if (clip_from..clip_to).contains(&px_x) {
+                                canvas.blend(px_x, top + dy as i32, src);
+                            }
+                        }
+                    }
                 }
+            }
+        }
 
-                let offset = (px_y * width + px_x) * 4;
-                if offset + 4 <= pixels.len() {
-                    pixels[offset] = bb;
-                    pixels[offset + 1] = bg;
-                    pixels[offset + 2] = br;
-                    pixels[offset + 3] = 255;
-                }
+        // Decorations span the whole run, relative to the text baseline
+        let underline = modifier.contains(Modifier::UNDERLINED);
+        let strikethrough = modifier.contains(Modifier::CROSSED_OUT);
+        if underline || strikethrough {
+            let font_size = self.text_renderer.font_size;
+            let baseline = baseline.unwrap_or(font_size);
+            let thickness = (font_size / 14.0).round().max(1.0);
+            let src = premultiply(Rgba {
+                r: fr,
+                g: fg,
+                b: fb,
+                a: fa,
+            });
+            let mut line = |offset: f32| {
+                let y = (rect_y as f32 + baseline + offset).round().max(0.0) as usize;
+                // Stay inside the cell so neighbouring rows are untouched
+                let y = y.min(rect_y + char_h - thickness as usize);
+                canvas.fill_rect((rect_x, y, rect_w, thickness as usize), src);
+            };
+            if underline {
+                line(thickness.max(font_size * 0.1));
+            }
+            if strikethrough {
+                line(-font_size * 0.3);
             }
         }
     }
+}
 
-    // Draw Text with Cosmic Text
-    let font_size = text_renderer.font_size;
-    let line_height = font_size * 1.2;
-
-    // debug!("Flush run: '{}' (bg={:?}) Cells: {} ", text, bg, width_in_cells);
+/// Shapes a segment and places its glyphs on the cell grid, relative to its
+/// first cell: narrow glyphs are centered in their cells, wider ones clipped to
+/// them. Unchanged segments come from the renderer's cache.
+fn shape_segment(
+    text_renderer: &mut TextRenderer,
+    segment: &[Grapheme],
+    bold: bool,
+    italic: bool,
+    char_w: usize,
+) -> Arc<ShapedSegment> {
+    let mut text = String::new();
+    let mut starts = Vec::with_capacity(segment.len());
+    for grapheme in segment {
+        starts.push(text.len());
+        text.push_str(grapheme.symbol);
+    }
+    let key = ShapeKey {
+        text,
+        cells: segment.iter().map(|g| g.cells).collect(),
+        bold,
+        italic,
+        font_size_bits: text_renderer.font_size.to_bits(),
+    };
+    if let Some(shaped) = text_renderer.shape_cache.get(&key) {
+        return shaped;
+    }
 
-    let mut buffer = Buffer::new(
-        &mut text_renderer.font_system,
-        Metrics::new(font_size, line_height),
-    );
+    let mut attrs = Attrs::new().family(Family::Name(&text_renderer.font_family));
+    if bold {
+        attrs = attrs.weight(Weight::BOLD);
+    }
+    if italic {
+        attrs = attrs.style(Style::Italic);
+        if !text_renderer.has_italic {
+            attrs = attrs.cache_key_flags(CacheKeyFlags::FAKE_ITALIC);
+        }
+    }
 
     // Shape text
+    let buffer = &mut text_renderer.shape_buffer;
     buffer.set_text(
         &mut text_renderer.font_system,
-        text,
-        &Attrs::new().family(Family::Name(&text_renderer.font_family)),
+        &key.text,
+        &attrs,
         Shaping::Advanced,
         None,
     );
     buffer.shape_until_scroll(&mut text_renderer.font_system, false);
 
-    // Rasterize
-    let (fr, fg, fb) = color_to_rgb(resolved_fg);
-    let cosmic_color = CosmicColor::rgb(fr, fg, fb);
-
-    // Run callback
-    let draw_x_base = (start_x_cell * char_w) as i32;
-    let draw_y_base = (start_y_offset + y_cell * char_h) as i32;
-    #[allow(clippy::unnecessary_cast)]
-    let stride = width as usize;
-    let width_i32 = width as i32;
-    let height_i32 = height as i32;
-
-    buffer.draw(
-        &mut text_renderer.font_system,
-        &mut text_renderer.swash_cache,
-        cosmic_color,
-        |x, y, w, h, color| {
-            for dy in 0..h {
-                for dx in 0..w {
-                    let px_x = draw_x_base + x + dx as i32;
-                    let px_y = draw_y_base + y + dy as i32;
-
-                    if px_x < 0 || px_x >= width_i32 || px_y < 0 || px_y >= height_i32 {
-                        continue;
-                    }
-
-                    let offset = (px_y as usize * stride + px_x as usize) * 4;
-                    if offset + 4 > pixels.len() {
-                        continue;
-                    }
-
-                    let alpha = color.a();
-                    if alpha > 0 {
-                        let bg_b = pixels[offset];
-                        let bg_g = pixels[offset + 1];
-                        let bg_r = pixels[offset + 2];
-
-                        let a_f = alpha as f32 / 255.0;
-                        let inv_a = 1.0 - a_f;
+    let grapheme_at = |byte: usize| &segment[starts.partition_point(|&s| s <= byte) - 1];
+    let mut glyphs = Vec::new();
+    let mut baseline = None;
+    for line in buffer.layout_runs() {
+        baseline.get_or_insert(line.line_y);
+        // Glyphs of one cluster (base and marks, ligatures) move together
+        for cluster in line
+            .glyphs
+            .chunk_by(|a, b| (a.start, a.end) == (b.start, b.end))
+        {
+            let first = grapheme_at(cluster[0].start);
+            let last = grapheme_at(cluster[0].end.max(cluster[0].start + 1) - 1);
+            let cell_x = ((first.x - segment[0].x) * char_w) as f32;
+            let cell_w = ((last.x + last.cells - first.x) * char_w) as f32;
+            let glyph_x = cluster.iter().map(|g| g.x).fold(f32::INFINITY, f32::min);
+            let glyph_w: f32 = cluster.iter().map(|g| g.w).sum();
+
+            let shift = cell_x + ((cell_w - glyph_w) / 2.0).max(0.0) - glyph_x;
+            let clip = (glyph_w > cell_w).then_some((cell_x as i32, (cell_x + cell_w) as i32));
+            for glyph in cluster {
+                let physical = glyph.physical((shift, line.line_y), 1.0);
+                glyphs.push(ShapedGlyph {
+                    cache_key: physical.cache_key,
+                    x: physical.x,
+                    y: physical.y,
+                    clip,
+                });
+            }
+        }
+    }
 
-                        let r = (color.r() as f32 * a_f + bg_r as f32 * inv_a) as u8;
-                        let g = (color.g() as f32 * a_f + bg_g as f32 * inv_a) as u8;
-                        let b = (color.b() as f32 * a_f + bg_b as f32 * inv_a) as u8;
+    let baseline = baseline.unwrap_or(text_renderer.font_size);
+    text_renderer
+        .shape_cache
+        .insert(key, ShapedSegment { glyphs, baseline })
+}
 
-                        pixels[offset] = b;
-                        pixels[offset + 1] = g;
-                        pixels[offset + 2] = r;
-                        pixels[offset + 3] = 255;
-                    }
-                }
-            }
-        },
-    );
+/// Terminal-style `REVERSED`: the text takes the background color and the
+/// background the text color. A default background reads as the bar's.
+fn apply_reversed(fg: Color, bg: Color, modifier: Modifier, window_bg: Color) -> (Color, Color) {
+    if !modifier.contains(Modifier::REVERSED) {
+        return (fg, bg);
+    }
+    let fg_from_bg = if bg == Color::Reset { window_bg } else { bg };
+    (fg_from_bg, fg)
 }
 
 // Helpers
+/// Resolves a cell color to RGB. `Reset` is the theme's `fg` for text (or
+/// stays `Reset` for backgrounds), the 16 ANSI colors come from the theme's
+/// `color0`..`color15` and the rest of the 256-color range is xterm's.
 fn resolve_color(c: Color, config_ink: &Config, default: Color) -> Color {
     match c {
         Color::Reset => {
@@ -265,39 +610,244 @@ fn resolve_color(c: Color, config_ink: &Config, default: Color) -> Color {
                     .theme
                     .colors
                     .get("fg")
-                    .map(|s| {
-                        let cc = ColorResolver::hex_to_color(s);
-                        Color::Rgb(cc.r, cc.g, cc.b)
-                    })
+                    .map(|s| Rgba::from_hex(s).color())
                     .unwrap_or(Color::White)
             } else {
                 default
             }
         }
-        c => c,
+        Color::Rgb(..) => c,
+        c => match ansi_index(c) {
+            Some(i) => config_ink
+                .theme
+                .colors
+                .get(&format!("color{}", i))
+                .map(|s| Rgba::from_hex(s).color())
+                .unwrap_or_else(|| rgb(color_to_rgb(c))),
+            None => rgb(color_to_rgb(c)),
+        },
     }
 }
 
+fn rgb((r, g, b): (u8, u8, u8)) -> Color {
+    Color::Rgb(r, g, b)
+}
+
+/// The ANSI palette slot (0-15) of a named or low indexed color.
+fn ansi_index(color: Color) -> Option<usize> {
+    let i = match color {
+        Color::Black => 0,
+        Color::Red => 1,
+        Color::Green => 2,
+        Color::Yellow => 3,
+        Color::Blue => 4,
+        Color::Magenta => 5,
+        Color::Cyan => 6,
+        Color::Gray => 7,
+        Color::DarkGray => 8,
+        Color::LightRed => 9,
+        Color::LightGreen => 10,
+        Color::LightYellow => 11,
+        Color::LightBlue => 12,
+        Color::LightMagenta => 13,
+        Color::LightCyan => 14,
+        Color::White => 15,
+        Color::Indexed(i) if i < 16 => i as usize,
+        _ => return None,
+    };
+    Some(i)
+}
+
+/// xterm's default ANSI colors, used when the theme doesn't set `colorN`.
+const ANSI_COLORS: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+/// RGB of a color without consulting the theme (xterm palette).
 fn color_to_rgb(color: Color) -> (u8, u8, u8) {
     match color {
-        Color::Black => (0, 0, 0),
-        Color::Red => (255, 0, 0),
-        Color::Green => (0, 255, 0),
-        Color::Yellow => (255, 255, 0),
-        Color::Blue => (0, 0, 255),
-        Color::Magenta => (255, 0, 255),
-        Color::Cyan => (0, 255, 255),
-        Color::Gray => (128, 128, 128),
-        Color::DarkGray => (64, 64, 64),
-        Color::LightRed => (255, 128, 128),
-        Color::LightGreen => (128, 255, 128),
-        Color::LightYellow => (255, 255, 128),
-        Color::LightBlue => (128, 128, 255),
-        Color::LightMagenta => (255, 128, 255),
-        Color::LightCyan => (128, 255, 255),
-        Color::White => (255, 255, 255),
         Color::Rgb(r, g, b) => (r, g, b),
-        Color::Indexed(i) => (i, i, i),
         Color::Reset => (0, 0, 0),
+        // 6x6x6 color cube
+        Color::Indexed(i @ 16..=231) => {
+            let level = |v: u8| if v == 0 { 0 } else { 55 + v * 40 };
+            let i = i - 16;
+            (level(i / 36), level(i / 6 % 6), level(i % 6))
+        }
+        // Grayscale ramp
+        Color::Indexed(i @ 232..=255) => {
+            let v = 8 + (i - 232) * 10;
+            (v, v, v)
+        }
+        c => ansi_index(c).map_or((0, 0, 0), |i| ANSI_COLORS[i]),
+    }
+}
+
+/// wl_shm's ARGB8888 is premultiplied and little-endian, i.e. BGRA in memory.
+fn premultiply(c: Rgba) -> [u8; 4] {
+    let scale = |v: u8| ((v as u16 * c.a as u16 + 127) / 255) as u8;
+    [scale(c.b), scale(c.g), scale(c.r), c.a]
+}
+
+/// Composites a premultiplied `src` pixel over `dst` (source-over).
+fn blend_over(dst: &mut [u8], src: [u8; 4]) {
+    let inv = 255 - src[3] as u16;
+    for (d, s) in dst.iter_mut().zip(src) {
+        *d = s.saturating_add(((*d as u16 * inv + 127) / 255) as u8);
+    }
+}
+
+/// Rectangles `(x, y, width, height)` covering the fully opaque pixels of a
+/// frame, for the surface's opaque region. Identical spans on consecutive rows
+/// are merged, so a plain bar yields a handful of rectangles.
+pub fn opaque_rects(pixels: &[u8], width: u32, height: u32) -> Vec<(u32, u32, u32, u32)> {
+    let row_spans = |y: u32| {
+        let row = &pixels[(y * width * 4) as usize..((y + 1) * width * 4) as usize];
+        let mut spans = Vec::new();
+        let mut start = None;
+        for (x, px) in row.chunks_exact(4).enumerate() {
+            match (px[3] == 255, start) {
+                (true, None) => start = Some(x as u32),
+                (false, Some(s)) => {
+                    spans.push((s, x as u32 - s));
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+        if let Some(s) = start {
+            spans.push((s, width - s));
+        }
+        spans
+    };
+
+    let mut rects = Vec::new();
+    // Spans still growing downwards: (x, width, first row)
+    let mut open: Vec<(u32, u32, u32)> = Vec::new();
+    for y in 0..=height {
+        let spans = if y < height { row_spans(y) } else { Vec::new() };
+        let mut next = Vec::new();
+        for &(x, w, top) in &open {
+            if spans.contains(&(x, w)) {
+                next.push((x, w, top));
+            } else {
+                rects.push((x, top, w, y - top));
+            }
+        }
+        for &(x, w) in &spans {
+            if !open.iter().any(|&(ox, ow, _)| (ox, ow) == (x, w)) {
+                next.push((x, w, y));
+            }
+        }
+        open = next;
+    }
+    rects
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blend_premultiplied() {
+        let half_red = premultiply(Rgba {
+            r: 255,
+            g: 0,
+            b: 0,
+            a: 128,
+        });
+        assert_eq!(half_red, [0, 0, 128, 128]);
+
+        let mut pixel = premultiply(Rgba {
+            r: 0,
+            g: 0,
+            b: 255,
+            a: 255,
+        });
+        blend_over(&mut pixel, half_red);
+        assert_eq!(pixel, [127, 0, 128, 255]);
+
+        let mut clear = [0; 4];
+        blend_over(&mut clear, half_red);
+        assert_eq!(clear, half_red);
+    }
+
+    #[test]
+    fn test_xterm_palette() {
+        assert_eq!(color_to_rgb(Color::Red), (205, 0, 0));
+        assert_eq!(color_to_rgb(Color::Indexed(12)), (92, 92, 255));
+        assert_eq!(color_to_rgb(Color::Indexed(16)), (0, 0, 0));
+        assert_eq!(color_to_rgb(Color::Indexed(208)), (255, 135, 0));
+        assert_eq!(color_to_rgb(Color::Indexed(231)), (255, 255, 255));
+        assert_eq!(color_to_rgb(Color::Indexed(232)), (8, 8, 8));
+        assert_eq!(color_to_rgb(Color::Indexed(255)), (238, 238, 238));
+    }
+
+    #[test]
+    fn test_reversed_colors() {
+        let bar = Color::Rgb(30, 30, 46);
+        let plain = Modifier::BOLD;
+        assert_eq!(
+            apply_reversed(Color::White, Color::Blue, plain, bar),
+            (Color::White, Color::Blue)
+        );
+        assert_eq!(
+            apply_reversed(Color::White, Color::Blue, Modifier::REVERSED, bar),
+            (Color::Blue, Color::White)
+        );
+        // Without a background the text is cut out of the bar color
+        assert_eq!(
+            apply_reversed(Color::White, Color::Reset, Modifier::REVERSED, bar),
+            (bar, Color::White)
+        );
+    }
+
+    #[test]
+    fn test_changed_spans() {
+        use ratatui::layout::Rect;
+        use ratatui::style::Style;
+
+        let mut previous = RatatuiBuffer::empty(Rect::new(0, 0, 10, 2));
+        previous.set_string(0, 0, "12:00 cpu", Style::default());
+        previous.set_string(0, 1, "mem", Style::default());
+        let mut buffer = previous.clone();
+        buffer.set_string(0, 0, "12:01", Style::default());
+        assert_eq!(
+            changed_spans(&previous, &buffer)
+                .into_iter()
+                .collect::<Vec<_>>(),
+            vec![(0, (4, 5))]
+        );
+
+        // A wide character damages both of its cells
+        buffer.set_string(7, 1, "世", Style::default());
+        assert_eq!(changed_spans(&previous, &buffer).get(&1), Some(&(7, 9)));
+    }
+
+    #[test]
+    fn test_opaque_rects() {
+        // 4x3, opaque except a transparent hole at (1, 1)
+        let mut pixels = vec![255u8; 4 * 3 * 4];
+        pixels[(4 + 1) * 4 + 3] = 0;
+        assert_eq!(
+            opaque_rects(&pixels, 4, 3),
+            vec![(0, 0, 4, 1), (0, 1, 1, 1), (2, 1, 2, 1), (0, 2, 4, 1)]
+        );
+        assert_eq!(opaque_rects(&[0; 8], 2, 1), vec![]);
     }
 }
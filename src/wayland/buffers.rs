@@ -0,0 +1,83 @@
+use anyhow::{Context, Result, bail};
+use smithay_client_toolkit::reexports::client::protocol::wl_shm;
+use smithay_client_toolkit::shm::{
+    Shm,
+    slot::{Buffer, SlotPool},
+};
+
+/// Buffers kept per surface: one on screen, one being drawn and a spare for
+/// compositors that hold on to the previous one a little longer.
+const MAX_BUFFERS: usize = 3;
+
+/// The shm buffers of one surface. The pool is sized from the frames actually
+/// drawn and grows with them; buffers are reused once the compositor releases
+/// them.
+pub struct ShmBuffers {
+    pool: SlotPool,
+    buffers: Vec<Buffer>,
+    /// Physical size of the kept buffers.
+    width: u32,
+    height: u32,
+}
+
+impl ShmBuffers {
+    /// Starts with a pool for two `width`x`height` frames (physical pixels);
+    /// zero when the size isn't known until the first configure.
+    pub fn new(shm: &Shm, width: u32, height: u32) -> Result<Self> {
+        let len = (2 * frame_len(width, height)).max(4096);
+        let pool = SlotPool::new(len, shm).context("Failed to create shm pool")?;
+        Ok(Self {
+            pool,
+            buffers: Vec::new(),
+            width,
+            height,
+        })
+    }
+
+    /// A `width`x`height` ARGB8888 buffer the compositor isn't using, and its
+    /// pixels. A new size drops the old buffers and grows the pool; fails if
+    /// every buffer is still held by the compositor or memory runs out.
+    pub fn acquire(&mut self, width: u32, height: u32) -> Result<(&Buffer, &mut [u8])> {
+        if (width, height) != (self.width, self.height) {
+            self.buffers.clear();
+            self.width = width;
+            self.height = height;
+            let len = 2 * frame_len(width, height);
+            if len > self.pool.len() {
+                self.pool
+                    .resize(len)
+                    .with_context(|| format!("Failed to grow shm pool to {} bytes", len))?;
+            }
+        }
+
+        let index = match (0..self.buffers.len())
+            .find(|&i| self.buffers[i].canvas(&mut self.pool).is_some())
+        {
+            Some(index) => index,
+            None if self.buffers.len() < MAX_BUFFERS => {
+                let (buffer, _) = self
+                    .pool
+                    .create_buffer(
+                        width as i32,
+                        height as i32,
+                        width as i32 * 4,
+                        wl_shm::Format::Argb8888,
+                    )
+                    .with_context(|| format!("Failed to create {}x{} buffer", width, height))?;
+                self.buffers.push(buffer);
+                self.buffers.len() - 1
+            }
+            None => bail!("All {} buffers are still in use", MAX_BUFFERS),
+        };
+
+        let buffer = &self.buffers[index];
+        let canvas = buffer
+            .canvas(&mut self.pool)
+            .context("Buffer is still in use")?;
+        Ok((buffer, canvas))
+    }
+}
+
+fn frame_len(width: u32, height: u32) -> usize {
+    width as usize * height as usize * 4
+}
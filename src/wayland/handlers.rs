@@ -1,10 +1,21 @@
 use crate::wayland::state::WaylandState;
 use smithay_client_toolkit::reexports::client::protocol::wl_pointer;
+use smithay_client_toolkit::globals::GlobalData;
+use smithay_client_toolkit::reexports::client::{Dispatch, delegate_dispatch, delegate_noop};
+use smithay_client_toolkit::reexports::protocols::xdg::decoration::zv1::client::zxdg_decoration_manager_v1::{self, ZxdgDecorationManagerV1};
+use smithay_client_toolkit::reexports::protocols::xdg::shell::client::xdg_wm_base::XdgWmBase;
+use smithay_client_toolkit::reexports::protocols::wp::fractional_scale::v1::client::{
+    wp_fractional_scale_manager_v1::WpFractionalScaleManagerV1,
+    wp_fractional_scale_v1::{self, WpFractionalScaleV1},
+};
+use smithay_client_toolkit::reexports::protocols::wp::viewporter::client::{
+    wp_viewport::WpViewport, wp_viewporter::WpViewporter,
+};
 use smithay_client_toolkit::shell::WaylandSurface;
 use smithay_client_toolkit::{
     compositor::CompositorHandler,
     delegate_compositor, delegate_layer, delegate_output, delegate_pointer, delegate_registry,
-    delegate_seat, delegate_shm,
+    delegate_seat, delegate_shm, delegate_xdg_popup,
     output::{OutputHandler, OutputState},
     reexports::client::{
         Connection, QueueHandle,
@@ -15,6 +26,10 @@ use smithay_client_toolkit::{
     seat::pointer::{PointerEvent, PointerEventKind, PointerHandler},
     seat::{Capability, SeatHandler, SeatState},
     shell::wlr_layer::{LayerShellHandler, LayerSurface, LayerSurfaceConfigure},
+    shell::xdg::{
+        XdgShell,
+        popup::{Popup, PopupConfigure, PopupHandler},
+    },
     shm::{Shm, ShmHandler},
 };
 
@@ -28,53 +43,61 @@ impl PointerHandler for WaylandState {
     ) {
         use crate::event::WidgetEvent;
         for event in events {
+            let Some(bar) = self.bar_for_surface(&event.surface) else {
+                continue;
+            };
+            // Events on a popup go to the widget that opened it, in popup coordinates
+            let on_popup = bar
+                .popup
+                .as_ref()
+                .is_some_and(|p| p.wl_surface() == &event.surface);
+            let (input_events, cursor) = if on_popup {
+                bar.popup_redraw_requested = true;
+                (&mut bar.popup_input_events, &mut bar.popup_cursor)
+            } else {
+                (&mut bar.input_events, &mut bar.cursor)
+            };
             match event.kind {
                 PointerEventKind::Enter { .. } => {
-                    self.input_events.push(WidgetEvent::Enter);
-                    self.input_events.push(WidgetEvent::Motion {
+                    input_events.push(WidgetEvent::Enter);
+                    input_events.push(WidgetEvent::Motion {
                         x: event.position.0 as u16,
                         y: event.position.1 as u16,
                     });
-                    self.cursor_x = event.position.0;
-                    self.cursor_y = event.position.1;
-                    self.redraw_requested = true;
+                    *cursor = event.position;
+                    bar.pointer_inside = true;
                 }
                 PointerEventKind::Leave { .. } => {
-                    self.input_events.push(WidgetEvent::Leave);
-                    self.redraw_requested = true;
+                    input_events.push(WidgetEvent::Leave);
+                    bar.pointer_inside = false;
                 }
                 PointerEventKind::Motion { .. } => {
-                    self.input_events.push(WidgetEvent::Motion {
+                    input_events.push(WidgetEvent::Motion {
                         x: event.position.0 as u16,
                         y: event.position.1 as u16,
                     });
-                    self.cursor_x = event.position.0;
-                    self.cursor_y = event.position.1;
-                    self.redraw_requested = true;
+                    *cursor = event.position;
                 }
                 PointerEventKind::Press { button, .. } => {
-                    self.input_events.push(WidgetEvent::Click {
+                    input_events.push(WidgetEvent::Click {
                         button,
-                        x: self.cursor_x as u16,
-                        y: self.cursor_y as u16,
+                        x: cursor.0 as u16,
+                        y: cursor.1 as u16,
                     });
-                    self.redraw_requested = true;
                 }
                 PointerEventKind::Axis {
                     horizontal,
                     vertical,
                     ..
-                } => {
-                    if horizontal.absolute != 0.0 || vertical.absolute != 0.0 {
-                        self.input_events.push(WidgetEvent::Scroll {
-                            dx: horizontal.absolute,
-                            dy: vertical.absolute,
-                        });
-                        self.redraw_requested = true;
-                    }
+                } if horizontal.absolute != 0.0 || vertical.absolute != 0.0 => {
+                    input_events.push(WidgetEvent::Scroll {
+                        dx: horizontal.absolute,
+                        dy: vertical.absolute,
+                    });
                 }
-                _ => {}
+                _ => continue,
             }
+            bar.redraw_requested = true;
         }
     }
 }
@@ -84,9 +107,18 @@ impl CompositorHandler for WaylandState {
         &mut self,
         _conn: &Connection,
         _qh: &QueueHandle<Self>,
-        _surface: &wl_surface::WlSurface,
-        _new_factor: i32,
+        surface: &wl_surface::WlSurface,
+        new_factor: i32,
     ) {
+        // Integer fallback; with wp_fractional_scale_v1 the preferred scale wins
+        if let Some(bar) = self
+            .bars
+            .iter_mut()
+            .find(|b| b.layer.wl_surface() == surface)
+            && bar.fractional_scale.is_none()
+        {
+            bar.set_scale(new_factor as f64);
+        }
     }
 
     fn transform_changed(
@@ -102,10 +134,18 @@ impl CompositorHandler for WaylandState {
         &mut self,
         _conn: &Connection,
         _qh: &QueueHandle<Self>,
-        _surface: &wl_surface::WlSurface,
+        surface: &wl_surface::WlSurface,
         _time: u32,
     ) {
-        self.redraw_requested = true;
+        // The compositor is ready for a new buffer. Whether we actually redraw
+        // is decided by the event loop (input, widget deadlines, animations).
+        if let Some(bar) = self
+            .bars
+            .iter_mut()
+            .find(|b| b.layer.wl_surface() == surface)
+        {
+            bar.frame_pending = false;
+        }
     }
 
     fn surface_enter(
@@ -136,8 +176,14 @@ impl OutputHandler for WaylandState {
         &mut self,
         _conn: &Connection,
         _qh: &QueueHandle<Self>,
-        _output: wl_output::WlOutput,
+        output: wl_output::WlOutput,
     ) {
+        let name = self.output_state.info(&output).and_then(|i| i.name);
+        hyprlog::internal::info(
+            "WAYLAND",
+            &format!("Output connected: {}", name.as_deref().unwrap_or("?")),
+        );
+        self.outputs_changed = true;
     }
 
     fn update_output(
@@ -146,33 +192,31 @@ impl OutputHandler for WaylandState {
         _qh: &QueueHandle<Self>,
         _output: wl_output::WlOutput,
     ) {
+        // The name may have arrived late
+        self.outputs_changed = true;
     }
 
     fn output_destroyed(
         &mut self,
         _conn: &Connection,
         _qh: &QueueHandle<Self>,
-        _output: wl_output::WlOutput,
+        output: wl_output::WlOutput,
     ) {
+        let name = self.output_state.info(&output).and_then(|i| i.name);
+        hyprlog::internal::info(
+            "WAYLAND",
+            &format!("Output disconnected: {}", name.as_deref().unwrap_or("?")),
+        );
+        self.outputs_changed = true;
     }
 }
 
 impl LayerShellHandler for WaylandState {
     fn closed(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, layer: &LayerSurface) {
-        // Check if it's the popup or main surface
-        if let Some(popup_layer) = &self.popup_layer
-            && popup_layer.wl_surface() == layer.wl_surface()
-        {
-            // Popup was closed externally
-            hyprlog::internal::debug("POPUP", "Popup closed externally");
-            self.popup_layer = None;
-            self.popup_surface = None;
-            self.popup_pool = None;
-            self.popup_configured = false;
-            return;
+        // Main surface closed, e.g. its output went away
+        if let Some(bar) = self.bar_for_surface(layer.wl_surface()) {
+            bar.closed = true;
         }
-        // Main surface closed
-        self.exit = true;
     }
 
     fn configure(
@@ -183,32 +227,51 @@ impl LayerShellHandler for WaylandState {
         configure: LayerSurfaceConfigure,
         _serial: u32,
     ) {
-        // Check if it's the popup or main surface
-        if let Some(popup_layer) = &self.popup_layer
-            && popup_layer.wl_surface() == layer.wl_surface()
-        {
-            // Popup configured
-            if configure.new_size.0 != 0 && configure.new_size.1 != 0 {
-                self.popup_width = configure.new_size.0;
-                self.popup_height = configure.new_size.1;
-            }
-            self.popup_configured = true;
-            self.popup_redraw_requested = true;
-            hyprlog::internal::debug(
-                "POPUP",
-                &format!(
-                    "Popup configured {}x{}",
-                    self.popup_width, self.popup_height
-                ),
-            );
+        let Some(bar) = self.bar_for_surface(layer.wl_surface()) else {
             return;
-        }
-        // Main surface configured
+        };
         if configure.new_size.0 != 0 && configure.new_size.1 != 0 {
-            self.width = configure.new_size.0;
-            self.height = configure.new_size.1;
+            bar.width = configure.new_size.0;
+            bar.height = configure.new_size.1;
+        }
+        bar.configured = true;
+        // A new size (e.g. after a config reload) needs a fresh buffer
+        bar.redraw_requested = true;
+    }
+}
+
+impl PopupHandler for WaylandState {
+    fn configure(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        popup: &Popup,
+        config: PopupConfigure,
+    ) {
+        let Some(bar) = self.bar_for_surface(popup.wl_surface()) else {
+            return;
+        };
+        if config.width > 0 && config.height > 0 {
+            bar.popup_width = config.width as u32;
+            bar.popup_height = config.height as u32;
+        }
+        bar.popup_configured = true;
+        bar.popup_redraw_requested = true;
+        hyprlog::internal::debug(
+            "POPUP",
+            &format!(
+                "Popup configured {}x{} at {:?}",
+                bar.popup_width, bar.popup_height, config.position
+            ),
+        );
+    }
+
+    fn done(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, popup: &Popup) {
+        // The runner destroys it and tells the renderer
+        if let Some(bar) = self.bar_for_surface(popup.wl_surface()) {
+            hyprlog::internal::debug("POPUP", "Popup dismissed by the compositor");
+            bar.popup_dismissed = true;
         }
-        self.configured = true;
     }
 }
 
@@ -231,8 +294,11 @@ impl SeatHandler for WaylandState {
         seat: wl_seat::WlSeat,
         capability: Capability,
     ) {
-        if capability == Capability::Pointer && self.seat_state.get_pointer(qh, &seat).is_ok() {
+        if capability == Capability::Pointer
+            && let Ok(pointer) = self.seat_state.get_pointer(qh, &seat)
+        {
             hyprlog::internal::debug("WAYLAND", "Got pointer capability");
+            self.pointer = Some(pointer);
         }
     }
     fn remove_capability(
@@ -240,12 +306,53 @@ impl SeatHandler for WaylandState {
         _: &Connection,
         _: &QueueHandle<Self>,
         _: wl_seat::WlSeat,
-        _: Capability,
+        capability: Capability,
     ) {
+        if capability == Capability::Pointer {
+            self.pointer = None;
+        }
     }
     fn remove_seat(&mut self, _: &Connection, _: &QueueHandle<Self>, _: wl_seat::WlSeat) {}
 }
 
+impl Dispatch<WpFractionalScaleV1, wl_surface::WlSurface> for WaylandState {
+    fn event(
+        state: &mut Self,
+        _proxy: &WpFractionalScaleV1,
+        event: wp_fractional_scale_v1::Event,
+        surface: &wl_surface::WlSurface,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        if let wp_fractional_scale_v1::Event::PreferredScale { scale } = event
+            && let Some(bar) = state
+                .bars
+                .iter_mut()
+                .find(|b| b.layer.wl_surface() == surface)
+        {
+            // Sent in 120ths
+            bar.set_scale(scale as f64 / 120.0);
+        }
+    }
+}
+
+/// Bound along with `xdg_wm_base`; it has no events and bars have no decorations.
+impl Dispatch<ZxdgDecorationManagerV1, GlobalData> for WaylandState {
+    fn event(
+        _state: &mut Self,
+        _proxy: &ZxdgDecorationManagerV1,
+        _event: zxdg_decoration_manager_v1::Event,
+        _data: &GlobalData,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+delegate_noop!(WaylandState: WpFractionalScaleManagerV1);
+delegate_noop!(WaylandState: WpViewporter);
+delegate_noop!(WaylandState: ignore WpViewport);
+
 delegate_compositor!(WaylandState);
 delegate_output!(WaylandState);
 delegate_shm!(WaylandState);
@@ -253,6 +360,9 @@ delegate_seat!(WaylandState);
 delegate_pointer!(WaylandState);
 delegate_registry!(WaylandState);
 delegate_layer!(WaylandState);
+// `delegate_xdg_shell!` also wants a window handler; popups only need `xdg_wm_base`
+delegate_dispatch!(WaylandState: [XdgWmBase: GlobalData] => XdgShell);
+delegate_xdg_popup!(WaylandState);
 
 impl ProvidesRegistryState for WaylandState {
     fn registry(&mut self) -> &mut RegistryState {
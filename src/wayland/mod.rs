@@ -1,28 +1,41 @@
+pub mod autohide;
 pub mod blitter;
+pub mod buffers;
 pub mod handlers;
+pub mod shape_cache;
 pub mod state;
 pub mod text;
 
+use crate::config::{Edge, WindowConfig};
+use crate::renderer::WidgetSection;
 use anyhow::{Context, Result};
+use smithay_client_toolkit::reexports::protocols::xdg::shell::client::xdg_positioner::{
+    self, ConstraintAdjustment, Gravity,
+};
+use smithay_client_toolkit::seat::pointer::PointerData;
+use smithay_client_toolkit::shell::WaylandSurface;
 use smithay_client_toolkit::{
     compositor::CompositorState,
     output::OutputState,
-    reexports::client::{Connection, EventQueue, globals::registry_queue_init},
+    reexports::client::{
+        Connection, EventQueue, Proxy, globals::registry_queue_init,
+        protocol::wl_output::WlOutput,
+    },
     registry::RegistryState,
     seat::SeatState,
     shell::wlr_layer::{Anchor, Layer, LayerShell, LayerSurface},
-    shm::{Shm, slot::SlotPool},
+    shell::xdg::{XdgPositioner, XdgShell, popup::Popup},
+    shm::Shm,
 };
-use state::WaylandState;
+use smithay_client_toolkit::reexports::protocols::wp::fractional_scale::v1::client::wp_fractional_scale_manager_v1::WpFractionalScaleManagerV1;
+use smithay_client_toolkit::reexports::protocols::wp::viewporter::client::wp_viewporter::WpViewporter;
+use buffers::ShmBuffers;
+use state::{BarSurface, WaylandState};
 use text::TextRenderer;
 
-pub fn init(
-    height: u32,
-    anchor_bottom: bool,
-    monitor: Option<String>,
-    font_path: Option<String>,
-    font_size: f32,
-) -> Result<(WaylandState, EventQueue<WaylandState>, LayerSurface)> {
+/// Connects to the compositor and binds the globals. Bars are added with
+/// [`create_bar_surface`].
+pub fn init() -> Result<(WaylandState, EventQueue<WaylandState>)> {
     let conn = Connection::connect_to_env().context("Failed to connect to Wayland")?;
 
     let (globals, mut event_queue) =
@@ -34,14 +47,23 @@ pub fn init(
     let compositor_state =
         CompositorState::bind(&globals, &qh).context("Failed to bind compositor")?;
     let layer_shell = LayerShell::bind(&globals, &qh).context("Failed to bind layer shell")?;
+    let xdg_shell = XdgShell::bind(&globals, &qh).context("Failed to bind xdg shell")?;
     let shm = Shm::bind(&globals, &qh).context("Failed to bind shm")?;
     let output_state = OutputState::new(&globals, &qh);
     let seat_state = SeatState::new(&globals, &qh);
-
-    let pool = SlotPool::new(1920 * 1080 * 4, &shm).context("Failed to create Shm pool")?;
-
-    let text_renderer = TextRenderer::new(font_path.as_deref(), font_size)
-        .context("Failed to initialize text renderer")?;
+    let fractional_scale = match (
+        globals.bind::<WpFractionalScaleManagerV1, _, _>(&qh, 1..=1, ()),
+        globals.bind::<WpViewporter, _, _>(&qh, 1..=1, ()),
+    ) {
+        (Ok(manager), Ok(viewporter)) => Some((manager, viewporter)),
+        _ => {
+            hyprlog::internal::debug(
+                "WAYLAND",
+                "No fractional scale support, using integer buffer scale",
+            );
+            None
+        }
+    };
 
     let mut state = WaylandState {
         registry_state,
@@ -50,114 +72,283 @@ pub fn init(
         compositor_state,
         shm,
         layer_shell,
-        pool,
-        redraw_requested: true,
-        exit: false,
-        surface: None,
-        configured: false,
-        width: 0,
-        height: 0,
-        text_renderer,
-        cursor_x: 0.0,
-        cursor_y: 0.0,
-        input_events: Vec::new(),
-        // Popup state
-        popup_surface: None,
-        popup_layer: None,
-        popup_pool: None,
-        popup_configured: false,
-        popup_width: 0,
-        popup_height: 0,
-        popup_redraw_requested: false,
-        popup_input_events: Vec::new(),
+        xdg_shell,
+        pointer: None,
+        fractional_scale,
+        bars: Vec::new(),
+        outputs_changed: false,
     };
 
+    // Outputs and seats are announced during the roundtrip
     event_queue
         .roundtrip(&mut state)
         .context("Failed initial roundtrip")?;
 
-    let output = monitor.and_then(|monitor_name| {
-        state.output_state.outputs().find(|o| {
-            state
-                .output_state
-                .info(o)
-                .map(|info| info.name == Some(monitor_name.clone()))
-                .unwrap_or(false)
-        })
-    });
-
-    let surface = state.compositor_state.create_surface(&qh);
-    let layer_surface = state.layer_shell.create_layer_surface(
-        &qh,
+    Ok((state, event_queue))
+}
+
+/// The output named `name` (e.g. "DP-1"), if connected.
+pub fn find_output(state: &WaylandState, name: &str) -> Option<WlOutput> {
+    state.output_state.outputs().find(|o| {
+        state
+            .output_state
+            .info(o)
+            .is_some_and(|info| info.name.as_deref() == Some(name))
+    })
+}
+
+/// Creates the layer surface of a bar on `output` (`None`: compositor decides).
+/// It is drawn once the compositor has configured it.
+pub fn create_bar_surface(
+    state: &WaylandState,
+    qh: &QueueHandle<WaylandState>,
+    window: &WindowConfig,
+    output: Option<WlOutput>,
+    fonts: &[String],
+    font_size: f32,
+) -> Result<BarSurface> {
+    // Sized on the first frame, once the compositor has configured the width
+    let buffers = ShmBuffers::new(&state.shm, 0, 0)?;
+
+    let text_renderer =
+        TextRenderer::new(fonts, font_size).context("Failed to initialize text renderer")?;
+
+    let surface = state.compositor_state.create_surface(qh);
+    let layer = state.layer_shell.create_layer_surface(
+        qh,
         surface.clone(),
-        Layer::Top,
+        shell_layer(&window.layer),
         Some("hyprbar"),
         output.as_ref(),
     );
 
-    let anchor = if anchor_bottom {
-        Anchor::BOTTOM | Anchor::LEFT | Anchor::RIGHT
-    } else {
-        Anchor::TOP | Anchor::LEFT | Anchor::RIGHT
+    // The preferred scale arrives as an event; until then buffers are 1:1
+    let (fractional_scale, viewport) = match &state.fractional_scale {
+        Some((manager, viewporter)) => (
+            Some(manager.get_fractional_scale(&surface, qh, surface.clone())),
+            Some(viewporter.get_viewport(&surface, qh, ())),
+        ),
+        None => (None, None),
     };
-    layer_surface.set_anchor(anchor);
-    layer_surface.set_size(0, height);
-    layer_surface.set_exclusive_zone(height as i32);
+
+    configure_bar_surface(&layer, window, false);
     surface.commit();
 
-    state.surface = Some(surface);
+    Ok(BarSurface::new(
+        layer,
+        output,
+        buffers,
+        fractional_scale,
+        viewport,
+        text_renderer,
+        fonts,
+        font_size,
+    ))
+}
 
-    Ok((state, event_queue, layer_surface))
+/// Destroys a bar's popup, layer surface and `wl_surface`, in that order.
+pub fn destroy_bar_surface(mut bar: BarSurface) {
+    destroy_popup_surface(&mut bar);
+    if let Some(fractional_scale) = bar.fractional_scale.take() {
+        fractional_scale.destroy();
+    }
+    if let Some(viewport) = bar.viewport.take() {
+        viewport.destroy();
+    }
+    let surface = bar.layer.wl_surface().clone();
+    drop(bar.layer);
+    surface.destroy();
+}
+
+/// Applies the `[window]` placement: layer, anchor edge, thickness, length,
+/// margins and exclusive zone. A bar with a `width` is anchored to its edge
+/// only, which centers it; a `hidden` one shrinks to its autohide trigger
+/// strip. Takes effect with the next commit; the compositor answers with a
+/// configure.
+pub fn configure_bar_surface(layer_surface: &LayerSurface, window: &WindowConfig, hidden: bool) {
+    let (_, thickness) = window.calculate_dimensions();
+    let thickness = if hidden {
+        window.autohide_trigger.max(1)
+    } else {
+        thickness
+    };
+    let edge = window.edge();
+    let (edge_anchor, across) = match edge {
+        Edge::Top => (Anchor::TOP, Anchor::LEFT | Anchor::RIGHT),
+        Edge::Bottom => (Anchor::BOTTOM, Anchor::LEFT | Anchor::RIGHT),
+        Edge::Left => (Anchor::LEFT, Anchor::TOP | Anchor::BOTTOM),
+        Edge::Right => (Anchor::RIGHT, Anchor::TOP | Anchor::BOTTOM),
+    };
+    let anchor = if window.width == 0 {
+        edge_anchor | across
+    } else {
+        edge_anchor
+    };
+    let (width, height) = if edge.is_vertical() {
+        (thickness, window.width)
+    } else {
+        (window.width, thickness)
+    };
+    let (top, right, bottom, left) = window.margins();
+    layer_surface.set_layer(shell_layer(&window.layer));
+    layer_surface.set_anchor(anchor);
+    layer_surface.set_size(width, height);
+    layer_surface.set_margin(top, right, bottom, left);
+    layer_surface.set_exclusive_zone(window.exclusive_zone(thickness, hidden));
+}
+
+/// Unknown layers fall back to `top`.
+fn shell_layer(name: &str) -> Layer {
+    match name {
+        "background" => Layer::Background,
+        "bottom" => Layer::Bottom,
+        "overlay" => Layer::Overlay,
+        _ => Layer::Top,
+    }
 }
 
 use smithay_client_toolkit::reexports::client::QueueHandle;
+use std::os::fd::BorrowedFd;
+use std::time::Duration;
+
+/// Dispatches Wayland events, waiting at most `timeout` for new ones.
+/// `None` blocks until the compositor sends something.
+/// Readable `wake_fds` (e.g. file watchers) also end the wait; the caller drains them.
+pub fn dispatch_with_timeout(
+    event_queue: &mut EventQueue<WaylandState>,
+    state: &mut WaylandState,
+    timeout: Option<Duration>,
+    wake_fds: &[BorrowedFd<'_>],
+) -> Result<()> {
+    use nix::errno::Errno;
+    use nix::poll::{PollFd, PollFlags, PollTimeout, poll};
+
+    // Events may already be queued from a previous read
+    if event_queue.dispatch_pending(state)? > 0 {
+        return Ok(());
+    }
+
+    event_queue
+        .flush()
+        .context("Failed to flush Wayland connection")?;
+
+    if let Some(guard) = event_queue.prepare_read() {
+        let poll_timeout = match timeout {
+            // Round up so we never wake just before a deadline and spin
+            Some(t) => {
+                PollTimeout::try_from(t.as_micros().div_ceil(1000)).unwrap_or(PollTimeout::MAX)
+            }
+            None => PollTimeout::NONE,
+        };
 
-/// Creates a popup surface for displaying widget popups.
-/// Position is relative to screen, typically calculated from widget position.
+        let ready = {
+            let fd = guard.connection_fd();
+            let mut fds: Vec<PollFd> = std::iter::once(fd)
+                .chain(wake_fds.iter().copied())
+                .map(|fd| PollFd::new(fd, PollFlags::POLLIN))
+                .collect();
+            match poll(&mut fds, poll_timeout) {
+                // Only read the socket if it is the one that woke us
+                Ok(n) => n > 0 && fds[0].any().unwrap_or(false),
+                // Signals (SIGTERM handler) interrupt the wait, treat like a timeout
+                Err(Errno::EINTR) => false,
+                Err(e) => return Err(e).context("Failed to poll Wayland socket"),
+            }
+        };
+
+        if ready {
+            guard.read().context("Failed to read Wayland events")?;
+        }
+    }
+
+    event_queue
+        .dispatch_pending(state)
+        .context("Wayland dispatch failed")?;
+    Ok(())
+}
+
+/// Opens a widget's popup as an `xdg_popup` of the bar's layer surface,
+/// `width`x`height` logical pixels. It is anchored to the widget's `area`
+/// (logical pixels on the bar) and pushed `along` the bar and `away` from
+/// its `edge`; the compositor slides or flips it to keep it on screen. With
+/// `grab` it takes the pointer, so a click elsewhere dismisses it.
+#[allow(clippy::too_many_arguments)]
 pub fn create_popup_surface(
     state: &mut WaylandState,
+    bar: usize,
     qh: &QueueHandle<WaylandState>,
     width: u32,
     height: u32,
-    x: i32,
-    y: i32,
-    anchor_bottom: bool,
+    area: (i32, i32, i32, i32),
+    (along, away): (i32, i32),
+    edge: Edge,
+    section: WidgetSection,
+    grab: bool,
 ) -> Result<()> {
     // Destroy existing popup if any
-    destroy_popup_surface(state);
+    destroy_popup_surface(&mut state.bars[bar]);
 
-    // Create popup pool
-    let popup_pool = SlotPool::new((width * height * 4) as usize, &state.shm)
-        .context("Failed to create popup pool")?;
+    // Create popup buffers
+    let popup_buffers = ShmBuffers::new(
+        &state.shm,
+        state.bars[bar].to_physical(width),
+        state.bars[bar].to_physical(height),
+    )?;
 
     // Create popup surface
     let popup_wl_surface = state.compositor_state.create_surface(qh);
+    // Scaled like the bar it belongs to
+    let popup_viewport = match (&state.fractional_scale, &state.bars[bar].viewport) {
+        (Some((_, viewporter)), Some(_)) => {
+            Some(viewporter.get_viewport(&popup_wl_surface, qh, ()))
+        }
+        _ => None,
+    };
 
-    // Create layer surface for popup (Overlay layer = above everything)
-    let popup_layer = state.layer_shell.create_layer_surface(
-        qh,
-        popup_wl_surface.clone(),
-        Layer::Overlay,
-        Some("hyprbar-popup"),
-        None, // Same output as main surface
-    );
-
-    // Configure popup anchoring and margins
-    let anchor = if anchor_bottom {
-        Anchor::BOTTOM | Anchor::LEFT
-    } else {
-        Anchor::TOP | Anchor::LEFT
+    let positioner =
+        XdgPositioner::new(&state.xdg_shell).context("Failed to create popup positioner")?;
+    let (anchor, gravity) = popup_anchor(edge, section);
+    let (x, y, w, h) = area;
+    positioner.set_size(width as i32, height as i32);
+    positioner.set_anchor_rect(x, y, w.max(1), h.max(1));
+    positioner.set_anchor(anchor);
+    positioner.set_gravity(gravity);
+    let (dx, dy) = match edge {
+        Edge::Top => (along, away),
+        Edge::Bottom => (along, -away),
+        Edge::Left => (away, along),
+        Edge::Right => (-away, along),
     };
-    popup_layer.set_anchor(anchor);
-    popup_layer.set_size(width, height);
-    popup_layer.set_exclusive_zone(0); // Don't reserve space
-    popup_layer.set_margin(y, 0, 0, x); // top, right, bottom, left
+    positioner.set_offset(dx, dy);
+    positioner.set_constraint_adjustment(
+        ConstraintAdjustment::SlideX
+            | ConstraintAdjustment::SlideY
+            | ConstraintAdjustment::FlipX
+            | ConstraintAdjustment::FlipY,
+    );
 
-    popup_wl_surface.commit();
+    // The layer surface becomes the parent before the first commit
+    let popup = Popup::from_surface(None, &positioner, qh, popup_wl_surface, &state.xdg_shell)
+        .context("Failed to create popup")?;
+    state.bars[bar].layer.get_popup(popup.xdg_popup());
+    if grab {
+        // Only a recent click lets a popup grab the pointer
+        match state
+            .pointer
+            .as_ref()
+            .and_then(|pointer| pointer.data::<PointerData>())
+            .and_then(|data| Some((data.seat(), data.latest_button_serial()?)))
+        {
+            Some((seat, serial)) => popup.xdg_popup().grab(seat, serial),
+            None => hyprlog::internal::debug("POPUP", "No click to grab the pointer with"),
+        }
+    }
+    popup.wl_surface().commit();
 
-    state.popup_surface = Some(popup_wl_surface);
-    state.popup_layer = Some(popup_layer);
-    state.popup_pool = Some(popup_pool);
+    let state = &mut state.bars[bar];
+    state.popup = Some(popup);
+    state.popup_buffers = Some(popup_buffers);
+    state.popup_viewport = popup_viewport;
     state.popup_width = width;
     state.popup_height = height;
     state.popup_configured = false;
@@ -165,28 +356,54 @@ pub fn create_popup_surface(
 
     hyprlog::internal::debug(
         "POPUP",
-        &format!("Created popup {}x{} at ({}, {})", width, height, x, y),
+        &format!(
+            "Created popup {}x{} at {} along / {} from the {:?} edge",
+            width, height, along, away, edge
+        ),
     );
 
     Ok(())
 }
 
+/// Where a popup opens against its widget: past the side facing away from
+/// the bar's `edge`, growing from the widget's outer end for the outer
+/// sections and centered on it for the middle one.
+fn popup_anchor(edge: Edge, section: WidgetSection) -> (xdg_positioner::Anchor, Gravity) {
+    use xdg_positioner::Anchor as A;
+    match (edge, section) {
+        (Edge::Top, WidgetSection::Left) => (A::BottomLeft, Gravity::BottomRight),
+        (Edge::Top, WidgetSection::Center) => (A::Bottom, Gravity::Bottom),
+        (Edge::Top, WidgetSection::Right) => (A::BottomRight, Gravity::BottomLeft),
+        (Edge::Bottom, WidgetSection::Left) => (A::TopLeft, Gravity::TopRight),
+        (Edge::Bottom, WidgetSection::Center) => (A::Top, Gravity::Top),
+        (Edge::Bottom, WidgetSection::Right) => (A::TopRight, Gravity::TopLeft),
+        (Edge::Left, WidgetSection::Left) => (A::TopRight, Gravity::BottomRight),
+        (Edge::Left, WidgetSection::Center) => (A::Right, Gravity::Right),
+        (Edge::Left, WidgetSection::Right) => (A::BottomRight, Gravity::TopRight),
+        (Edge::Right, WidgetSection::Left) => (A::TopLeft, Gravity::BottomLeft),
+        (Edge::Right, WidgetSection::Center) => (A::Left, Gravity::Left),
+        (Edge::Right, WidgetSection::Right) => (A::BottomLeft, Gravity::TopLeft),
+    }
+}
+
 /// Destroys the current popup surface if it exists.
-pub fn destroy_popup_surface(state: &mut WaylandState) {
-    if state.popup_layer.is_some() {
+pub fn destroy_popup_surface(state: &mut BarSurface) {
+    if state.popup.is_some() {
         hyprlog::internal::debug("POPUP", "Destroying popup surface");
     }
 
-    // Drop layer surface first (this also destroys the underlying protocol object)
-    state.popup_layer = None;
-
-    // Then destroy the wl_surface
-    if let Some(surface) = state.popup_surface.take() {
-        surface.destroy();
+    if let Some(viewport) = state.popup_viewport.take() {
+        viewport.destroy();
     }
 
-    state.popup_pool = None;
+    // Destroys the xdg_popup, its xdg_surface and the wl_surface, in that order
+    state.popup = None;
+
+    state.popup_buffers = None;
+    // The next popup is a new surface and needs a complete first frame
+    state.popup_frame.invalidate();
     state.popup_configured = false;
+    state.popup_dismissed = false;
     state.popup_width = 0;
     state.popup_height = 0;
     state.popup_redraw_requested = false;
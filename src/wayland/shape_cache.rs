@@ -0,0 +1,127 @@
+use cosmic_text::CacheKey;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Segments kept per renderer; a bar shows a few dozen distinct ones, popups
+/// and animated widgets add more.
+const CAPACITY: usize = 1024;
+
+/// What a shaped segment looks like: text, style and the cells each grapheme
+/// covers (placement on the grid depends on them).
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ShapeKey {
+    pub text: String,
+    pub cells: Vec<usize>,
+    pub bold: bool,
+    pub italic: bool,
+    pub font_size_bits: u32,
+}
+
+/// A glyph placed on the cell grid, relative to the segment's first cell.
+pub struct ShapedGlyph {
+    pub cache_key: CacheKey,
+    pub x: i32,
+    pub y: i32,
+    /// Horizontal pixel range a glyph wider than its cells is clipped to.
+    pub clip: Option<(i32, i32)>,
+}
+
+pub struct ShapedSegment {
+    pub glyphs: Vec<ShapedGlyph>,
+    pub baseline: f32,
+}
+
+/// Least-recently-used cache of shaped segments, so unchanged text is only
+/// composited from cached glyph masks instead of being reshaped every frame.
+pub struct ShapeCache {
+    entries: HashMap<ShapeKey, (Arc<ShapedSegment>, u64)>,
+    tick: u64,
+    capacity: usize,
+}
+
+impl Default for ShapeCache {
+    fn default() -> Self {
+        Self::with_capacity(CAPACITY)
+    }
+}
+
+impl ShapeCache {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            entries: HashMap::new(),
+            tick: 0,
+            capacity: capacity.max(1),
+        }
+    }
+
+    pub fn get(&mut self, key: &ShapeKey) -> Option<Arc<ShapedSegment>> {
+        self.tick += 1;
+        let (segment, used) = self.entries.get_mut(key)?;
+        *used = self.tick;
+        Some(segment.clone())
+    }
+
+    /// Stores a freshly shaped segment, evicting the least recently used one
+    /// when full.
+    pub fn insert(&mut self, key: ShapeKey, segment: ShapedSegment) -> Arc<ShapedSegment> {
+        if self.entries.len() >= self.capacity
+            && !self.entries.contains_key(&key)
+            && let Some(oldest) = self
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, used))| *used)
+                .map(|(key, _)| key.clone())
+        {
+            self.entries.remove(&oldest);
+        }
+        self.tick += 1;
+        let segment = Arc::new(segment);
+        self.entries.insert(key, (segment.clone(), self.tick));
+        segment
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(text: &str) -> ShapeKey {
+        ShapeKey {
+            text: text.to_string(),
+            cells: vec![1; text.len()],
+            bold: false,
+            italic: false,
+            font_size_bits: 14f32.to_bits(),
+        }
+    }
+
+    fn segment() -> ShapedSegment {
+        ShapedSegment {
+            glyphs: Vec::new(),
+            baseline: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_lru_eviction() {
+        let mut cache = ShapeCache::with_capacity(2);
+        cache.insert(key("a"), segment());
+        cache.insert(key("b"), segment());
+        // Touch "a" so "b" is the least recently used
+        assert!(cache.get(&key("a")).is_some());
+        cache.insert(key("c"), segment());
+
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get(&key("a")).is_some());
+        assert!(cache.get(&key("b")).is_none());
+        assert!(cache.get(&key("c")).is_some());
+    }
+}
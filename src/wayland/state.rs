@@ -1,17 +1,29 @@
-use crate::wayland::blitter::blit_buffer_to_pixels;
+use crate::wayland::autohide::AutoHide;
+use crate::wayland::blitter::{FrameCache, opaque_rects};
+use crate::wayland::buffers::ShmBuffers;
 use anyhow::Context;
 use hyprink::config::Config;
 use ratatui::buffer::Buffer;
-use smithay_client_toolkit::reexports::client::protocol::wl_shm;
+use smithay_client_toolkit::reexports::client::protocol::wl_output::WlOutput;
+use smithay_client_toolkit::reexports::client::protocol::wl_pointer::WlPointer;
+use smithay_client_toolkit::reexports::protocols::wp::fractional_scale::v1::client::{
+    wp_fractional_scale_manager_v1::WpFractionalScaleManagerV1,
+    wp_fractional_scale_v1::WpFractionalScaleV1,
+};
+use smithay_client_toolkit::reexports::protocols::wp::viewporter::client::{
+    wp_viewport::WpViewport, wp_viewporter::WpViewporter,
+};
+use smithay_client_toolkit::shell::WaylandSurface;
 use smithay_client_toolkit::shell::wlr_layer::LayerSurface;
+use smithay_client_toolkit::shell::xdg::{XdgShell, popup::Popup};
 use smithay_client_toolkit::{
-    compositor::CompositorState,
+    compositor::{CompositorState, Region},
     output::OutputState,
     reexports::client::{QueueHandle, protocol::wl_surface::WlSurface},
     registry::RegistryState,
     seat::SeatState,
     shell::wlr_layer::LayerShell,
-    shm::{Shm, slot::SlotPool},
+    shm::Shm,
 };
 
 use crate::wayland::text::TextRenderer;
@@ -23,88 +35,358 @@ pub struct WaylandState {
     pub compositor_state: CompositorState,
     pub shm: Shm,
     pub layer_shell: LayerShell,
+    /// Widget popups are `xdg_popup`s of the bar's layer surface.
+    pub xdg_shell: XdgShell,
+    /// The seat's pointer; popups that grab it use its latest click.
+    pub pointer: Option<WlPointer>,
+    /// `wp_fractional_scale_manager_v1` and `wp_viewporter`, bound only if the
+    /// compositor has both. Without them bars use the integer scale of their output.
+    pub fractional_scale: Option<(WpFractionalScaleManagerV1, WpViewporter)>,
+
+    /// One per open bar, in the same order as the runner's bars.
+    pub bars: Vec<BarSurface>,
+    /// An output was added, removed or renamed; bars are re-matched to outputs.
+    pub outputs_changed: bool,
+}
+
+impl WaylandState {
+    pub fn new(_globals: &RegistryState, _qh: &QueueHandle<Self>) -> Self {
+        unimplemented!("Use helper initialization")
+    }
 
-    pub pool: SlotPool,
+    /// Names of the connected outputs, in the order they were announced.
+    pub fn output_names(&self) -> Vec<String> {
+        self.output_state
+            .outputs()
+            .filter_map(|o| self.output_state.info(&o)?.name)
+            .collect()
+    }
+
+    /// The bar that owns `surface`, either as its main surface or its popup.
+    pub fn bar_for_surface(&mut self, surface: &WlSurface) -> Option<&mut BarSurface> {
+        self.bars.iter_mut().find(|bar| {
+            bar.layer.wl_surface() == surface
+                || bar
+                    .popup
+                    .as_ref()
+                    .is_some_and(|p| p.wl_surface() == surface)
+        })
+    }
+}
+
+/// The layer surface of one bar and its popup.
+pub struct BarSurface {
+    pub layer: LayerSurface,
+    /// Output the bar was placed on; `None` lets the compositor pick.
+    pub output: Option<WlOutput>,
+
+    pub buffers: ShmBuffers,
     pub redraw_requested: bool,
+    /// A frame callback is outstanding; the compositor isn't ready for another buffer yet.
+    pub frame_pending: bool,
+    /// The compositor closed the surface; the runner drops the bar.
+    pub closed: bool,
 
-    // Application state
-    pub exit: bool,
-    pub surface: Option<WlSurface>,
     pub configured: bool,
+    /// An opaque region is set and has to be cleared when the option is turned off.
+    has_opaque_region: bool,
+    /// Logical size, as configured by the compositor.
     pub width: u32,
     pub height: u32,
+    /// Physical pixels per logical pixel. Buffers and glyphs are rendered at
+    /// `width * scale`, so text stays sharp on HiDPI outputs.
+    pub scale: f64,
+    /// Reports the preferred fractional scale; `viewport` maps the buffer
+    /// back to the logical size. `None` with the integer `buffer_scale` fallback.
+    pub fractional_scale: Option<WpFractionalScaleV1>,
+    pub viewport: Option<WpViewport>,
 
+    /// Rasterized at `font_size * scale`, so its cell metrics are in physical pixels.
     pub text_renderer: TextRenderer,
+    /// The last bar and popup frames, so unchanged cells aren't re-blitted.
+    pub frame: FrameCache,
+    pub popup_frame: FrameCache,
+    fonts: Vec<String>,
+    font_size: f32,
     pub input_events: Vec<crate::event::WidgetEvent>,
-    pub cursor_x: f64,
-    pub cursor_y: f64,
+    /// Last pointer position on the bar, in logical pixels.
+    pub cursor: (f64, f64),
+    /// The pointer is on the bar or its popup.
+    pub pointer_inside: bool,
+    /// `window.autohide` state; a hidden bar is only its trigger strip.
+    pub autohide: AutoHide,
 
     // Popup state
-    pub popup_surface: Option<WlSurface>,
-    pub popup_layer: Option<LayerSurface>,
-    pub popup_pool: Option<SlotPool>,
+    pub popup: Option<Popup>,
+    pub popup_buffers: Option<ShmBuffers>,
+    pub popup_viewport: Option<WpViewport>,
     pub popup_configured: bool,
+    /// The compositor dismissed the popup (e.g. a click outside a grabbing one).
+    pub popup_dismissed: bool,
     pub popup_width: u32,
     pub popup_height: u32,
     pub popup_redraw_requested: bool,
     pub popup_input_events: Vec<crate::event::WidgetEvent>,
+    /// Last pointer position on the popup, in logical pixels.
+    pub popup_cursor: (f64, f64),
 }
 
-impl WaylandState {
-    pub fn new(_globals: &RegistryState, _qh: &QueueHandle<Self>) -> Self {
-        unimplemented!("Use helper initialization")
+impl BarSurface {
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new(
+        layer: LayerSurface,
+        output: Option<WlOutput>,
+        buffers: ShmBuffers,
+        fractional_scale: Option<WpFractionalScaleV1>,
+        viewport: Option<WpViewport>,
+        text_renderer: TextRenderer,
+        fonts: &[String],
+        font_size: f32,
+    ) -> Self {
+        Self {
+            layer,
+            output,
+            buffers,
+            redraw_requested: true,
+            frame_pending: false,
+            closed: false,
+            configured: false,
+            has_opaque_region: false,
+            width: 0,
+            height: 0,
+            scale: 1.0,
+            fractional_scale,
+            viewport,
+            text_renderer,
+            frame: FrameCache::default(),
+            popup_frame: FrameCache::default(),
+            fonts: fonts.to_vec(),
+            font_size,
+            cursor: (0.0, 0.0),
+            pointer_inside: false,
+            autohide: AutoHide::default(),
+            input_events: Vec::new(),
+            // Popup state
+            popup: None,
+            popup_buffers: None,
+            popup_viewport: None,
+            popup_configured: false,
+            popup_dismissed: false,
+            popup_width: 0,
+            popup_height: 0,
+            popup_redraw_requested: false,
+            popup_input_events: Vec::new(),
+            popup_cursor: (0.0, 0.0),
+        }
+    }
+
+    /// Loads the fonts at `font_size` logical pixels for the current scale.
+    /// The old ones stay if the new ones fail to load.
+    pub fn set_font(&mut self, fonts: &[String], font_size: f32) -> anyhow::Result<()> {
+        self.text_renderer = TextRenderer::new(fonts, font_size * self.scale as f32)?;
+        self.invalidate_frames();
+        self.fonts = fonts.to_vec();
+        self.font_size = font_size;
+        Ok(())
+    }
+
+    /// Applies a new output scale: the font is re-rasterized and both the bar
+    /// and its popup are redrawn at the new resolution.
+    pub fn set_scale(&mut self, scale: f64) {
+        if scale <= 0.0 || scale == self.scale {
+            return;
+        }
+        hyprlog::internal::debug(
+            "WAYLAND",
+            &format!("Surface scale {} -> {}", self.scale, scale),
+        );
+        self.scale = scale;
+        let fonts = self.fonts.clone();
+        if let Err(e) = self.set_font(&fonts, self.font_size) {
+            hyprlog::internal::warn(
+                "FONT",
+                &format!("Failed to reload font for scale {}: {:#}", scale, e),
+            );
+        }
+        self.redraw_requested = true;
+        self.popup_redraw_requested = true;
+    }
+
+    /// Makes the next bar and popup frames repaint in full, for changes the
+    /// cells don't show (fonts, theme colors).
+    pub fn invalidate_frames(&mut self) {
+        self.frame.invalidate();
+        self.popup_frame.invalidate();
+    }
+
+    /// Converts logical pixels to physical ones.
+    pub fn to_physical(&self, logical: u32) -> u32 {
+        (logical as f64 * self.scale).round() as u32
+    }
+
+    /// Converts physical pixels (e.g. from cell metrics) to logical ones, rounding up.
+    pub fn to_logical(&self, physical: u32) -> u32 {
+        (physical as f64 / self.scale).ceil() as u32
+    }
+
+    /// Tells the compositor how the next buffer of `surface` maps to its
+    /// logical size: through the viewport, or with an integer buffer scale.
+    fn set_buffer_scale(
+        surface: &WlSurface,
+        viewport: Option<&WpViewport>,
+        scale: f64,
+        width: u32,
+        height: u32,
+    ) {
+        match viewport {
+            Some(viewport) => viewport.set_destination(width as i32, height as i32),
+            None => surface.set_buffer_scale(scale as i32),
+        }
     }
 
+    /// Declares the opaque parts of a `width`x`height` physical-pixel frame,
+    /// in logical coordinates rounded inwards.
+    fn set_opaque_region(
+        surface: &WlSurface,
+        compositor: &CompositorState,
+        scale: f64,
+        pixels: &[u8],
+        width: u32,
+        height: u32,
+    ) -> anyhow::Result<()> {
+        let region = Region::new(compositor).context("Failed to create opaque region")?;
+        let inner = |start: u32, len: u32| {
+            let first = (start as f64 / scale).ceil() as i32;
+            let end = ((start + len) as f64 / scale).floor() as i32;
+            (first, end - first)
+        };
+        for (x, y, w, h) in opaque_rects(pixels, width, height) {
+            let (x, w) = inner(x, w);
+            let (y, h) = inner(y, h);
+            if w > 0 && h > 0 {
+                region.add(x, y, w, h);
+            }
+        }
+        surface.set_opaque_region(Some(region.wl_region()));
+        Ok(())
+    }
+
+    /// Renders `buffer` and commits it. With `opaque_region` the fully opaque
+    /// parts of the frame are declared to the compositor.
     pub fn draw(
         &mut self,
-        _qh: &QueueHandle<Self>,
+        _qh: &QueueHandle<WaylandState>,
         buffer: &Buffer,
         config_ink: &Config,
         bg_color_hex: &str,
+        opaque_region: Option<&CompositorState>,
     ) -> anyhow::Result<()> {
-        let width = self.width;
-        let height = self.height;
-
-        if width == 0 || height == 0 {
+        if self.width == 0 || self.height == 0 {
             return Ok(());
         }
+        let width = self.to_physical(self.width);
+        let height = self.to_physical(self.height);
+
+        // Without a free buffer the frame is retried later; the kept cells
+        // still match what is on screen
+        let (wl_buffer, canvas) = match self.buffers.acquire(width, height) {
+            Ok(acquired) => acquired,
+            Err(e) => {
+                hyprlog::internal::warn("WAYLAND", &format!("Skipping frame: {:#}", e));
+                return Ok(());
+            }
+        };
 
-        let stride = width as i32 * 4;
-
-        // Create buffer
-        let (wl_buffer, canvas) = self
-            .pool
-            .create_buffer(
-                width as i32,
-                height as i32,
-                stride,
-                wl_shm::Format::Argb8888,
-            )
-            .context("Failed to create buffer")?;
-
-        // Blit
-        // Blit
-        blit_buffer_to_pixels(
+        // Only cells that changed since the last frame are blitted again
+        let damage = self.frame.update(
             buffer,
-            canvas,
             width,
             height,
             config_ink,
             &mut self.text_renderer,
             bg_color_hex,
         );
+        if damage.is_empty() {
+            self.redraw_requested = false;
+            return Ok(());
+        }
+
+        canvas.copy_from_slice(self.frame.pixels());
+
+        let surface = self.layer.wl_surface();
+        match opaque_region {
+            Some(compositor) => {
+                let pixels = self.frame.pixels();
+                Self::set_opaque_region(surface, compositor, self.scale, pixels, width, height)?;
+                self.has_opaque_region = true;
+            }
+            None if self.has_opaque_region => {
+                surface.set_opaque_region(None);
+                self.has_opaque_region = false;
+            }
+            None => {}
+        }
 
         // Attach and damage
-        if let Some(surface) = &self.surface {
-            // create_buffer returns (WlBuffer, &mut [u8])
-            // WlBuffer is a Proxy.
-            // surface.attach(Some(&wl_buffer), 0, 0);
+        Self::set_buffer_scale(
+            surface,
+            self.viewport.as_ref(),
+            self.scale,
+            self.width,
+            self.height,
+        );
+        wl_buffer
+            .attach_to(surface)
+            .context("Failed to attach buffer")?;
+        for (x, y, w, h) in damage {
+            surface.damage_buffer(x as i32, y as i32, w as i32, h as i32);
+        }
+        surface.frame(_qh, surface.clone());
+        surface.commit();
+        self.frame_pending = true;
 
-            surface.attach(Some(wl_buffer.wl_buffer()), 0, 0);
-            surface.damage_buffer(0, 0, width as i32, height as i32);
-            surface.frame(_qh, surface.clone());
-            surface.commit();
+        self.redraw_requested = false;
+        Ok(())
+    }
+
+    /// Commits a fully transparent frame: the trigger strip of a hidden bar
+    /// only has to catch the pointer. The next bar frame repaints in full.
+    pub fn draw_blank(&mut self, qh: &QueueHandle<WaylandState>) -> anyhow::Result<()> {
+        if self.width == 0 || self.height == 0 {
+            return Ok(());
         }
+        let width = self.to_physical(self.width);
+        let height = self.to_physical(self.height);
+
+        let (wl_buffer, canvas) = match self.buffers.acquire(width, height) {
+            Ok(acquired) => acquired,
+            Err(e) => {
+                hyprlog::internal::warn("WAYLAND", &format!("Skipping frame: {:#}", e));
+                return Ok(());
+            }
+        };
+        canvas.fill(0);
+        self.frame.invalidate();
+
+        let surface = self.layer.wl_surface();
+        if self.has_opaque_region {
+            surface.set_opaque_region(None);
+            self.has_opaque_region = false;
+        }
+        Self::set_buffer_scale(
+            surface,
+            self.viewport.as_ref(),
+            self.scale,
+            self.width,
+            self.height,
+        );
+        wl_buffer
+            .attach_to(surface)
+            .context("Failed to attach buffer")?;
+        surface.damage_buffer(0, 0, width as i32, height as i32);
+        surface.frame(qh, surface.clone());
+        surface.commit();
+        self.frame_pending = true;
 
         self.redraw_requested = false;
         Ok(())
@@ -112,46 +394,64 @@ impl WaylandState {
 
     pub fn draw_popup(
         &mut self,
-        _qh: &QueueHandle<Self>,
+        _qh: &QueueHandle<WaylandState>,
         buffer: &Buffer,
         config_ink: &Config,
         bg_color_hex: &str,
+        opaque_region: Option<&CompositorState>,
     ) -> anyhow::Result<()> {
-        let width = self.popup_width;
-        let height = self.popup_height;
-
-        if width == 0 || height == 0 {
+        if self.popup_width == 0 || self.popup_height == 0 {
             return Ok(());
         }
+        let width = self.to_physical(self.popup_width);
+        let height = self.to_physical(self.popup_height);
 
-        let Some(pool) = &mut self.popup_pool else {
+        let Some(buffers) = &mut self.popup_buffers else {
             return Ok(());
         };
+        let (wl_buffer, canvas) = match buffers.acquire(width, height) {
+            Ok(acquired) => acquired,
+            Err(e) => {
+                hyprlog::internal::warn("POPUP", &format!("Skipping frame: {:#}", e));
+                return Ok(());
+            }
+        };
 
-        let stride = width as i32 * 4;
-
-        let (wl_buffer, canvas) = pool
-            .create_buffer(
-                width as i32,
-                height as i32,
-                stride,
-                wl_shm::Format::Argb8888,
-            )
-            .context("Failed to create popup buffer")?;
-
-        blit_buffer_to_pixels(
+        let damage = self.popup_frame.update(
             buffer,
-            canvas,
             width,
             height,
             config_ink,
             &mut self.text_renderer,
             bg_color_hex,
         );
+        if damage.is_empty() {
+            self.popup_redraw_requested = false;
+            return Ok(());
+        }
+
+        canvas.copy_from_slice(self.popup_frame.pixels());
 
-        if let Some(surface) = &self.popup_surface {
-            surface.attach(Some(wl_buffer.wl_buffer()), 0, 0);
-            surface.damage_buffer(0, 0, width as i32, height as i32);
+        if let Some(popup) = &self.popup {
+            let surface = popup.wl_surface();
+            // A new popup surface starts without one, so there is nothing to clear
+            if let Some(compositor) = opaque_region {
+                let pixels = self.popup_frame.pixels();
+                Self::set_opaque_region(surface, compositor, self.scale, pixels, width, height)?;
+            }
+            Self::set_buffer_scale(
+                surface,
+                self.popup_viewport.as_ref(),
+                self.scale,
+                self.popup_width,
+                self.popup_height,
+            );
+            wl_buffer
+                .attach_to(surface)
+                .context("Failed to attach popup buffer")?;
+            for (x, y, w, h) in damage {
+                surface.damage_buffer(x as i32, y as i32, w as i32, h as i32);
+            }
             surface.frame(_qh, surface.clone());
             surface.commit();
         }
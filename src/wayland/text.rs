@@ -1,8 +1,13 @@
+use crate::wayland::shape_cache::ShapeCache;
 use anyhow::Result;
 use cosmic_text::fontdb::{Database, Source};
-use cosmic_text::{Attrs, Buffer, Family, FontSystem, Metrics, Shaping, SwashCache};
+use cosmic_text::{
+    Attrs, Buffer, Fallback, Family, FontSystem, Metrics, Shaping, Style, SwashCache, Weight,
+};
 use std::path::PathBuf;
 use std::process::Command;
+use std::sync::{Arc, Mutex};
+use unicode_script::Script;
 
 pub struct TextRenderer {
     pub font_system: FontSystem,
@@ -11,60 +16,89 @@ pub struct TextRenderer {
     pub char_height: usize,
     pub font_family: String,
     pub font_size: f32,
+    /// Whether the primary family has a real bold / italic face. Without one
+    /// the blitter synthesizes the style.
+    pub has_bold: bool,
+    pub has_italic: bool,
+    /// The primary family is strictly monospace, so whole runs can be shaped
+    /// (keeping ligatures) without drifting off the cell grid.
+    pub monospace: bool,
+    /// Reused for shaping, with the renderer's metrics.
+    pub shape_buffer: Buffer,
+    pub shape_cache: ShapeCache,
 }
 
 impl TextRenderer {
-    pub fn new(font_path: Option<&str>, font_size: f32) -> Result<Self> {
+    /// Loads `fonts` (family names or font files) in fallback order: glyphs
+    /// missing from the first font are taken from the next one that has them,
+    /// e.g. `["JetBrains Mono", "Symbols Nerd Font", "Noto Color Emoji"]`.
+    /// Without fonts the system monospace font is used.
+    pub fn new(fonts: &[String], font_size: f32) -> Result<Self> {
         let swash_cache = SwashCache::new();
 
-        // Store the requested family name (or default to "Monospace")
-        let font_family = font_path.unwrap_or("Monospace").to_string();
-
-        // 1. Determine which font to load
-        let font_to_load = if let Some(path_str) = font_path {
-            hyprlog::internal::debug("FONT", &format!("Requesting user font: {}", path_str));
-            let path = PathBuf::from(path_str);
-            if path.exists() {
-                Some(path)
-            } else if let Some(resolved) = resolve_font_via_fc_match(path_str) {
-                hyprlog::internal::debug(
+        // 1. Load every font of the chain. The database starts EMPTY to avoid
+        // scanning system fonts (slow!)
+        let mut db = Database::new();
+        let mut loaded: Vec<PathBuf> = Vec::new();
+        let mut families: Vec<String> = Vec::new();
+
+        if fonts.is_empty() {
+            hyprlog::internal::debug("FONT", "No font specified, resolving system monospace");
+        }
+        for font in fonts {
+            hyprlog::internal::debug("FONT", &format!("Requesting user font: {}", font));
+            match resolve_font(font) {
+                Some(path) => load_font(&mut db, path, &mut loaded, &mut families),
+                None => hyprlog::internal::warn(
                     "FONT",
-                    &format!("Resolved '{}' to '{}'", path_str, resolved),
-                );
-                Some(PathBuf::from(resolved))
-            } else {
+                    &format!("Could not find font '{}', skipping it.", font),
+                ),
+            }
+        }
+        if families.is_empty() {
+            if !fonts.is_empty() {
                 hyprlog::internal::warn(
                     "FONT",
-                    &format!(
-                        "Could not find font '{}', falling back to defaults.",
-                        path_str
-                    ),
+                    "No configured font loaded, falling back to defaults.",
                 );
-                // Try to resolve monospace as fallback
-                resolve_font_via_fc_match("monospace").map(PathBuf::from)
             }
-        } else {
-            // No font specified - resolve system monospace
-            hyprlog::internal::debug("FONT", "No font specified, resolving system monospace");
-            resolve_font_via_fc_match("monospace").map(PathBuf::from)
-        };
+            if let Some(path) = resolve_font_via_fc_match("monospace") {
+                load_font(&mut db, PathBuf::from(path), &mut loaded, &mut families);
+            }
+        }
+        if families.is_empty() {
+            hyprlog::internal::warn("FONT", "No specific font loaded. Text might not render.");
+        }
 
-        // 2. Initialize FontSystem with EMPTY database to avoid scanning system fonts (slow!)
-        let mut db = Database::new();
+        // Shaping asks for the first family; the rest are the fallback chain
+        let font_family = families
+            .first()
+            .cloned()
+            .unwrap_or_else(|| "Monospace".to_string());
 
-        // Load the specific font file if found
-        if let Some(path) = font_to_load {
-            if let Ok(data) = std::fs::read(&path) {
-                db.load_font_source(Source::Binary(std::sync::Arc::new(data)));
-                hyprlog::internal::debug("FONT", &format!("Loaded font file: {:?}", path));
-            } else {
-                hyprlog::internal::warn("FONT", &format!("Failed to read font file: {:?}", path));
+        // 2. Bold and italic faces of the primary family, when it has them
+        for style in [":weight=bold", ":slant=italic", ":weight=bold:slant=italic"] {
+            if let Some(path) = resolve_font_via_fc_match(&format!("{}{}", font_family, style)) {
+                load_variant(&mut db, PathBuf::from(path), &font_family, &mut loaded);
             }
-        } else {
-            hyprlog::internal::warn("FONT", "No specific font loaded. Text might not render.");
         }
+        let primary_faces = || {
+            db.faces().filter(|face| {
+                face.families
+                    .first()
+                    .is_some_and(|(family, _)| *family == font_family)
+            })
+        };
+        let has_bold = primary_faces().any(|face| face.weight >= Weight::SEMIBOLD);
+        let has_italic = primary_faces().any(|face| face.style != Style::Normal);
+        let monospace = primary_faces().all(|face| face.monospaced);
 
-        let mut font_system = FontSystem::new_with_locale_and_db("en-US".into(), db);
+        let fallback = ChainFallback {
+            families: families.iter().skip(1).map(|f| intern(f)).collect(),
+        };
+
+        let mut font_system =
+            FontSystem::new_with_locale_and_db_and_fallback("en-US".into(), db, fallback);
 
         // 3. Setup Metrics (Fixed Grid)
         let line_height = font_size * 1.2;
@@ -72,9 +106,7 @@ impl TextRenderer {
         // Create a dummy buffer to measure 'M' width for grid size
         let mut buffer = Buffer::new(&mut font_system, Metrics::new(font_size, line_height));
 
-        // We set a default family to ensure we measure something reasonable.
-        // Even if we loaded a custom font, we usually need to specify it by name
-        // We use the requested family name here.
+        // Measured with the first font of the chain, which sets the cell grid
         buffer.set_text(
             &mut font_system,
             "M",
@@ -121,10 +153,113 @@ impl TextRenderer {
             char_height,
             font_family,
             font_size,
+            has_bold,
+            has_italic,
+            monospace,
+            shape_buffer: buffer,
+            shape_cache: ShapeCache::default(),
         })
     }
 }
 
+/// A font file as given, or the file fontconfig picks for a family name.
+fn resolve_font(font: &str) -> Option<PathBuf> {
+    let path = PathBuf::from(font);
+    if path.exists() {
+        return Some(path);
+    }
+    let resolved = resolve_font_via_fc_match(font)?;
+    hyprlog::internal::debug("FONT", &format!("Resolved '{}' to '{}'", font, resolved));
+    Some(PathBuf::from(resolved))
+}
+
+/// Loads a font file once and records the families it provides, in order.
+fn load_font(
+    db: &mut Database,
+    path: PathBuf,
+    loaded: &mut Vec<PathBuf>,
+    families: &mut Vec<String>,
+) {
+    // Different names often resolve to the same fallback file
+    if loaded.contains(&path) {
+        return;
+    }
+    let data = match std::fs::read(&path) {
+        Ok(data) => data,
+        Err(e) => {
+            hyprlog::internal::warn(
+                "FONT",
+                &format!("Failed to read font file {:?}: {}", path, e),
+            );
+            return;
+        }
+    };
+    for id in db.load_font_source(Source::Binary(Arc::new(data))) {
+        if let Some((family, _)) = db.face(id).and_then(|face| face.families.first())
+            && !families.contains(family)
+        {
+            families.push(family.clone());
+        }
+    }
+    hyprlog::internal::debug("FONT", &format!("Loaded font file: {:?}", path));
+    loaded.push(path);
+}
+
+/// Loads a styled face of `family`. fontconfig answers with the closest match,
+/// which may be the regular face or another family; those are dropped again.
+fn load_variant(db: &mut Database, path: PathBuf, family: &str, loaded: &mut Vec<PathBuf>) {
+    if loaded.contains(&path) {
+        return;
+    }
+    let Ok(data) = std::fs::read(&path) else {
+        return;
+    };
+    for id in db.load_font_source(Source::Binary(Arc::new(data))) {
+        let same_family = db
+            .face(id)
+            .and_then(|face| face.families.first())
+            .is_some_and(|(name, _)| name == family);
+        if !same_family {
+            db.remove_face(id);
+        }
+    }
+    hyprlog::internal::debug("FONT", &format!("Loaded styled face: {:?}", path));
+    loaded.push(path);
+}
+
+/// Falls back through the configured fonts in order. Only those are in the
+/// database, so platform fallback lists would never match anyway.
+struct ChainFallback {
+    families: Vec<&'static str>,
+}
+
+impl Fallback for ChainFallback {
+    fn common_fallback(&self) -> &[&'static str] {
+        &self.families
+    }
+
+    fn forbidden_fallback(&self) -> &[&'static str] {
+        &[]
+    }
+
+    fn script_fallback(&self, _script: Script, _locale: &str) -> &[&'static str] {
+        &[]
+    }
+}
+
+/// cosmic-text wants `'static` family names. Each distinct name is leaked
+/// once, so recreating the renderer (reloads, scale changes) doesn't grow memory.
+fn intern(name: &str) -> &'static str {
+    static NAMES: Mutex<Vec<&'static str>> = Mutex::new(Vec::new());
+    let mut names = NAMES.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(interned) = names.iter().find(|n| **n == name) {
+        return interned;
+    }
+    let interned: &'static str = Box::leak(name.to_owned().into_boxed_str());
+    names.push(interned);
+    interned
+}
+
 fn resolve_font_via_fc_match(font_name: &str) -> Option<String> {
     // Run: fc-match --format=%{file} "font_name"
     match Command::new("fc-match")
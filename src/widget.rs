@@ -1,6 +1,8 @@
+use crate::config::ProviderKind;
 use crate::state::BarState;
 use ratatui::buffer::Buffer;
 use ratatui::layout::Rect;
+use std::time::Duration;
 
 #[derive(Debug, Clone, Copy)]
 pub struct PopupRequest {
@@ -41,15 +43,59 @@ pub trait Widget: Send + Sync {
     fn render(&mut self, area: Rect, buf: &mut Buffer, state: &BarState, dt: std::time::Duration);
     fn update(&mut self, _dt: std::time::Duration, _state: &BarState) {}
     fn width(&self, state: &BarState) -> u16;
+    /// Rows the widget takes up in a vertical (left/right anchored) bar.
+    fn height(&self, _state: &BarState) -> u16 {
+        1
+    }
     fn set_instance_config(&mut self, _name: String) {}
     fn handle_event(&mut self, _event: crate::event::WidgetEvent) {}
+    /// Time until the widget next needs `update`/`render`, measured from the last frame.
+    /// `Some(Duration::ZERO)` keeps the frame loop running (animations),
+    /// `None` means the widget only changes in response to events.
+    /// Defaults to once per second so simple timer-based widgets keep ticking.
+    fn next_update(&self) -> Option<Duration> {
+        Some(Duration::from_secs(1))
+    }
+    /// Returns true if the widget changed outside of `update` (e.g. from a background task)
+    /// and wants a redraw as soon as possible.
+    fn needs_redraw(&self) -> bool {
+        false
+    }
     fn popup_request(&self) -> Option<PopupRequest> {
         None
     }
     fn render_popup(&mut self, _area: Rect, _buf: &mut Buffer, _state: &BarState) {}
+    /// Pointer input on the widget's popup, in cells from the popup's corner.
+    fn handle_popup_event(&mut self, _event: crate::event::WidgetEvent) {}
 }
 
 /// A provider that creates widgets by name.
 pub trait WidgetProvider {
-    fn create_widget(&self, name: &str) -> Option<Box<dyn Widget>>;
+    /// Creates `name`, along with the kind of provider that made it.
+    fn create_widget(&self, name: &str) -> Option<(Box<dyn Widget>, ProviderKind)>;
+}
+
+/// Chains several providers; they are tried in order and the first one that
+/// knows the name wins.
+#[derive(Default)]
+pub struct CompositeProvider<'a> {
+    providers: Vec<&'a dyn WidgetProvider>,
+}
+
+impl<'a> CompositeProvider<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, provider: &'a dyn WidgetProvider) {
+        self.providers.push(provider);
+    }
+}
+
+impl WidgetProvider for CompositeProvider<'_> {
+    fn create_widget(&self, name: &str) -> Option<(Box<dyn Widget>, ProviderKind)> {
+        self.providers
+            .iter()
+            .find_map(|provider| provider.create_widget(name))
+    }
 }